@@ -0,0 +1,98 @@
+/*!
+Comparing the throughput of logging with and without a background thread.
+
+Records are written to a pipe that's slow to write to, like a busy terminal,
+and to one that discards them. The time spent in the logging thread and the
+time until every record was written are measured separately. Run this
+benchmark with the `background` feature:
+
+```no_run,shell
+$ cargo bench --bench background --features background
+```
+*/
+
+extern crate log;
+extern crate env_logger;
+
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::{Level, Log, Record};
+use env_logger::{Builder, Target};
+use env_logger::fmt::Overflow;
+
+const RECORDS: u32 = 2000;
+const RUNS: u32 = 5;
+
+/// A pipe that takes a little while to write each record.
+struct SlowPipe;
+
+impl Write for SlowPipe {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        thread::sleep(Duration::from_micros(50));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The fastest times over a few runs to log each record, and until each
+/// record was written.
+fn bench<P, W>(pipe: P, background: bool) -> (Duration, Duration)
+where
+    P: Fn() -> W,
+    W: Write + Send + 'static,
+{
+    let runs: Vec<_> = (0..RUNS).map(|_| {
+        let mut builder = Builder::new();
+
+        builder
+            .parse("info")
+            .target(Target::Pipe(Box::new(pipe())));
+
+        if background {
+            builder.background(RECORDS as usize, Overflow::Block);
+        }
+
+        let logger = builder.build();
+
+        let start = Instant::now();
+
+        for i in 0..RECORDS {
+            logger.log(&Record::builder()
+                .level(Level::Info)
+                .target("background")
+                .args(format_args!("record {}", i))
+                .build());
+        }
+
+        let logged = start.elapsed();
+
+        logger.flush();
+
+        let written = start.elapsed();
+
+        (logged / RECORDS, written / RECORDS)
+    }).collect();
+
+    let logged = runs.iter().map(|run| run.0).min().expect("there's at least one run");
+    let written = runs.iter().map(|run| run.1).min().expect("there's at least one run");
+
+    (logged, written)
+}
+
+fn report(name: &str, background: bool, (logged, written): (Duration, Duration)) {
+    let mode = if background { "background" } else { "synchronous" };
+
+    println!("{}, {}: {:?} per record logged, {:?} per record written", name, mode, logged, written);
+}
+
+fn main() {
+    for &background in &[false, true] {
+        report("slow pipe", background, bench(|| SlowPipe, background));
+        report("discarding pipe", background, bench(io::sink, background));
+    }
+}
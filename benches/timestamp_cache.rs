@@ -0,0 +1,59 @@
+/*!
+Comparing the time spent formatting records with and without reusing
+formatted timestamps.
+
+Records are written with the default format to a pipe that discards them, so
+most of the time is spent formatting. Run this benchmark with:
+
+```no_run,shell
+$ cargo bench --bench timestamp_cache
+```
+*/
+
+extern crate log;
+extern crate env_logger;
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use log::{Level, Log, Record};
+use env_logger::{Builder, Logger, Target};
+
+const RECORDS: u32 = 200_000;
+const RUNS: u32 = 5;
+
+fn logger(refresh: Option<Duration>) -> Logger {
+    Builder::new()
+        .parse("info")
+        .target(Target::Pipe(Box::new(io::sink())))
+        .format_timestamp_cache(refresh)
+        .build()
+}
+
+/// The fastest time to log a record over a few runs.
+fn bench(logger: &Logger) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+
+            for i in 0..RECORDS {
+                logger.log(&Record::builder()
+                    .level(Level::Info)
+                    .target("timestamp_cache")
+                    .args(format_args!("record {}", i))
+                    .build());
+            }
+
+            start.elapsed() / RECORDS
+        })
+        .min()
+        .expect("there's at least one run")
+}
+
+fn main() {
+    let uncached = bench(&logger(None));
+    let cached = bench(&logger(Some(Duration::from_secs(1))));
+
+    println!("fresh timestamps:  {:?} per record", uncached);
+    println!("cached timestamps: {:?} per record", cached);
+}
@@ -0,0 +1,288 @@
+//! Capturing log records in tests.
+//!
+//! A [`CaptureLogger`] filters and formats records like a [`Logger`] built
+//! from the same [`Builder`], but keeps them in memory instead of printing
+//! them, so tests can check what was logged.
+//!
+//! This module is only available with the `test-util` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! #[macro_use] extern crate log;
+//! extern crate env_logger;
+//!
+//! use log::Level;
+//! use env_logger::Builder;
+//! use env_logger::capture::CaptureLogger;
+//!
+//! # fn main() {
+//! let logger = CaptureLogger::new(Builder::new().parse("info")).try_init().unwrap();
+//!
+//! warn!("disk is {}% full", 95);
+//! debug!("not captured");
+//!
+//! assert!(logger.contains(Level::Warn, "disk is 95% full"));
+//! assert_eq!(1, logger.records().len());
+//! # }
+//! ```
+//!
+//! [`CaptureLogger`]: struct.CaptureLogger.html
+//! [`Logger`]: ../struct.Logger.html
+//! [`Builder`]: ../struct.Builder.html
+
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+use std::sync::atomic::Ordering;
+use std::thread::{self, ThreadId};
+
+use log::{self, Log, Level, Metadata, Record, SetLoggerError};
+
+use {Builder, Logger};
+
+/// A logger that keeps the records it's given instead of printing them.
+///
+/// Records are filtered and formatted by the builder's filter and format,
+/// so a captured line is the same as the one the [`Logger`] would print.
+/// Styles are turned off, so lines don't contain terminal escape codes.
+///
+/// A `CaptureLogger` can be installed as the global logger with
+/// [`try_init`], or records can be passed to it directly through its [`Log`]
+/// implementation. There's only one global logger per process, and tests
+/// in the same binary run on their own threads, so use [`thread_records`]
+/// to only see the records logged by the current test.
+///
+/// [`Logger`]: ../struct.Logger.html
+/// [`try_init`]: #method.try_init
+/// [`Log`]: https://docs.rs/log/0.4/log/trait.Log.html
+/// [`thread_records`]: #method.thread_records
+pub struct CaptureLogger {
+    logger: Logger,
+    records: Mutex<Vec<CapturedRecord>>,
+}
+
+/// A record kept by a [`CaptureLogger`].
+///
+/// [`CaptureLogger`]: struct.CaptureLogger.html
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CapturedRecord {
+    level: Level,
+    target: String,
+    message: String,
+    line: String,
+    thread: ThreadId,
+}
+
+impl CaptureLogger {
+    /// Build a capture logger with the filter and format of `builder`.
+    ///
+    /// The builder's target isn't written to. Captured lines never have
+    /// escape codes for styles, whatever the builder's style choice is.
+    pub fn new(builder: &mut Builder) -> CaptureLogger {
+        CaptureLogger {
+            logger: builder.try_build_styled(false).expect("failed to build the logger"),
+            records: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Install the capture logger as the global logger.
+    ///
+    /// The installed logger is returned so the records it captures can be
+    /// checked.
+    ///
+    /// # Errors
+    ///
+    /// This function will fail if another global logger has already been
+    /// installed, including another capture logger.
+    pub fn try_init(self) -> Result<&'static CaptureLogger, SetLoggerError> {
+        let logger: &'static CaptureLogger = Box::leak(Box::new(self));
+
+        log::set_logger(logger)?;
+
+        logger.logger.installed.store(true, Ordering::SeqCst);
+        log::set_max_level(logger.logger.filter());
+
+        Ok(logger)
+    }
+
+    /// Get every record captured so far, in the order they were logged.
+    pub fn records(&self) -> Vec<CapturedRecord> {
+        self.lock().clone()
+    }
+
+    /// Get the records captured so far that were logged on the current
+    /// thread.
+    pub fn thread_records(&self) -> Vec<CapturedRecord> {
+        let current = thread::current().id();
+
+        self.lock().iter().filter(|record| record.thread == current).cloned().collect()
+    }
+
+    /// Get the formatted lines of every record captured so far.
+    pub fn lines(&self) -> Vec<String> {
+        self.lock().iter().map(|record| record.line.clone()).collect()
+    }
+
+    /// Whether or not a record at `level` whose message contains `message`
+    /// has been captured.
+    pub fn contains(&self, level: Level, message: &str) -> bool {
+        self.lock().iter().any(|record| record.level == level && record.message.contains(message))
+    }
+
+    /// Forget every record captured so far.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> MutexGuard<Vec<CapturedRecord>> {
+        // A panicking test doesn't leave the records in an invalid state
+        self.records.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Log for CaptureLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.logger.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.logger.matches(record) {
+            return;
+        }
+
+        let line = match self.logger.format_to_string(record) {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+
+        let captured = CapturedRecord {
+            level: record.level(),
+            target: record.target().to_owned(),
+            message: record.args().to_string(),
+            line: line,
+            thread: thread::current().id(),
+        };
+
+        self.lock().push(captured);
+    }
+
+    fn flush(&self) {}
+}
+
+impl CapturedRecord {
+    /// The level of the record.
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    /// The target of the record.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// The message of the record, without any formatting.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The record as it was formatted, including the trailing newline if
+    /// the format writes one.
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    /// The thread the record was logged on.
+    pub fn thread(&self) -> ThreadId {
+        self.thread
+    }
+}
+
+impl fmt::Debug for CaptureLogger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CaptureLogger")
+            .field("logger", &self.logger)
+            .field("records", &self.lock().len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write;
+    use log::LevelFilter;
+    use WriteStyle;
+
+    fn log(logger: &CaptureLogger, level: Level, target: &str, message: &str) {
+        logger.log(&Record::builder()
+            .level(level)
+            .target(target)
+            .args(format_args!("{}", message))
+            .build());
+    }
+
+    #[test]
+    fn capture_filtered_and_formatted() {
+        let logger = CaptureLogger::new(Builder::new()
+            .filter(None, LevelFilter::Info)
+            .filter(Some("noisy"), LevelFilter::Error)
+            .format(|buf, record| writeln!(buf, "{} {}: {}", record.level(), record.target(), record.args())));
+
+        log(&logger, Level::Info, "app", "started");
+        log(&logger, Level::Debug, "app", "not captured");
+        log(&logger, Level::Warn, "noisy", "not captured");
+        log(&logger, Level::Error, "noisy", "failed");
+
+        assert_eq!(vec!["INFO app: started\n", "ERROR noisy: failed\n"], logger.lines());
+
+        let records = logger.thread_records();
+
+        assert_eq!(2, records.len());
+        assert_eq!(Level::Error, records[1].level());
+        assert_eq!("noisy", records[1].target());
+        assert_eq!("failed", records[1].message());
+
+        assert!(logger.contains(Level::Info, "start"));
+        assert!(!logger.contains(Level::Warn, "start"));
+
+        logger.clear();
+
+        assert!(logger.records().is_empty());
+    }
+
+    #[test]
+    fn capture_leaves_builder_style() {
+        let mut builder = Builder::new();
+        builder.write_style(WriteStyle::Always);
+
+        let logger = CaptureLogger::new(builder.format(|buf, record| {
+            let mut style = buf.style();
+            style.set_bold(true);
+
+            writeln!(buf, "{}", style.value(record.args()))
+        }));
+
+        log(&logger, Level::Error, "app", "failed");
+
+        assert_eq!(vec!["failed\n"], logger.lines());
+        assert_eq!(WriteStyle::Always, builder.writer.get_write_style());
+    }
+
+    #[test]
+    fn capture_threads() {
+        let logger = CaptureLogger::new(Builder::new().filter(None, LevelFilter::Info));
+
+        log(&logger, Level::Info, "app", "this thread");
+
+        thread::scope(|scope| {
+            scope.spawn(|| log(&logger, Level::Info, "app", "another thread"));
+        });
+
+        let messages: Vec<_> = logger.records().iter().map(|record| record.message().to_owned()).collect();
+        assert_eq!(vec!["this thread", "another thread"], messages);
+
+        let messages: Vec<_> = logger.thread_records().iter().map(|record| record.message().to_owned()).collect();
+        assert_eq!(vec!["this thread"], messages);
+    }
+}
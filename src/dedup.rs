@@ -0,0 +1,201 @@
+//! Collapsing consecutive duplicate log records.
+//!
+//! Records are duplicates if they have the same level, target and message.
+//! Duplicates aren't printed. Instead, a summary record that says how many
+//! times the last message was repeated is printed just before the next
+//! record that's different, or when the logger is flushed.
+//!
+//! The summary and the different record are printed without holding the
+//! lock, so formats can log records of their own. That means that when
+//! several threads log at once, another thread's record can be printed
+//! between the summary and the record that caused it, or before the summary.
+
+use std::fmt::Write;
+use std::sync::Mutex;
+
+use log::{Level, Record};
+
+/// The last record printed by a logger and how many times it's been repeated.
+pub(crate) struct Dedup {
+    last: Mutex<Option<Last>>,
+}
+
+struct Last {
+    level: Level,
+    target: String,
+    message: String,
+    repeated: usize,
+}
+
+/// A summary of a record that was repeated, waiting to be printed.
+struct Repeated {
+    level: Level,
+    target: String,
+    count: usize,
+}
+
+impl Dedup {
+    pub(crate) fn new() -> Self {
+        Dedup {
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Print a record unless it's a duplicate of the last one.
+    ///
+    /// If the record is different and the last one was repeated, then a
+    /// summary is printed first. Records logged by other threads at the same
+    /// time can be printed before or between them.
+    pub(crate) fn log<F>(&self, record: &Record, mut print: F)
+    where
+        F: FnMut(&Record),
+    {
+        let mut message = String::new();
+        let _ = write!(message, "{}", record.args());
+
+        let repeated = {
+            // The lock isn't held while printing, so formats can log records
+            // of their own without deadlocking
+            let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+
+            let repeated = match *last {
+                Some(ref mut last) => {
+                    if last.level == record.level() && last.target == record.target() && last.message == message {
+                        last.repeated += 1;
+                        return;
+                    }
+
+                    last.take_repeated()
+                },
+                None => None,
+            };
+
+            *last = Some(Last {
+                level: record.level(),
+                target: record.target().to_owned(),
+                message: message,
+                repeated: 0,
+            });
+
+            repeated
+        };
+
+        if let Some(repeated) = repeated {
+            repeated.print(&mut print);
+        }
+
+        print(record);
+    }
+
+    /// Print a summary of the last record if it was repeated.
+    pub(crate) fn flush<F>(&self, mut print: F)
+    where
+        F: FnMut(&Record),
+    {
+        let repeated = match *self.last.lock().unwrap_or_else(|e| e.into_inner()) {
+            Some(ref mut last) => last.take_repeated(),
+            None => None,
+        };
+
+        if let Some(repeated) = repeated {
+            repeated.print(&mut print);
+        }
+    }
+}
+
+impl Last {
+    /// Take the summary of the record if it was repeated.
+    fn take_repeated(&mut self) -> Option<Repeated> {
+        if self.repeated == 0 {
+            return None;
+        }
+
+        let count = self.repeated;
+        self.repeated = 0;
+
+        Some(Repeated {
+            level: self.level,
+            target: self.target.clone(),
+            count: count,
+        })
+    }
+}
+
+impl Repeated {
+    fn print<F>(&self, print: &mut F)
+    where
+        F: FnMut(&Record),
+    {
+        let times = if self.count == 1 { "time" } else { "times" };
+
+        print(&Record::builder()
+            .level(self.level)
+            .target(&self.target)
+            .args(format_args!("last message repeated {} {}", self.count, times))
+            .build());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(dedup: &Dedup, printed: &mut Vec<String>, level: Level, message: &str) {
+        dedup.log(&Record::builder()
+            .level(level)
+            .args(format_args!("{}", message))
+            .build(),
+            |record| printed.push(format!("{} {}", record.level(), record.args())));
+    }
+
+    #[test]
+    fn collapse_duplicates() {
+        let dedup = Dedup::new();
+        let mut printed = Vec::new();
+
+        log(&dedup, &mut printed, Level::Info, "a");
+        log(&dedup, &mut printed, Level::Info, "a");
+        log(&dedup, &mut printed, Level::Info, "a");
+        log(&dedup, &mut printed, Level::Warn, "a");
+        log(&dedup, &mut printed, Level::Info, "b");
+        log(&dedup, &mut printed, Level::Info, "b");
+
+        dedup.flush(|record| printed.push(format!("{} {}", record.level(), record.args())));
+        dedup.flush(|record| printed.push(format!("{} {}", record.level(), record.args())));
+
+        assert_eq!(vec![
+            "INFO a",
+            "INFO last message repeated 2 times",
+            "WARN a",
+            "INFO b",
+            "INFO last message repeated 1 time",
+        ], printed);
+    }
+
+    #[test]
+    fn print_without_lock() {
+        let dedup = Dedup::new();
+        let mut printed = Vec::new();
+
+        log(&dedup, &mut printed, Level::Info, "a");
+        log(&dedup, &mut printed, Level::Info, "a");
+
+        // Records can be logged while printing another one
+        let mut nested = Vec::new();
+
+        dedup.log(&Record::builder()
+            .level(Level::Info)
+            .args(format_args!("b"))
+            .build(),
+            |record| {
+                if record.args().to_string() == "b" {
+                    log(&dedup, &mut nested, Level::Info, "c");
+                }
+
+                printed.push(format!("{} {}", record.level(), record.args()));
+            });
+
+        assert_eq!(vec!["INFO a", "INFO last message repeated 1 time", "INFO b"], printed);
+        assert_eq!(vec!["INFO c"], nested);
+    }
+}
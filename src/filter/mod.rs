@@ -60,9 +60,10 @@
 //! [`Filter::matches`]: struct.Filter.html#method.matches
 
 use std::env;
+use std::error::Error;
 use std::mem;
 use std::fmt;
-use log::{Level, LevelFilter, Record, Metadata};
+use log::{Level, LevelFilter, Record, Metadata, STATIC_MAX_LEVEL};
 
 #[cfg(feature = "regex")]
 #[path = "regex.rs"]
@@ -81,6 +82,7 @@ mod inner;
 /// [`Builder`]: struct.Builder.html
 pub struct Filter {
     directives: Vec<Directive>,
+    patterns: Vec<PatternDirective>,
     filter: Option<inner::Filter>,
 }
 
@@ -115,15 +117,74 @@ pub struct Filter {
 /// [`Filter`]: struct.Filter.html
 pub struct Builder {
     directives: Vec<Directive>,
+    patterns: Vec<PatternDirective>,
     filter: Option<inner::Filter>,
+    errors: Vec<ParseError>,
+    static_max_level: bool,
 }
 
-#[derive(Debug)]
-struct Directive {
+/// A part of a directives string that couldn't be parsed.
+/// 
+/// Invalid parts are ignored when the directives are parsed, and the rest
+/// of the string is still used. Call [`Builder::parse_errors`] to get the
+/// errors for the directives parsed so far.
+/// 
+/// Module names can't be checked, because the targets that will be logged
+/// to aren't known up-front. A misspelled level on its own, like `ifno`, is
+/// treated as a module name.
+/// 
+/// [`Builder::parse_errors`]: struct.Builder.html#method.parse_errors
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseError {
+    kind: ParseErrorKind,
+    text: String,
+    reason: Option<String>,
+}
+
+/// The kind of a [`ParseError`].
+/// 
+/// [`ParseError`]: struct.ParseError.html
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ParseErrorKind {
+    /// The directives string has more than one `/`.
+    TooManySlashes,
+    /// A directive has more than one `=`.
+    InvalidDirective,
+    /// A directive's level isn't a known level name or number.
+    UnknownLevel,
+    /// The filter after the `/` isn't a valid regular expression.
+    InvalidFilter,
+    /// A directive's `~` target pattern isn't a valid regular expression.
+    InvalidPattern,
+    /// A directive's `~` target pattern has regex metacharacters, but the
+    /// `regex` feature is off, so it's matched as a plain substring.
+    LiteralPattern,
+    /// A directive's level is more verbose than the maximum level `log` was
+    /// compiled with, so the directive's level was lowered to it.
+    CompiledOut,
+}
+
+/// A directive that sets the level for a module path.
+/// 
+/// Use [`Filter::directives`] to get the directives in effect for a filter.
+/// A directive's `Display` implementation writes it the same way it's
+/// written in a directives string, like `my_app::net=debug`.
+/// 
+/// [`Filter::directives`]: struct.Filter.html#method.directives
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Directive {
     name: Option<String>,
     level: LevelFilter,
 }
 
+/// A directive that applies to targets matching a pattern instead of
+/// starting with a module path.
+#[derive(Debug)]
+struct PatternDirective {
+    pattern: inner::Filter,
+    level: LevelFilter,
+}
+
 impl Filter {
     /// Returns the maximum `LevelFilter` that this filter instance is
     /// configured to output.
@@ -149,6 +210,7 @@ impl Filter {
     pub fn filter(&self) -> LevelFilter {
         self.directives.iter()
             .map(|d| d.level)
+            .chain(self.patterns.iter().map(|d| d.level))
             .max()
             .unwrap_or(LevelFilter::Off)
     }
@@ -173,8 +235,69 @@ impl Filter {
         let level = metadata.level();
         let target = metadata.target();
 
+        // Target patterns take precedence over module paths
+        if let Some(directive) = self.patterns.iter().rev().find(|d| d.pattern.is_match(target)) {
+            return level <= directive.level;
+        }
+
         enabled(&self.directives, level, target)
     }
+
+    /// Returns the module directives this filter was built with.
+    ///
+    /// The directives are ordered from the shortest module path to the
+    /// longest, starting with the directive for all modules if there is one.
+    /// When no directives were given this is the default `error` directive.
+    /// Directives with a `~` target pattern aren't included.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// extern crate log;
+    /// extern crate env_logger;
+    ///
+    /// use log::LevelFilter;
+    /// use env_logger::filter::Builder;
+    ///
+    /// fn main() {
+    ///     let filter = Builder::new().parse("warn,my_app::net=debug").build();
+    ///
+    ///     let directives: Vec<_> = filter.directives()
+    ///         .iter()
+    ///         .map(|d| (d.name(), d.level()))
+    ///         .collect();
+    ///
+    ///     assert_eq!(vec![(None, LevelFilter::Warn), (Some("my_app::net"), LevelFilter::Debug)], directives);
+    /// }
+    /// ```
+    pub fn directives(&self) -> &[Directive] {
+        &self.directives
+    }
+}
+
+impl Directive {
+    /// The module path this directive applies to.
+    /// 
+    /// This is `None` for a directive that applies to all modules.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(|name| &**name)
+    }
+
+    /// The maximum level enabled for the module.
+    pub fn level(&self) -> LevelFilter {
+        self.level
+    }
+}
+
+impl fmt::Display for Directive {
+    fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
+        let level = self.level.to_string().to_lowercase();
+
+        match self.name {
+            Some(ref name) => write!(f, "{}={}", name, level),
+            None => write!(f, "{}", level),
+        }
+    }
 }
 
 impl Builder {
@@ -182,7 +305,10 @@ impl Builder {
     pub fn new() -> Builder {
         Builder {
             directives: Vec::new(),
+            patterns: Vec::new(),
             filter: None,
+            errors: Vec::new(),
+            static_max_level: false,
         }
     }
 
@@ -214,19 +340,77 @@ impl Builder {
     /// Parses the directives string.
     ///
     /// See the [Enabling Logging] section for more details.
+    /// Parts of the string that can't be parsed are ignored, and can be
+    /// inspected with [`parse_errors`].
     /// 
     /// [Enabling Logging]: ../index.html#enabling-logging
+    /// [`parse_errors`]: #method.parse_errors
     pub fn parse(&mut self, filters: &str) -> &mut Self {
-        let (directives, filter) = parse_spec(filters);
+        let (mut directives, mut patterns, filter, errors) = parse_spec(filters);
 
         self.filter = filter;
+        self.errors.extend(errors);
+
+        if self.static_max_level {
+            let levels = directives.iter_mut().map(|d| &mut d.level).chain(patterns.iter_mut().map(|p| &mut p.level));
+
+            self.errors.extend(clamp_levels(levels, STATIC_MAX_LEVEL));
+        }
 
         for directive in directives {
             self.directives.push(directive);
         }
+
+        for pattern in patterns {
+            self.patterns.push(pattern);
+        }
         self
     }
 
+    /// Whether or not to lower parsed levels to the maximum level `log` was
+    /// compiled with.
+    /// 
+    /// The `max_level_*` and `release_max_level_*` features of `log` remove
+    /// more verbose log statements at compile time, so a directive like
+    /// `myapp=trace` can't enable them. When this is enabled, parsed levels
+    /// above `log::STATIC_MAX_LEVEL` are lowered to it, and a
+    /// [`ParseErrorKind::CompiledOut`] error is recorded for each one so the
+    /// user can be told. This is disabled by default.
+    /// 
+    /// Only levels parsed after calling this are checked.
+    /// 
+    /// [`ParseErrorKind::CompiledOut`]: enum.ParseErrorKind.html#variant.CompiledOut
+    pub fn static_max_level(&mut self, static_max_level: bool) -> &mut Self {
+        self.static_max_level = static_max_level;
+        self
+    }
+
+    /// Whether or not parsed levels are lowered to `log::STATIC_MAX_LEVEL`.
+    pub(crate) fn get_static_max_level(&self) -> bool {
+        self.static_max_level
+    }
+
+    /// Get the errors for the directives that have been parsed so far.
+    /// 
+    /// The errors are cleared when the filter is built.
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// use env_logger::filter::{Builder, ParseErrorKind};
+    /// 
+    /// let mut builder = Builder::new();
+    /// builder.parse("myapp=ifno");
+    /// 
+    /// let errors = builder.parse_errors();
+    /// 
+    /// assert_eq!(ParseErrorKind::UnknownLevel, errors[0].kind());
+    /// assert_eq!("ifno", errors[0].text());
+    /// ```
+    pub fn parse_errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
     /// Build a log filter.
     pub fn build(&mut self) -> Filter {
         if self.directives.is_empty() {
@@ -245,8 +429,11 @@ impl Builder {
             });
         }
 
+        self.errors.clear();
+
         Filter {
             directives: mem::replace(&mut self.directives, Vec::new()),
+            patterns: mem::replace(&mut self.patterns, Vec::new()),
             filter: mem::replace(&mut self.filter, None),
         }
     }
@@ -263,6 +450,7 @@ impl fmt::Debug for Filter {
         f.debug_struct("Filter")
             .field("filter", &self.filter)
             .field("directives", &self.directives)
+            .field("patterns", &self.patterns)
             .finish()
     }
 }
@@ -272,22 +460,74 @@ impl fmt::Debug for Builder {
         f.debug_struct("Filter")
             .field("filter", &self.filter)
             .field("directives", &self.directives)
+            .field("patterns", &self.patterns)
             .finish()
     }
 }
 
+impl ParseError {
+    fn new(kind: ParseErrorKind, text: &str) -> Self {
+        ParseError {
+            kind: kind,
+            text: text.to_owned(),
+            reason: None,
+        }
+    }
+
+    /// The kind of error.
+    pub fn kind(&self) -> ParseErrorKind {
+        self.kind
+    }
+
+    /// The part of the directives string that couldn't be parsed.
+    /// 
+    /// This is the level for an unknown level, the filter or pattern for an
+    /// invalid filter or pattern, and the whole directive or string otherwise.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
+        match self.kind {
+            ParseErrorKind::TooManySlashes => write!(f, "invalid logging spec '{}' (too many '/'s)", self.text),
+            ParseErrorKind::InvalidDirective => write!(f, "invalid logging directive '{}'", self.text),
+            ParseErrorKind::UnknownLevel => write!(f, "unknown log level '{}'", self.text),
+            ParseErrorKind::InvalidFilter => write!(f, "invalid regex filter '{}'", self.text),
+            ParseErrorKind::InvalidPattern => write!(f, "invalid target pattern '{}'", self.text),
+            ParseErrorKind::LiteralPattern => write!(f, "target pattern '{}' is matched as plain text without the `regex` feature", self.text),
+            ParseErrorKind::CompiledOut => write!(f, "log level '{}' is disabled at compile time", self.text),
+        }?;
+
+        if let Some(ref reason) = self.reason {
+            write!(f, ": {}", reason)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for ParseError {
+    fn description(&self) -> &str {
+        "invalid logging directive"
+    }
+}
+
 /// Parse a logging specification string (e.g: "crate1,crate2::mod3,crate3::x=error/foo")
-/// and return a vector with log directives.
-fn parse_spec(spec: &str) -> (Vec<Directive>, Option<inner::Filter>) {
+/// and return vectors with log directives and target pattern directives, along with any
+/// parts that couldn't be parsed.
+fn parse_spec(spec: &str) -> (Vec<Directive>, Vec<PatternDirective>, Option<inner::Filter>, Vec<ParseError>) {
     let mut dirs = Vec::new();
+    let mut patterns = Vec::new();
+    let mut errors = Vec::new();
 
     let mut parts = spec.split('/');
     let mods = parts.next();
     let filter = parts.next();
     if parts.next().is_some() {
-        println!("warning: invalid logging spec '{}', \
-                 ignoring it (too many '/'s)", spec);
-        return (dirs, None);
+        errors.push(ParseError::new(ParseErrorKind::TooManySlashes, spec));
+        return (dirs, patterns, None, errors);
     }
     mods.map(|m| { for s in m.split(',') {
         if s.len() == 0 { continue }
@@ -306,37 +546,89 @@ fn parse_spec(spec: &str) -> (Vec<Directive>, Option<inner::Filter>) {
                 match part1.parse() {
                     Ok(num) => (num, Some(part0)),
                     _ => {
-                        println!("warning: invalid logging spec '{}', \
-                                 ignoring it", part1);
+                        errors.push(ParseError::new(ParseErrorKind::UnknownLevel, part1));
                         continue
                     }
                 }
             },
             _ => {
-                println!("warning: invalid logging spec '{}', \
-                         ignoring it", s);
+                errors.push(ParseError::new(ParseErrorKind::InvalidDirective, s));
                 continue
             }
         };
-        dirs.push(Directive {
-            name: name.map(|s| s.to_string()),
-            level: log_level,
-        });
+        match name {
+            // A leading `~` makes the name a pattern for the whole target
+            Some(name) if name.starts_with('~') => {
+                let pattern = &name[1..];
+                match inner::Filter::new(pattern) {
+                    Ok(filter) => {
+                        // The pattern is still used, but it probably won't
+                        // match what was meant
+                        if !cfg!(feature = "regex") && pattern.contains(is_regex_meta) {
+                            errors.push(ParseError::new(ParseErrorKind::LiteralPattern, pattern));
+                        }
+                        patterns.push(PatternDirective {
+                            pattern: filter,
+                            level: log_level,
+                        });
+                    },
+                    Err(e) => errors.push(ParseError {
+                        reason: Some(e),
+                        ..ParseError::new(ParseErrorKind::InvalidPattern, pattern)
+                    }),
+                }
+            },
+            _ => dirs.push(Directive {
+                name: name.map(|s| s.to_string()),
+                level: log_level,
+            }),
+        }
     }});
 
     let filter = filter.map_or(None, |filter| {
         match inner::Filter::new(filter) {
             Ok(re) => Some(re),
             Err(e) => {
-                println!("warning: invalid regex filter - {}", e);
+                errors.push(ParseError {
+                    reason: Some(e),
+                    ..ParseError::new(ParseErrorKind::InvalidFilter, filter)
+                });
                 None
             }
         }
     });
 
-    return (dirs, filter);
+    return (dirs, patterns, filter, errors);
 }
 
+/// Whether `c` has a special meaning in a regular expression.
+fn is_regex_meta(c: char) -> bool {
+    match c {
+        '\\' | '.' | '+' | '*' | '?' | '(' | ')' | '|' | '[' | ']' | '{' | '}' | '^' | '$' => true,
+        _ => false,
+    }
+}
+
+/// Lower `levels` that are above `max` to it, returning an error for each one.
+fn clamp_levels<'a, I>(levels: I, max: LevelFilter) -> Vec<ParseError>
+where
+    I: Iterator<Item = &'a mut LevelFilter>,
+{
+    let mut errors = Vec::new();
+
+    for level in levels {
+        if *level > max {
+            errors.push(ParseError {
+                reason: Some(format!("the maximum level is {}", max.to_string().to_lowercase())),
+                ..ParseError::new(ParseErrorKind::CompiledOut, &level.to_string().to_lowercase())
+            });
+
+            *level = max;
+        }
+    }
+
+    errors
+}
 
 // Check whether a level and target are enabled by the set of directives.
 fn enabled(directives: &[Directive], level: Level, target: &str) -> bool {
@@ -354,9 +646,9 @@ fn enabled(directives: &[Directive], level: Level, target: &str) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use log::{Level, LevelFilter};
+    use log::{Level, LevelFilter, Metadata};
 
-    use super::{Builder, Filter, Directive, parse_spec, enabled};
+    use super::{Builder, Filter, Directive, ParseError, ParseErrorKind, parse_spec, clamp_levels, enabled};
 
     fn make_logger_filter(dirs: Vec<Directive>) -> Filter {
         let mut logger = Builder::new().build();
@@ -382,6 +674,22 @@ mod tests {
         assert!(!enabled(&logger.directives, Level::Debug, "crate2"));
     }
 
+    #[test]
+    fn directives_display() {
+        let inputs = vec![
+            ("", vec!["error"]),
+            ("info,crate1::mod1=warn", vec!["info", "crate1::mod1=warn"]),
+            ("crate1::mod1=off,crate2", vec!["crate2=trace", "crate1::mod1=off"]),
+        ];
+
+        for (spec, expected) in inputs {
+            let filter = Builder::new().parse(spec).build();
+            let directives: Vec<_> = filter.directives().iter().map(|d| d.to_string()).collect();
+
+            assert_eq!(expected, directives);
+        }
+    }
+
     #[test]
     fn parse_default() {
         let logger = Builder::new().parse("info,crate1::mod1=warn").build();
@@ -458,7 +766,7 @@ mod tests {
 
     #[test]
     fn parse_spec_valid() {
-        let (dirs, filter) = parse_spec("crate1::mod1=error,crate1::mod2,crate2=debug");
+        let (dirs, _, filter, _) = parse_spec("crate1::mod1=error,crate1::mod2,crate2=debug");
         assert_eq!(dirs.len(), 3);
         assert_eq!(dirs[0].name, Some("crate1::mod1".to_string()));
         assert_eq!(dirs[0].level, LevelFilter::Error);
@@ -474,37 +782,135 @@ mod tests {
     #[test]
     fn parse_spec_invalid_crate() {
         // test parse_spec with multiple = in specification
-        let (dirs, filter) = parse_spec("crate1::mod1=warn=info,crate2=debug");
+        let (dirs, _, filter, errors) = parse_spec("crate1::mod1=warn=info,crate2=debug");
         assert_eq!(dirs.len(), 1);
         assert_eq!(dirs[0].name, Some("crate2".to_string()));
         assert_eq!(dirs[0].level, LevelFilter::Debug);
         assert!(filter.is_none());
+        assert_eq!(errors, vec![ParseError::new(ParseErrorKind::InvalidDirective, "crate1::mod1=warn=info")]);
     }
 
     #[test]
     fn parse_spec_invalid_level() {
         // test parse_spec with 'noNumber' as log level
-        let (dirs, filter) = parse_spec("crate1::mod1=noNumber,crate2=debug");
+        let (dirs, _, filter, errors) = parse_spec("crate1::mod1=noNumber,crate2=debug");
         assert_eq!(dirs.len(), 1);
         assert_eq!(dirs[0].name, Some("crate2".to_string()));
         assert_eq!(dirs[0].level, LevelFilter::Debug);
         assert!(filter.is_none());
+        assert_eq!(errors, vec![ParseError::new(ParseErrorKind::UnknownLevel, "noNumber")]);
+    }
+
+    #[test]
+    fn parse_spec_pattern() {
+        let (dirs, patterns, filter, errors) = parse_spec("info,~::db=debug,~::cache/abc");
+        assert_eq!(dirs.len(), 1);
+        assert_eq!(dirs[0].name, None);
+        assert_eq!(dirs[0].level, LevelFilter::Info);
+        assert_eq!(patterns.len(), 2);
+        assert_eq!(patterns[0].pattern.to_string(), "::db");
+        assert_eq!(patterns[0].level, LevelFilter::Debug);
+        assert_eq!(patterns[1].pattern.to_string(), "::cache");
+        assert_eq!(patterns[1].level, LevelFilter::max());
+        assert!(filter.is_some() && filter.unwrap().to_string() == "abc");
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn parse_spec_invalid_pattern() {
+        let (dirs, patterns, _, errors) = parse_spec("~(=debug,crate2=warn");
+        assert_eq!(dirs.len(), 1);
+        assert!(patterns.is_empty());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind(), ParseErrorKind::InvalidPattern);
+        assert_eq!(errors[0].text(), "(");
+    }
+
+    #[test]
+    #[cfg(not(feature = "regex"))]
+    fn parse_spec_literal_pattern() {
+        let (_, patterns, _, errors) = parse_spec("~::db::.*=trace,~::cache=debug");
+        assert_eq!(patterns.len(), 2);
+        assert_eq!(patterns[0].pattern.to_string(), "::db::.*");
+        assert_eq!(errors, vec![ParseError::new(ParseErrorKind::LiteralPattern, "::db::.*")]);
+    }
+
+    #[test]
+    fn pattern_takes_precedence() {
+        let logger = Builder::new().parse("warn,crate1::db=error,~::db=debug,~::db::pool=off").build();
+
+        let inputs = vec![
+            (Level::Debug, "crate1::db", true),
+            (Level::Debug, "crate2::db::query", true),
+            (Level::Error, "crate1::db::pool", false),
+            (Level::Info, "crate1::http", false),
+            (Level::Warn, "crate1::http", true),
+        ];
+
+        for (level, target, expected) in inputs {
+            let metadata = Metadata::builder().level(level).target(target).build();
+
+            assert_eq!(expected, logger.enabled(&metadata), "{} {}", level, target);
+        }
+
+        assert_eq!(LevelFilter::Debug, logger.filter());
+    }
+
+    #[test]
+    fn parse_spec_too_many_slashes() {
+        let (dirs, _, filter, errors) = parse_spec("crate1=debug/a/b");
+        assert!(dirs.is_empty());
+        assert!(filter.is_none());
+        assert_eq!(errors, vec![ParseError::new(ParseErrorKind::TooManySlashes, "crate1=debug/a/b")]);
+    }
+
+    #[test]
+    fn parse_errors_cleared_on_build() {
+        let mut builder = Builder::new();
+        builder.parse("crate1=ifno");
+
+        assert_eq!("unknown log level 'ifno'", builder.parse_errors()[0].to_string());
+
+        builder.build();
+
+        assert!(builder.parse_errors().is_empty());
     }
 
     #[test]
     fn parse_spec_string_level() {
         // test parse_spec with 'warn' as log level
-        let (dirs, filter) = parse_spec("crate1::mod1=wrong,crate2=warn");
+        let (dirs, _, filter, _) = parse_spec("crate1::mod1=wrong,crate2=warn");
         assert_eq!(dirs.len(), 1);
         assert_eq!(dirs[0].name, Some("crate2".to_string()));
         assert_eq!(dirs[0].level, LevelFilter::Warn);
         assert!(filter.is_none());
     }
 
+    #[test]
+    fn parse_spec_mixed_case_level() {
+        let inputs = vec![
+            ("crate1=WaRn", Some("crate1"), LevelFilter::Warn),
+            ("crate1=DEBUG", Some("crate1"), LevelFilter::Debug),
+            ("Info", None, LevelFilter::Info),
+            ("TRACE", None, LevelFilter::Trace),
+            ("crate1=Off", Some("crate1"), LevelFilter::Off),
+        ];
+
+        for (spec, name, level) in inputs {
+            let (dirs, _, filter, errors) = parse_spec(spec);
+            assert_eq!(dirs.len(), 1);
+            assert_eq!(dirs[0].name, name.map(|name| name.to_string()));
+            assert_eq!(dirs[0].level, level);
+            assert!(filter.is_none());
+            assert!(errors.is_empty());
+        }
+    }
+
     #[test]
     fn parse_spec_empty_level() {
         // test parse_spec with '' as log level
-        let (dirs, filter) = parse_spec("crate1::mod1=wrong,crate2=");
+        let (dirs, _, filter, _) = parse_spec("crate1::mod1=wrong,crate2=");
         assert_eq!(dirs.len(), 1);
         assert_eq!(dirs[0].name, Some("crate2".to_string()));
         assert_eq!(dirs[0].level, LevelFilter::max());
@@ -514,7 +920,7 @@ mod tests {
     #[test]
     fn parse_spec_global() {
         // test parse_spec with no crate
-        let (dirs, filter) = parse_spec("warn,crate2=debug");
+        let (dirs, _, filter, _) = parse_spec("warn,crate2=debug");
         assert_eq!(dirs.len(), 2);
         assert_eq!(dirs[0].name, None);
         assert_eq!(dirs[0].level, LevelFilter::Warn);
@@ -525,7 +931,7 @@ mod tests {
 
     #[test]
     fn parse_spec_valid_filter() {
-        let (dirs, filter) = parse_spec("crate1::mod1=error,crate1::mod2,crate2=debug/abc");
+        let (dirs, _, filter, _) = parse_spec("crate1::mod1=error,crate1::mod2,crate2=debug/abc");
         assert_eq!(dirs.len(), 3);
         assert_eq!(dirs[0].name, Some("crate1::mod1".to_string()));
         assert_eq!(dirs[0].level, LevelFilter::Error);
@@ -540,7 +946,7 @@ mod tests {
 
     #[test]
     fn parse_spec_invalid_crate_filter() {
-        let (dirs, filter) = parse_spec("crate1::mod1=error=warn,crate2=debug/a.c");
+        let (dirs, _, filter, _) = parse_spec("crate1::mod1=error=warn,crate2=debug/a.c");
         assert_eq!(dirs.len(), 1);
         assert_eq!(dirs[0].name, Some("crate2".to_string()));
         assert_eq!(dirs[0].level, LevelFilter::Debug);
@@ -549,10 +955,25 @@ mod tests {
 
     #[test]
     fn parse_spec_empty_with_filter() {
-        let (dirs, filter) = parse_spec("crate1/a*c");
+        let (dirs, _, filter, _) = parse_spec("crate1/a*c");
         assert_eq!(dirs.len(), 1);
         assert_eq!(dirs[0].name, Some("crate1".to_string()));
         assert_eq!(dirs[0].level, LevelFilter::max());
         assert!(filter.is_some() && filter.unwrap().to_string() == "a*c");
     }
+
+    #[test]
+    fn clamp_levels_above_max() {
+        let mut levels = vec![LevelFilter::Warn, LevelFilter::Trace, LevelFilter::Info, LevelFilter::Debug];
+
+        let errors = clamp_levels(levels.iter_mut(), LevelFilter::Info);
+
+        assert_eq!(vec![LevelFilter::Warn, LevelFilter::Info, LevelFilter::Info, LevelFilter::Info], levels);
+
+        let errors: Vec<_> = errors.iter().map(|err| (err.kind(), err.to_string())).collect();
+        assert_eq!(vec![
+            (ParseErrorKind::CompiledOut, "log level 'trace' is disabled at compile time: the maximum level is info".to_owned()),
+            (ParseErrorKind::CompiledOut, "log level 'debug' is disabled at compile time: the maximum level is info".to_owned()),
+        ], errors);
+    }
 }
@@ -0,0 +1,89 @@
+//! Stripping ANSI escape sequences from formatted records.
+//!
+//! This is used when styles are disabled but a custom format writes its own
+//! escape sequences. Only the sequences terminals use for styling and
+//! hyperlinks are recognized: CSI sequences like `ESC [ 31 m`, OSC sequences
+//! like `ESC ] 8 ; ; url ESC \`, and other two-byte escapes.
+
+const ESC: u8 = 0x1b;
+const BEL: u8 = 0x07;
+
+/// Copy `bytes` into `out` without any ANSI escape sequences.
+pub(super) fn strip(bytes: &[u8], out: &mut Vec<u8>) {
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != ESC {
+            let start = i;
+
+            while i < bytes.len() && bytes[i] != ESC {
+                i += 1;
+            }
+
+            out.extend_from_slice(&bytes[start..i]);
+            continue;
+        }
+
+        i = match bytes.get(i + 1) {
+            Some(&b'[') => skip_csi(bytes, i + 2),
+            Some(&b']') => skip_osc(bytes, i + 2),
+            Some(_) => i + 2,
+            None => i + 1,
+        };
+    }
+}
+
+/// Skip the parameters and final byte of a CSI sequence.
+pub(super) fn skip_csi(bytes: &[u8], mut i: usize) -> usize {
+    while i < bytes.len() {
+        let b = bytes[i];
+        i += 1;
+
+        if (0x40..=0x7e).contains(&b) {
+            break;
+        }
+    }
+
+    i
+}
+
+/// Skip the contents of an OSC sequence, up to and including its terminator.
+pub(super) fn skip_osc(bytes: &[u8], mut i: usize) -> usize {
+    while i < bytes.len() {
+        match bytes[i] {
+            BEL => return i + 1,
+            ESC if bytes.get(i + 1) == Some(&b'\\') => return i + 2,
+            _ => i += 1,
+        }
+    }
+
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_sequences() {
+        let inputs: Vec<(&[u8], &[u8])> = vec![
+            (b"plain text", b"plain text"),
+            (b"\x1b[0m\x1b[1;31mERROR\x1b[0m: failed", b"ERROR: failed"),
+            (b"\x1b[38;2;255;0;0mred\x1b[0m", b"red"),
+            (b"\x1b]8;;https://example.com\x1b\\docs\x1b]8;;\x1b\\", b"docs"),
+            (b"\x1b]0;title\x07text", b"text"),
+            (b"\x1bctext", b"text"),
+            (b"unterminated \x1b[31", b"unterminated "),
+            (b"trailing \x1b", b"trailing "),
+            ("caf\u{e9} \x1b[1m\u{2713}\x1b[0m".as_bytes(), "caf\u{e9} \u{2713}".as_bytes()),
+        ];
+
+        for (input, expected) in inputs {
+            let mut out = Vec::new();
+
+            strip(input, &mut out);
+
+            assert_eq!(expected, &out[..]);
+        }
+    }
+}
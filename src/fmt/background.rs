@@ -0,0 +1,382 @@
+//! Printing records on a background thread.
+//!
+//! Formatted records are copied onto a bounded queue, and a dedicated thread
+//! prints them to the target. The logging thread only waits for the target
+//! if the queue is full and the overflow policy is to block.
+//!
+//! Records are printed in the order they're queued by default. An [`Order`]
+//! can hold records back and print them in a different order instead.
+//!
+//! [`Order`]: trait.Order.html
+
+use std::io;
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use log::Level;
+use termcolor::Buffer;
+
+use super::Output;
+
+/// What to do with a record when the background queue is full.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Overflow {
+    /// Wait until the background thread has printed a record.
+    Block,
+    /// Drop the oldest record in the queue to make room.
+    DropOldest,
+}
+
+/// A record waiting to be printed.
+pub(super) struct Pending {
+    pub(super) logged_at: Instant,
+    // The number of records queued before this one, to keep records logged
+    // at the same instant in the order they were queued
+    pub(super) sequence: u64,
+    pub(super) buf: Buffer,
+    pub(super) level: Level,
+}
+
+/// The order queued records are printed in.
+pub(super) trait Order: Send {
+    /// Queue a record.
+    fn push(&mut self, record: Pending);
+
+    /// Take the next record to print.
+    fn pop(&mut self) -> Option<Pending>;
+
+    /// The number of queued records.
+    fn len(&self) -> usize;
+
+    /// When the next record should be printed, or `None` if there isn't one.
+    fn due(&self) -> Option<Instant>;
+}
+
+/// Records printed in the order they were queued, as soon as possible.
+pub(super) struct Fifo(VecDeque<Pending>);
+
+impl Fifo {
+    pub(super) fn new() -> Self {
+        Fifo(VecDeque::new())
+    }
+}
+
+impl Order for Fifo {
+    fn push(&mut self, record: Pending) {
+        self.0.push_back(record);
+    }
+
+    fn pop(&mut self) -> Option<Pending> {
+        self.0.pop_front()
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn due(&self) -> Option<Instant> {
+        self.0.front().map(|record| record.logged_at)
+    }
+}
+
+/// A background thread that prints queued records.
+///
+/// When the worker is dropped, the records left in the queue are printed
+/// before the thread exits.
+pub(super) struct Worker {
+    queue: Arc<Queue>,
+    thread: Option<JoinHandle<()>>,
+}
+
+struct Queue {
+    state: Mutex<State>,
+    // Notified whenever a record is queued or printed, or the queue is
+    // flushed or closed
+    changed: Condvar,
+    capacity: usize,
+    overflow: Overflow,
+}
+
+struct State {
+    records: Box<Order>,
+    // The number of records queued so far
+    sequence: u64,
+    printing: bool,
+    // The number of threads waiting for room in the queue
+    blocked: usize,
+    // The number of threads waiting for the queue to be flushed
+    flushing: usize,
+    closed: bool,
+}
+
+impl Worker {
+    pub(super) fn spawn(output: Arc<Output>, records: Box<Order>, capacity: usize, overflow: Overflow) -> io::Result<Worker> {
+        let queue = Arc::new(Queue {
+            state: Mutex::new(State {
+                records: records,
+                sequence: 0,
+                printing: false,
+                blocked: 0,
+                flushing: 0,
+                closed: false,
+            }),
+            changed: Condvar::new(),
+            // A queue without room for any records would block forever
+            capacity: capacity.max(1),
+            overflow: overflow,
+        });
+
+        let thread = {
+            let queue = queue.clone();
+
+            thread::Builder::new()
+                .name("env_logger".into())
+                .spawn(move || queue.run(&output))?
+        };
+
+        Ok(Worker {
+            queue: queue,
+            thread: Some(thread),
+        })
+    }
+
+    /// Queue a record that was logged at `logged_at` to be printed.
+    pub(super) fn send(&self, buf: &Buffer, level: Level, logged_at: Instant) {
+        let mut state = self.queue.lock();
+
+        if state.records.len() >= self.queue.capacity {
+            match self.queue.overflow {
+                Overflow::Block => {
+                    state.blocked += 1;
+                    self.queue.changed.notify_all();
+
+                    while state.records.len() >= self.queue.capacity {
+                        state = self.queue.wait(state);
+                    }
+
+                    state.blocked -= 1;
+                },
+                Overflow::DropOldest => {
+                    state.records.pop();
+                },
+            }
+        }
+
+        let sequence = state.sequence;
+        state.sequence += 1;
+
+        state.records.push(Pending {
+            logged_at: logged_at,
+            sequence: sequence,
+            buf: buf.clone(),
+            level: level,
+        });
+
+        self.queue.changed.notify_all();
+    }
+
+    /// Print every queued record without waiting for it to be due, and wait
+    /// until they've all been printed.
+    pub(super) fn wait(&self) {
+        let mut state = self.queue.lock();
+
+        state.flushing += 1;
+        self.queue.changed.notify_all();
+
+        while state.records.len() > 0 || state.printing {
+            state = self.queue.wait(state);
+        }
+
+        state.flushing -= 1;
+    }
+
+    /// The number of records waiting to be printed.
+    #[cfg(test)]
+    pub(super) fn len(&self) -> usize {
+        self.queue.lock().records.len()
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        self.queue.lock().closed = true;
+        self.queue.changed.notify_all();
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Queue {
+    fn lock(&self) -> MutexGuard<State> {
+        // A panic while printing doesn't leave the queue in an invalid state
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn wait<'a>(&self, state: MutexGuard<'a, State>) -> MutexGuard<'a, State> {
+        self.changed.wait(state).unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// How long to wait before the next record can be printed, or `None` if
+    /// there isn't one.
+    fn until_due(&self, state: &State) -> Option<Duration> {
+        let due = state.records.due()?;
+
+        // Records are printed straight away to make room, or when they're
+        // all being printed
+        let full = state.blocked > 0 && state.records.len() >= self.capacity;
+
+        if state.closed || state.flushing > 0 || full {
+            return Some(Duration::from_secs(0));
+        }
+
+        Some(due.saturating_duration_since(Instant::now()))
+    }
+
+    /// Print records as they become due until the queue is closed and empty.
+    fn run(&self, output: &Output) {
+        loop {
+            let record = {
+                let mut state = self.lock();
+
+                loop {
+                    match self.until_due(&state) {
+                        Some(until_due) if until_due == Duration::from_secs(0) => {
+                            state.printing = true;
+                            break state.records.pop().expect("a record is due");
+                        },
+                        Some(until_due) => {
+                            state = self.changed.wait_timeout(state, until_due)
+                                .map(|(state, _)| state)
+                                .unwrap_or_else(|e| e.into_inner().0);
+                        },
+                        None if state.closed => return,
+                        None => state = self.wait(state),
+                    }
+                }
+            };
+
+            // Room was made in the queue
+            self.changed.notify_all();
+
+            // There's nowhere to report errors from the background thread
+            let _ = output.print(&record.buf, record.level);
+
+            self.lock().printing = false;
+            self.changed.notify_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{Builder, Target};
+    use std::io::Write;
+
+    /// A pipe that can be paused so records pile up in the queue.
+    #[derive(Clone)]
+    struct PausedBuf {
+        written: Arc<Mutex<Vec<u8>>>,
+        paused: Arc<Mutex<()>>,
+    }
+
+    impl Write for PausedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let _paused = self.paused.lock().unwrap();
+
+            self.written.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn print(writer: &super::super::Writer, message: &str) {
+        let mut buf = Buffer::no_color();
+        io::Write::write_all(&mut buf, message.as_bytes()).unwrap();
+
+        writer.print(&buf, Level::Info).unwrap();
+    }
+
+    #[test]
+    fn overflow() {
+        let inputs = vec![
+            (Overflow::Block, "a\nb\nc\nd\n"),
+            (Overflow::DropOldest, "a\nc\nd\n"),
+        ];
+
+        for (overflow, expected) in inputs {
+            let pipe = PausedBuf {
+                written: Default::default(),
+                paused: Default::default(),
+            };
+
+            let writer = Arc::new(Builder::new()
+                .target(Target::Pipe(Box::new(pipe.clone())))
+                .background(2, overflow)
+                .try_build()
+                .unwrap());
+
+            let paused = pipe.paused.lock().unwrap();
+
+            print(&writer, "a\n");
+
+            // Wait for the background thread to start printing the first record
+            while writer.worker.as_ref().unwrap().len() > 0 {
+                thread::sleep(Duration::from_millis(1));
+            }
+
+            let sender = {
+                let writer = writer.clone();
+
+                thread::spawn(move || {
+                    print(&writer, "b\n");
+                    print(&writer, "c\n");
+                    print(&writer, "d\n");
+                })
+            };
+
+            if overflow == Overflow::DropOldest {
+                sender.join().unwrap();
+                drop(paused);
+            }
+            else {
+                // The sender blocks on the full queue until the pipe is unpaused
+                thread::sleep(Duration::from_millis(20));
+                drop(paused);
+                sender.join().unwrap();
+            }
+
+            writer.flush().unwrap();
+
+            assert_eq!(expected.as_bytes(), &pipe.written.lock().unwrap()[..]);
+        }
+    }
+
+    #[test]
+    fn drop_prints_queued_records() {
+        let pipe = PausedBuf {
+            written: Default::default(),
+            paused: Default::default(),
+        };
+
+        {
+            let writer = Builder::new()
+                .target(Target::Pipe(Box::new(pipe.clone())))
+                .background(16, Overflow::Block)
+                .try_build()
+                .unwrap();
+
+            for message in &["a\n", "b\n", "c\n"] {
+                print(&writer, message);
+            }
+        }
+
+        assert_eq!(b"a\nb\nc\n", &pipe.written.lock().unwrap()[..]);
+    }
+}
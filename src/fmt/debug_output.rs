@@ -0,0 +1,31 @@
+//! Printing records to the Windows debug output.
+//!
+//! Each record is converted to UTF-16 and passed to `OutputDebugStringW`, so
+//! it shows up in an attached debugger or a viewer like DebugView. Nothing is
+//! printed if there's no debugger or viewer listening.
+
+use std::ffi::OsStr;
+use std::os::windows::ffi::OsStrExt;
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn OutputDebugStringW(output_string: *const u16);
+}
+
+/// Print a formatted record to the debug output.
+///
+/// Invalid UTF-8 is replaced rather than dropping the whole record.
+pub(super) fn print(record: &[u8]) {
+    let record = String::from_utf8_lossy(record);
+
+    // The string is passed as a null-terminated wide string
+    let wide: Vec<u16> = OsStr::new(&*record)
+        .encode_wide()
+        .filter(|&c| c != 0)
+        .chain(Some(0))
+        .collect();
+
+    unsafe {
+        OutputDebugStringW(wide.as_ptr());
+    }
+}
@@ -0,0 +1,210 @@
+//! Converting styled records to HTML.
+//!
+//! Styles are written to the record's buffer as ANSI escape sequences, and
+//! the record is converted just before it's printed. SGR sequences like
+//! `ESC [ 31 m` become `<span>` elements with inline styles, other escape
+//! sequences are dropped, and the text is escaped so it's safe to embed.
+
+use std::str;
+use std::io::Write;
+
+use super::ansi;
+
+const ESC: u8 = 0x1b;
+
+/// The styles in effect at some point in a record.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+struct Style {
+    fg: Option<(u8, u8, u8)>,
+    bg: Option<(u8, u8, u8)>,
+    bold: bool,
+    dimmed: bool,
+    italic: bool,
+    underline: bool,
+}
+
+/// Copy `bytes` into `out` as HTML.
+pub(super) fn convert(bytes: &[u8], out: &mut Vec<u8>) {
+    let mut style = Style::default();
+    // The style of the span the text is currently written in
+    let mut span = Style::default();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == ESC {
+            i = match bytes.get(i + 1) {
+                Some(&b'[') => {
+                    let end = ansi::skip_csi(bytes, i + 2);
+
+                    if bytes[end - 1] == b'm' {
+                        style.apply(&bytes[i + 2..end - 1]);
+                    }
+
+                    end
+                },
+                Some(&b']') => ansi::skip_osc(bytes, i + 2),
+                Some(_) => i + 2,
+                None => i + 1,
+            };
+            continue;
+        }
+
+        // Spans are only opened when there's text to style
+        if style != span {
+            if span != Style::default() {
+                out.extend_from_slice(b"</span>");
+            }
+
+            if style != Style::default() {
+                style.open_span(out);
+            }
+
+            span = style;
+        }
+
+        match bytes[i] {
+            b'<' => out.extend_from_slice(b"&lt;"),
+            b'>' => out.extend_from_slice(b"&gt;"),
+            b'&' => out.extend_from_slice(b"&amp;"),
+            b'"' => out.extend_from_slice(b"&quot;"),
+            b => out.push(b),
+        }
+
+        i += 1;
+    }
+
+    if span != Style::default() {
+        out.extend_from_slice(b"</span>");
+    }
+}
+
+impl Style {
+    /// Apply the parameters of an SGR sequence.
+    fn apply(&mut self, params: &[u8]) {
+        let params: Vec<u16> = params
+            .split(|&b| b == b';')
+            // An empty parameter is the same as `0`
+            .map(|param| str::from_utf8(param).ok().and_then(|param| param.parse().ok()).unwrap_or(0))
+            .collect();
+
+        let mut params = params.into_iter();
+
+        while let Some(param) = params.next() {
+            match param {
+                0 => *self = Style::default(),
+                1 => self.bold = true,
+                2 => self.dimmed = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                22 => {
+                    self.bold = false;
+                    self.dimmed = false;
+                },
+                23 => self.italic = false,
+                24 => self.underline = false,
+                30..=37 => self.fg = Some(palette(param - 30)),
+                38 => self.fg = extended_color(&mut params),
+                39 => self.fg = None,
+                40..=47 => self.bg = Some(palette(param - 40)),
+                48 => self.bg = extended_color(&mut params),
+                49 => self.bg = None,
+                90..=97 => self.fg = Some(palette(param - 90 + 8)),
+                100..=107 => self.bg = Some(palette(param - 100 + 8)),
+                _ => (),
+            }
+        }
+    }
+
+    fn open_span(&self, out: &mut Vec<u8>) {
+        let mut css = Vec::new();
+
+        if let Some((r, g, b)) = self.fg {
+            css.push(format!("color:#{:02x}{:02x}{:02x}", r, g, b));
+        }
+        if let Some((r, g, b)) = self.bg {
+            css.push(format!("background-color:#{:02x}{:02x}{:02x}", r, g, b));
+        }
+        if self.bold {
+            css.push("font-weight:bold".to_owned());
+        }
+        if self.dimmed {
+            css.push("opacity:0.7".to_owned());
+        }
+        if self.italic {
+            css.push("font-style:italic".to_owned());
+        }
+        if self.underline {
+            css.push("text-decoration:underline".to_owned());
+        }
+
+        let _ = write!(out, "<span style=\"{}\">", css.join(";"));
+    }
+}
+
+/// Read a 256 color or RGB color that follows a `38` or `48` parameter.
+fn extended_color<I: Iterator<Item = u16>>(params: &mut I) -> Option<(u8, u8, u8)> {
+    match params.next() {
+        Some(5) => params.next().map(|n| palette(n)),
+        Some(2) => {
+            let r = params.next().unwrap_or(0);
+            let g = params.next().unwrap_or(0);
+            let b = params.next().unwrap_or(0);
+
+            Some((r as u8, g as u8, b as u8))
+        },
+        _ => None,
+    }
+}
+
+/// The RGB value of a color in the 256 color palette, using xterm's colors.
+fn palette(n: u16) -> (u8, u8, u8) {
+    const BASIC: [(u8, u8, u8); 16] = [
+        (0, 0, 0), (205, 0, 0), (0, 205, 0), (205, 205, 0),
+        (0, 0, 238), (205, 0, 205), (0, 205, 205), (229, 229, 229),
+        (127, 127, 127), (255, 0, 0), (0, 255, 0), (255, 255, 0),
+        (92, 92, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255),
+    ];
+    const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+    match n {
+        0..=15 => BASIC[n as usize],
+        16..=231 => {
+            let n = n - 16;
+
+            (CUBE[(n / 36) as usize], CUBE[(n / 6 % 6) as usize], CUBE[(n % 6) as usize])
+        },
+        _ => {
+            let gray = (8 + 10 * (n.min(255) - 232)) as u8;
+
+            (gray, gray, gray)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_records() {
+        let inputs: Vec<(&[u8], &str)> = vec![
+            (b"plain text", "plain text"),
+            (b"<b> & \"quoted\"", "&lt;b&gt; &amp; &quot;quoted&quot;"),
+            (b"\x1b[0m\x1b[1m\x1b[31mERROR\x1b[0m: failed", "<span style=\"color:#cd0000;font-weight:bold\">ERROR</span>: failed"),
+            (b"\x1b[38;5;9mred\x1b[0m", "<span style=\"color:#ff0000\">red</span>"),
+            (b"\x1b[38;2;1;2;3m\x1b[48;5;232mrgb\x1b[39mgray", "<span style=\"color:#010203;background-color:#080808\">rgb</span><span style=\"background-color:#080808\">gray</span>"),
+            (b"\x1b[3m\x1b[4mstyled", "<span style=\"font-style:italic;text-decoration:underline\">styled</span>"),
+            (b"\x1b]8;;https://example.com\x1b\\docs\x1b]8;;\x1b\\", "docs"),
+            (b"\x1b[31m\x1b[0m\n", "\n"),
+            ("caf\u{e9} \x1b[2m\u{2713}".as_bytes(), "caf\u{e9} <span style=\"opacity:0.7\">\u{2713}</span>"),
+        ];
+
+        for (input, expected) in inputs {
+            let mut out = Vec::new();
+
+            convert(input, &mut out);
+
+            assert_eq!(expected, String::from_utf8(out).unwrap());
+        }
+    }
+}
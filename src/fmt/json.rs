@@ -0,0 +1,298 @@
+//! JSON formatting for log records.
+//! 
+//! Records are written as a single-line JSON object without pulling in a
+//! serialization library.
+
+use std::{io, fmt};
+use std::io::prelude::*;
+use std::time::Duration;
+
+use log::Record;
+
+use super::{Formatter, TimestampPrecision, UtcOffset};
+
+/// The JSON format for log records.
+pub(super) struct JsonFormat<'a> {
+    pub(super) timestamp: Option<TimestampPrecision>,
+    pub(super) timestamp_cache: Option<Duration>,
+    pub(super) timestamp_offset: Option<UtcOffset>,
+    pub(super) fields: bool,
+    pub(super) line_terminator: &'a [u8],
+    pub(super) metadata: &'a [(String, String)],
+    pub(super) buf: &'a mut Formatter,
+}
+
+impl<'a> JsonFormat<'a> {
+    pub(super) fn write(self, record: &Record) -> io::Result<()> {
+        let ts = self.buf.record_timestamp(self.timestamp, self.timestamp_cache, self.timestamp_offset, false);
+        let mut object = JsonObject::new(self.buf)?;
+
+        if let Some(ts) = ts {
+            object.field("timestamp", &ts)?;
+        }
+
+        object.field("level", &record.level())?;
+        object.field("target", &record.target())?;
+
+        if let Some(module_path) = record.module_path() {
+            object.field("module_path", &module_path)?;
+        }
+
+        if let Some(line) = record.line() {
+            object.raw_field("line", &line)?;
+        }
+
+        object.field("message", record.args())?;
+
+        if self.fields {
+            write_fields(object.object("fields")?, record)?;
+        }
+        else {
+            #[cfg(all(feature = "kv", not(feature = "kv_serde")))]
+            super::kv::visit(record, |key, value| object.field(key.as_str(), &value))?;
+
+            #[cfg(feature = "kv_serde")]
+            super::kv::visit(record, |key, value| {
+                match super::kv::to_json(&value) {
+                    Some(json) => object.raw_field(key.as_str(), &json),
+                    None => object.field(key.as_str(), &value),
+                }
+            })?;
+        }
+
+        for &(ref key, ref value) in self.metadata {
+            object.field(key, value)?;
+        }
+
+        object.finish()?;
+
+        self.buf.write_all(self.line_terminator)
+    }
+}
+
+/// Write the key-value pairs of a record as a JSON object, keeping their types.
+#[cfg(feature = "kv")]
+fn write_fields(mut fields: JsonObject, record: &Record) -> io::Result<()> {
+    super::kv::visit(record, |key, value| {
+        match super::kv::typed_json(&value) {
+            Some(json) => fields.raw_field(key.as_str(), &json),
+            None => fields.field(key.as_str(), &value),
+        }
+    })?;
+
+    fields.finish()
+}
+
+#[cfg(not(feature = "kv"))]
+fn write_fields(fields: JsonObject, _: &Record) -> io::Result<()> {
+    fields.finish()
+}
+
+/// A JSON object that's being written.
+pub(super) struct JsonObject<'a> {
+    buf: &'a mut Formatter,
+    has_fields: bool,
+}
+
+impl<'a> JsonObject<'a> {
+    pub(super) fn new(buf: &'a mut Formatter) -> io::Result<Self> {
+        buf.write_all(b"{")?;
+
+        Ok(JsonObject {
+            buf: buf,
+            has_fields: false,
+        })
+    }
+
+    /// Write a field with a string value.
+    pub(super) fn field(&mut self, key: &str, value: &fmt::Display) -> io::Result<()> {
+        self.key(key)?;
+        write_str(self.buf, value)
+    }
+
+    /// Write a field with a value that's already valid JSON, like a number.
+    pub(super) fn raw_field(&mut self, key: &str, value: &fmt::Display) -> io::Result<()> {
+        self.key(key)?;
+        write!(self.buf, "{}", value)
+    }
+
+    /// Write a field with an object value.
+    /// 
+    /// The object has to be finished before any more fields are written.
+    pub(super) fn object(&mut self, key: &str) -> io::Result<JsonObject<'_>> {
+        self.key(key)?;
+        JsonObject::new(self.buf)
+    }
+
+    pub(super) fn finish(self) -> io::Result<()> {
+        self.buf.write_all(b"}")
+    }
+
+    fn key(&mut self, key: &str) -> io::Result<()> {
+        if self.has_fields {
+            self.buf.write_all(b",")?;
+        }
+
+        self.has_fields = true;
+
+        write_str(self.buf, &key)?;
+        self.buf.write_all(b":")
+    }
+}
+
+/// Write a value as an escaped JSON string.
+pub(super) fn write_str(buf: &mut Formatter, value: &fmt::Display) -> io::Result<()> {
+    use std::fmt::Write as FmtWrite;
+
+    buf.write_all(b"\"")?;
+
+    {
+        let mut escape = Escape {
+            buf: buf,
+            error: Ok(()),
+        };
+
+        if write!(escape, "{}", value).is_err() {
+            // Prefer the underlying I/O error if there was one
+            escape.error?;
+
+            return Err(io::Error::new(io::ErrorKind::Other, "formatter error"));
+        }
+    }
+
+    buf.write_all(b"\"")
+}
+
+/// An adapter that escapes formatted values as they're written.
+struct Escape<'a> {
+    buf: &'a mut Formatter,
+    error: io::Result<()>,
+}
+
+impl<'a> Escape<'a> {
+    fn write_escaped(&mut self, s: &str) -> io::Result<()> {
+        let bytes = s.as_bytes();
+        let mut start = 0;
+
+        for (i, &b) in bytes.iter().enumerate() {
+            let escaped: &[u8] = match b {
+                b'"' => b"\\\"",
+                b'\\' => b"\\\\",
+                b'\n' => b"\\n",
+                b'\r' => b"\\r",
+                b'\t' => b"\\t",
+                _ if b < 0x20 => b"",
+                _ => continue,
+            };
+
+            self.buf.write_all(&bytes[start..i])?;
+
+            if escaped.is_empty() {
+                write!(self.buf, "\\u{:04x}", b)?;
+            }
+            else {
+                self.buf.write_all(escaped)?;
+            }
+
+            start = i + 1;
+        }
+
+        self.buf.write_all(&bytes[start..])
+    }
+}
+
+impl<'a> fmt::Write for Escape<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        match self.write_escaped(s) {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                self.error = Err(e);
+                Err(fmt::Error)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::WriteStyle;
+    use testing::written;
+
+    #[test]
+    fn write_str_escapes() {
+        let mut buf = Formatter::in_memory(WriteStyle::Never);
+
+        write_str(&mut buf, &"a \"quoted\"\nmessage\\ with\ttabs and \x07").unwrap();
+
+        assert_eq!(r#""a \"quoted\"\nmessage\\ with\ttabs and \u0007""#, written(&buf));
+    }
+
+    #[test]
+    fn object_fields() {
+        let mut buf = Formatter::in_memory(WriteStyle::Never);
+
+        {
+            let mut object = JsonObject::new(&mut buf).unwrap();
+
+            object.field("message", &"log message").unwrap();
+            object.raw_field("line", &42).unwrap();
+            object.finish().unwrap();
+        }
+
+        assert_eq!(r#"{"message":"log message","line":42}"#, written(&buf));
+    }
+
+    #[test]
+    fn format_omits_missing_fields() {
+        let mut buf = Formatter::in_memory(WriteStyle::Never);
+
+        JsonFormat { timestamp: Some(TimestampPrecision::Seconds), timestamp_cache: None, timestamp_offset: None, fields: false, line_terminator: b"\n", metadata: &[("region".to_owned(), "us-east-1".to_owned())], buf: &mut buf }
+            .write(&Record::builder()
+                .target("json")
+                .args(format_args!("a \"message\""))
+                .build())
+            .unwrap();
+
+        let written = written(&buf);
+
+        assert!(written.starts_with(r#"{"timestamp":""#));
+        assert!(written.ends_with(r#"","level":"INFO","target":"json","message":"a \"message\"","region":"us-east-1"}
+"#));
+    }
+
+    #[test]
+    fn format_nested_fields() {
+        let mut buf = Formatter::in_memory(WriteStyle::Never);
+
+        JsonFormat { timestamp: None, timestamp_cache: None, timestamp_offset: None, fields: true, line_terminator: b"\n", metadata: &[("region".to_owned(), "us-east-1".to_owned())], buf: &mut buf }
+            .write(&Record::builder()
+                .target("json")
+                .args(format_args!("log message"))
+                .build())
+            .unwrap();
+
+        assert_eq!(r#"{"level":"INFO","target":"json","message":"log message","fields":{},"region":"us-east-1"}
+"#, written(&buf));
+    }
+
+    #[test]
+    #[cfg(feature = "kv")]
+    fn format_nested_typed_fields() {
+        use log::kv::Value;
+
+        let kvs = [("user", Value::from("alice")), ("attempt", Value::from(3)), ("retry", Value::from(true))];
+        let mut buf = Formatter::in_memory(WriteStyle::Never);
+
+        JsonFormat { timestamp: None, timestamp_cache: None, timestamp_offset: None, fields: true, line_terminator: b"\n", metadata: &[], buf: &mut buf }
+            .write(&Record::builder()
+                .target("json")
+                .args(format_args!("log message"))
+                .key_values(&kvs)
+                .build())
+            .unwrap();
+
+        assert_eq!(r#"{"level":"INFO","target":"json","message":"log message","fields":{"user":"alice","attempt":3,"retry":true}}
+"#, written(&buf));
+    }
+}
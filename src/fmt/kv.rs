@@ -0,0 +1,161 @@
+//! Structured key-value pairs on log records.
+//!
+//! This module is only available with the `kv` feature. With the `kv_serde`
+//! feature, values can also be serialized as JSON.
+
+use std::io;
+
+use log::Record;
+use log::kv::{self, Key, Value, VisitSource};
+
+/// Visit each key-value pair on a record.
+///
+/// Visiting stops at the first error returned by `visit_pair`.
+pub(super) fn visit<F>(record: &Record, visit_pair: F) -> io::Result<()>
+where
+    F: for<'kvs> FnMut(Key<'kvs>, Value<'kvs>) -> io::Result<()>,
+{
+    let mut visitor = Visitor {
+        visit_pair: visit_pair,
+        error: Ok(()),
+    };
+
+    if record.key_values().visit(&mut visitor).is_err() {
+        // Prefer the underlying I/O error if there was one
+        visitor.error?;
+
+        return Err(io::Error::new(io::ErrorKind::Other, "failed to visit key-values"));
+    }
+
+    Ok(())
+}
+
+/// An adapter that keeps the I/O errors from visiting key-value pairs.
+struct Visitor<F> {
+    visit_pair: F,
+    error: io::Result<()>,
+}
+
+impl<'kvs, F> VisitSource<'kvs> for Visitor<F>
+where
+    F: for<'a> FnMut(Key<'a>, Value<'a>) -> io::Result<()>,
+{
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        match (self.visit_pair)(key, value) {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                self.error = Err(e);
+                Err(kv::Error::msg("failed to write key-value"))
+            }
+        }
+    }
+}
+
+/// Serialize a value as compact JSON.
+///
+/// Returns `None` if the value can't be serialized, like a map with keys that
+/// aren't strings.
+#[cfg(feature = "kv_serde")]
+pub(super) fn to_json(value: &Value) -> Option<String> {
+    ::serde_json::to_string(value).ok()
+}
+
+/// Serialize a value as compact JSON if it's a map or a sequence.
+///
+/// Other values are written the same way with or without JSON, so only
+/// nested structures are worth serializing.
+#[cfg(feature = "kv_serde")]
+pub(super) fn nested_json(value: &Value) -> Option<String> {
+    match to_json(value) {
+        Some(json) => if json.starts_with('{') || json.starts_with('[') {
+            Some(json)
+        }
+        else {
+            None
+        },
+        None => None,
+    }
+}
+
+#[cfg(not(feature = "kv_serde"))]
+pub(super) fn nested_json(_: &Value) -> Option<String> {
+    None
+}
+
+/// Write a value as a JSON value that keeps its type.
+///
+/// Booleans and numbers are written as themselves. With the `kv_serde`
+/// feature, any value that can be serialized is written as JSON. Returns
+/// `None` for other values, which should be written as strings.
+pub(super) fn typed_json(value: &Value) -> Option<String> {
+    #[cfg(feature = "kv_serde")]
+    {
+        if let Some(json) = to_json(value) {
+            return Some(json);
+        }
+    }
+
+    if let Some(b) = value.to_bool() {
+        return Some(b.to_string());
+    }
+
+    if let Some(n) = value.to_u64() {
+        return Some(n.to_string());
+    }
+
+    if let Some(n) = value.to_i64() {
+        return Some(n.to_string());
+    }
+
+    // JSON doesn't have infinities or NaN
+    value.to_f64().filter(|n| n.is_finite()).map(|n| n.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "kv_serde")]
+    fn serialize_json() {
+        use std::collections::BTreeMap;
+
+        let mut map = BTreeMap::new();
+        map.insert("b", 2);
+        map.insert("a", 1);
+
+        let mut bad_map = BTreeMap::new();
+        bad_map.insert(vec![1], 1);
+
+        let ids = vec![1, 2];
+
+        let inputs = vec![
+            (Value::from_serde(&map), Some("{\"a\":1,\"b\":2}"), Some("{\"a\":1,\"b\":2}")),
+            (Value::from_serde(&ids), Some("[1,2]"), Some("[1,2]")),
+            (Value::from(42), Some("42"), None),
+            (Value::from("text"), Some("\"text\""), None),
+            (Value::from_serde(&bad_map), None, None),
+        ];
+
+        for (value, json, nested) in inputs {
+            assert_eq!(json.map(|json| json.to_owned()), to_json(&value));
+            assert_eq!(nested.map(|nested| nested.to_owned()), nested_json(&value));
+        }
+    }
+
+    #[test]
+    fn primitive_typed_json() {
+        let inputs = vec![
+            (Value::from(true), Some("true")),
+            (Value::from(42u8), Some("42")),
+            (Value::from(-7i64), Some("-7")),
+            (Value::from(1.5f64), Some("1.5")),
+            (Value::from(f64::NAN), if cfg!(feature = "kv_serde") { Some("null") } else { None }),
+            (Value::from("42"), if cfg!(feature = "kv_serde") { Some("\"42\"") } else { None }),
+        ];
+
+        for (value, expected) in inputs {
+            assert_eq!(expected.map(|json| json.to_owned()), typed_json(&value));
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! `logfmt` formatting for log records.
+//!
+//! Records are written as a single line of `key=value` pairs. Keys and values
+//! that contain whitespace, `=` or `"` are quoted and escaped.
+
+use std::io;
+use std::io::prelude::*;
+use std::time::Duration;
+
+use log::{Level, Record};
+
+use super::{Formatter, TimestampPrecision, UtcOffset};
+use super::json;
+
+/// The `logfmt` format for log records.
+pub(super) struct LogfmtFormat<'a> {
+    pub(super) timestamp: Option<TimestampPrecision>,
+    pub(super) timestamp_cache: Option<Duration>,
+    pub(super) timestamp_offset: Option<UtcOffset>,
+    pub(super) line_terminator: &'a [u8],
+    pub(super) metadata: &'a [(String, String)],
+    pub(super) buf: &'a mut Formatter,
+}
+
+impl<'a> LogfmtFormat<'a> {
+    pub(super) fn write(self, record: &Record) -> io::Result<()> {
+        if let Some(ts) = self.buf.record_timestamp(self.timestamp, self.timestamp_cache, self.timestamp_offset, false) {
+            write!(self.buf, "ts={} ", ts)?;
+        }
+
+        write!(self.buf, "level={} ", level_name(record.level()))?;
+
+        write_pair(self.buf, "target", record.target())?;
+
+        #[cfg(feature = "kv")]
+        {
+            let buf = &mut *self.buf;
+
+            super::kv::visit(record, |key, value| {
+                match (value.to_borrowed_str(), super::kv::nested_json(&value)) {
+                    (Some(value), _) => write_pair(buf, key.as_str(), value),
+                    (None, Some(json)) => write_pair(buf, key.as_str(), &json),
+                    (None, None) => write_pair(buf, key.as_str(), &value.to_string()),
+                }
+            })?;
+        }
+
+        for &(ref key, ref value) in self.metadata {
+            write_pair(self.buf, key, value)?;
+        }
+
+        // The message is always quoted so it doesn't need to be checked first
+        self.buf.write_all(b"msg=")?;
+        json::write_str(self.buf, record.args())?;
+
+        self.buf.write_all(self.line_terminator)
+    }
+}
+
+fn level_name(level: Level) -> &'static str {
+    match level {
+        Level::Error => "error",
+        Level::Warn => "warn",
+        Level::Info => "info",
+        Level::Debug => "debug",
+        Level::Trace => "trace",
+    }
+}
+
+/// Write a `key=value` pair followed by a space.
+fn write_pair(buf: &mut Formatter, key: &str, value: &str) -> io::Result<()> {
+    write_part(buf, key)?;
+    buf.write_all(b"=")?;
+    write_part(buf, value)?;
+
+    buf.write_all(b" ")
+}
+
+/// Write a key or value, quoted if it could be confused with the
+/// surrounding pairs.
+fn write_part(buf: &mut Formatter, part: &str) -> io::Result<()> {
+    if needs_quotes(part) {
+        json::write_str(buf, &part)
+    }
+    else {
+        buf.write_all(part.as_bytes())
+    }
+}
+
+fn needs_quotes(value: &str) -> bool {
+    value.is_empty() || value.contains(|c: char| c.is_whitespace() || c.is_control() || c == '=' || c == '"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::WriteStyle;
+    use testing::written;
+
+    #[test]
+    fn quote_values() {
+        let inputs = vec![
+            ("key", "myapp", "key=myapp "),
+            ("key", "", "key=\"\" "),
+            ("key", "my app", "key=\"my app\" "),
+            ("key", "a=b", "key=\"a=b\" "),
+            ("key", "a \"quote\"", "key=\"a \\\"quote\\\"\" "),
+            ("my key", "myapp", "\"my key\"=myapp "),
+            ("a=b", "myapp", "\"a=b\"=myapp "),
+            ("\"key\"", "myapp", "\"\\\"key\\\"\"=myapp "),
+        ];
+
+        for (key, value, expected) in inputs {
+            let mut buf = Formatter::in_memory(WriteStyle::Never);
+
+            write_pair(&mut buf, key, value).unwrap();
+
+            assert_eq!(expected, written(&buf));
+        }
+    }
+
+    #[test]
+    fn format_message_last() {
+        let mut buf = Formatter::in_memory(WriteStyle::Never);
+
+        LogfmtFormat { timestamp: Some(TimestampPrecision::Seconds), timestamp_cache: None, timestamp_offset: None, line_terminator: b"\n", metadata: &[("region".to_owned(), "us east".to_owned())], buf: &mut buf }
+            .write(&Record::builder()
+                .target("myapp")
+                .args(format_args!("log message"))
+                .build())
+            .unwrap();
+
+        let written = written(&buf);
+
+        assert!(written.starts_with("ts="));
+        assert!(written.ends_with(" level=info target=myapp region=\"us east\" msg=\"log message\"\n"));
+    }
+}
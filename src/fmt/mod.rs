@@ -0,0 +1,6577 @@
+//! Formatting for log records.
+//! 
+//! This module contains a [`Formatter`] that can be used to format log records
+//! into without needing temporary allocations. Usually you won't need to worry
+//! about the contents of this module and can use the `Formatter` like an ordinary
+//! [`Write`].
+//! 
+//! # Formatting log records
+//! 
+//! The format used to print log records can be customised using the [`Builder::format`]
+//! method.
+//! Custom formats can apply different color and weight to printed values using
+//! [`Style`] builders.
+//! 
+//! ```
+//! use std::io::Write;
+//! use env_logger::fmt::Color;
+//! 
+//! let mut builder = env_logger::Builder::new();
+//! 
+//! builder.format(|buf, record| {
+//!     let mut level_style = buf.style();
+//! 
+//!     level_style.set_color(Color::Red).set_bold(true);
+//! 
+//!     writeln!(buf, "{}: {}",
+//!         level_style.value(record.level()),
+//!         record.args())
+//! });
+//! ```
+//! 
+//! [`Formatter`]: struct.Formatter.html
+//! [`Style`]: struct.Style.html
+//! [`Builder::format`]: ../struct.Builder.html#method.format
+//! [`Write`]: https://doc.rust-lang.org/stable/std/io/trait.Write.html
+
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::io::prelude::*;
+use std::{env, io, fmt, mem, thread};
+use std::ffi::OsString;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+
+use log::{Level, LevelFilter, Record};
+use termcolor::{Buffer, BufferWriter, ColorSpec, WriteColor};
+use chrono::{DateTime, FixedOffset, Local, Offset, TimeZone, Utc};
+use chrono::format::{Item, StrftimeItems};
+
+mod ansi;
+mod html;
+mod terminal;
+mod json;
+mod logfmt;
+#[cfg(feature = "kv")]
+mod kv;
+#[cfg(feature = "background")]
+mod background;
+#[cfg(feature = "background")]
+mod reorder;
+#[cfg(windows)]
+mod debug_output;
+mod rotating;
+
+use self::json::JsonFormat;
+use self::logfmt::LogfmtFormat;
+pub(crate) use self::rotating::FileTarget;
+
+pub use termcolor::{Color, ColorChoice, ParseColorError};
+#[cfg(feature = "background")]
+pub use self::background::Overflow;
+#[cfg(feature = "background")]
+use self::background::Fifo;
+#[cfg(feature = "background")]
+use self::reorder::Reorder;
+
+/// A formatter to write logs into.
+/// 
+/// `Formatter` implements the standard [`Write`] trait for writing log records.
+/// It also supports terminal colors, through the [`style`] method.
+/// 
+/// # Examples
+/// 
+/// Use the [`writeln`] macro to easily format a log record:
+/// 
+/// ```
+/// use std::io::Write;
+/// 
+/// let mut builder = env_logger::Builder::new();
+/// 
+/// builder.format(|buf, record| writeln!(buf, "{}: {}", record.level(), record.args()));
+/// ```
+/// 
+/// [`Write`]: https://doc.rust-lang.org/stable/std/io/trait.Write.html
+/// [`writeln`]: https://doc.rust-lang.org/stable/std/macro.writeln.html
+/// [`style`]: #method.style
+/// 
+/// # Threads
+/// 
+/// By default, a `Formatter` and its [`Style`]s share their buffer through a
+/// reference-counted `RefCell`, so they can't be sent to other threads.
+/// Enabling the `sync-formatter` feature shares the buffer through an `Arc`
+/// and a `Mutex` instead, which makes `Formatter` and `Style` both `Send`
+/// and `Sync`. That costs an atomic reference count and a lock on every write,
+/// so only enable it if records need to be formatted off the logging thread.
+/// 
+/// [`Style`]: struct.Style.html
+pub struct Formatter {
+    buf: SharedBuffer,
+    write_style: WriteStyle,
+    timestamp_cache: Option<TimestampCache>,
+    level_color: Option<Arc<LevelColorFn>>,
+    clock: Option<Arc<ClockFn>>,
+    previous_record: Arc<PreviousRecord>,
+    // Whether or not the formatted records are printed
+    prints: bool,
+    terminal: Option<terminal::Stream>,
+}
+
+/// The time the previous relative timestamp was taken.
+/// 
+/// It's shared by every formatter printed to the same writer.
+pub(crate) type PreviousRecord = Mutex<Option<Instant>>;
+
+/// A formatted timestamp that's reused by the built-in formats.
+struct TimestampCache {
+    precision: TimestampPrecision,
+    offset: Option<UtcOffset>,
+    time_only: bool,
+    refreshed: Instant,
+    formatted: Arc<str>,
+}
+
+/// The timestamp of a record written by one of the built-in formats.
+pub(crate) enum RecordTimestamp {
+    Fresh(Timestamp),
+    Cached(Arc<str>),
+}
+
+/// A set of styles to apply to the terminal output.
+/// 
+/// Call [`Formatter::style`] to get a `Style` and use the builder methods to 
+/// set styling properties, like [color] and [weight].
+/// To print a value using the style, wrap it in a call to [`value`] when the log
+/// record is formatted.
+/// 
+/// # Examples
+/// 
+/// Create a bold, red colored style and use it to print the log level:
+/// 
+/// ```
+/// use std::io::Write;
+/// use env_logger::fmt::Color;
+/// 
+/// let mut builder = env_logger::Builder::new();
+/// 
+/// builder.format(|buf, record| {
+///     let mut level_style = buf.style();
+/// 
+///     level_style.set_color(Color::Red).set_bold(true);
+/// 
+///     writeln!(buf, "{}: {}",
+///         level_style.value(record.level()),
+///         record.args())
+/// });
+/// ```
+/// 
+/// Styles can be re-used to output multiple values:
+/// 
+/// ```
+/// use std::io::Write;
+/// use env_logger::fmt::Color;
+/// 
+/// let mut builder = env_logger::Builder::new();
+/// 
+/// builder.format(|buf, record| {
+///     let mut bold = buf.style();
+/// 
+///     bold.set_bold(true);
+/// 
+///     writeln!(buf, "{}: {} {}",
+///         bold.value(record.level()),
+///         bold.value("some bold text"),
+///         record.args())
+/// });
+/// ```
+/// 
+/// Styles are tied to the formatter that created them. To define styles
+/// ahead of time, like a theme that's shared by every record, use a
+/// [`StyleSpec`] and apply it with [`Formatter::apply`]:
+/// 
+/// ```
+/// use std::io::Write;
+/// use env_logger::fmt::{Color, StyleSpec};
+/// 
+/// let mut warning = StyleSpec::new();
+/// warning.set_color(Color::Yellow);
+/// 
+/// let mut builder = env_logger::Builder::new();
+/// 
+/// builder.format(move |buf, record| {
+///     writeln!(buf, "{}: {}",
+///         buf.apply(&warning, record.level()),
+///         record.args())
+/// });
+/// ```
+/// 
+/// [`Formatter::style`]: struct.Formatter.html#method.style
+/// [`StyleSpec`]: struct.StyleSpec.html
+/// [`Formatter::apply`]: struct.Formatter.html#method.apply
+/// [color]: #method.set_color
+/// [weight]: #method.set_bold
+/// [`value`]: #method.value
+#[derive(Clone)]
+pub struct Style {
+    buf: SharedBuffer,
+    spec: ColorSpec,
+}
+
+/// A set of text styles that isn't tied to a formatter.
+/// 
+/// Unlike a [`Style`], a `StyleSpec` can be defined once, like in a theme
+/// that's shared by every record, and applied to values with
+/// [`Formatter::apply`]. It's `Send` and `Sync`, so it can be moved into a
+/// format closure.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use std::io::Write;
+/// use env_logger::fmt::{Color, StyleSpec};
+/// 
+/// let mut error = StyleSpec::new();
+/// error.set_color(Color::Red).set_bold(true);
+/// 
+/// let mut builder = env_logger::Builder::new();
+/// 
+/// builder.format(move |buf, record| {
+///     writeln!(buf, "{}: {}", buf.apply(&error, record.level()), record.args())
+/// });
+/// ```
+/// 
+/// [`Style`]: struct.Style.html
+/// [`Formatter::apply`]: struct.Formatter.html#method.apply
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StyleSpec {
+    spec: ColorSpec,
+}
+
+/// The buffer shared between a `Formatter` and its `Style`s.
+#[cfg(not(feature = "sync-formatter"))]
+#[derive(Clone)]
+struct SharedBuffer {
+    buf: ::std::rc::Rc<::std::cell::RefCell<Buffer>>,
+    windows_bold: WindowsBold,
+}
+
+#[cfg(not(feature = "sync-formatter"))]
+type BufferRef<'a> = ::std::cell::Ref<'a, Buffer>;
+
+#[cfg(not(feature = "sync-formatter"))]
+impl SharedBuffer {
+    fn new(buf: Buffer, windows_bold: WindowsBold) -> Self {
+        SharedBuffer {
+            buf: ::std::rc::Rc::new(::std::cell::RefCell::new(buf)),
+            windows_bold: windows_bold,
+        }
+    }
+
+    fn borrow(&self) -> BufferRef {
+        self.buf.borrow()
+    }
+
+    fn borrow_mut(&self) -> ::std::cell::RefMut<Buffer> {
+        self.buf.borrow_mut()
+    }
+}
+
+/// The buffer shared between a `Formatter` and its `Style`s.
+#[cfg(feature = "sync-formatter")]
+#[derive(Clone)]
+struct SharedBuffer {
+    buf: ::std::sync::Arc<Mutex<Buffer>>,
+    windows_bold: WindowsBold,
+}
+
+#[cfg(feature = "sync-formatter")]
+type BufferRef<'a> = ::std::sync::MutexGuard<'a, Buffer>;
+
+#[cfg(feature = "sync-formatter")]
+impl SharedBuffer {
+    fn new(buf: Buffer, windows_bold: WindowsBold) -> Self {
+        SharedBuffer {
+            buf: ::std::sync::Arc::new(Mutex::new(buf)),
+            windows_bold: windows_bold,
+        }
+    }
+
+    fn borrow(&self) -> BufferRef {
+        self.buf.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn borrow_mut(&self) -> ::std::sync::MutexGuard<Buffer> {
+        self.borrow()
+    }
+}
+
+impl SharedBuffer {
+    /// Write the following text in the style `spec`.
+    fn set_color(&self, spec: &ColorSpec) -> io::Result<()> {
+        let spec = console_spec(spec, self.windows_bold);
+
+        self.borrow_mut().set_color(&spec)
+    }
+}
+
+/// The bytes written to a [`Formatter`] so far.
+/// 
+/// It is the result of calling [`Formatter::buffer_bytes`], and dereferences
+/// to a byte slice. Nothing else can be written to the formatter while it's
+/// borrowed.
+/// 
+/// [`Formatter`]: struct.Formatter.html
+/// [`Formatter::buffer_bytes`]: struct.Formatter.html#method.buffer_bytes
+pub struct BufferBytes<'a> {
+    buf: BufferRef<'a>,
+}
+
+impl<'a> ::std::ops::Deref for BufferBytes<'a> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.buf.as_slice()
+    }
+}
+
+/// A value that can be printed using the given styles.
+/// 
+/// It is the result of calling [`Style::value`].
+/// 
+/// [`Style::value`]: struct.Style.html#method.value
+pub struct StyledValue<'a, T> {
+    style: Cow<'a, Style>,
+    value: T,
+}
+
+/// A value that's written as a terminal hyperlink.
+/// 
+/// It is the result of calling [`Formatter::hyperlink`].
+/// 
+/// [`Formatter::hyperlink`]: struct.Formatter.html#method.hyperlink
+#[derive(Debug)]
+pub struct Hyperlink<'a, T> {
+    url: &'a str,
+    text: T,
+    enabled: bool,
+}
+
+/// An [RFC3339] formatted timestamp.
+/// 
+/// The timestamp implements [`Display`] and can be written to a [`Formatter`].
+/// UTC timestamps end with a `Z`, timestamps in other timezones end with
+/// their numeric offset, like `+02:00`. Use [`numeric_offset`] to write
+/// `+00:00` instead of `Z`, [`time_only`] to leave out the date and offset,
+/// or [`week_date`] and [`ordinal_date`] to write the date in one of the
+/// other ISO 8601 forms.
+/// 
+/// [`numeric_offset`]: #method.numeric_offset
+/// [`time_only`]: #method.time_only
+/// [`week_date`]: #method.week_date
+/// [`ordinal_date`]: #method.ordinal_date
+/// [RFC3339]: https://www.ietf.org/rfc/rfc3339.txt
+/// [`Display`]: https://doc.rust-lang.org/stable/std/fmt/trait.Display.html
+/// [`Formatter`]: struct.Formatter.html
+pub struct Timestamp {
+    time: DateTime<FixedOffset>,
+    offset: TimestampOffset,
+    precision: TimestampPrecision,
+    time_only: bool,
+    date: TimestampDate,
+}
+
+/// The precision of a [`Timestamp`]'s fractional seconds.
+/// 
+/// [`Timestamp`]: struct.Timestamp.html
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TimestampPrecision {
+    /// Write whole seconds only.
+    Seconds,
+    /// Write seconds with 3 fractional digits.
+    Millis,
+    /// Write seconds with 6 fractional digits.
+    Micros,
+    /// Write seconds with 9 fractional digits.
+    Nanos,
+}
+
+impl Default for TimestampPrecision {
+    fn default() -> Self {
+        TimestampPrecision::Seconds
+    }
+}
+
+/// A fixed offset from UTC that timestamps can be written in.
+/// 
+/// # Examples
+/// 
+/// Japan Standard Time is 9 hours east of UTC:
+/// 
+/// ```
+/// use env_logger::fmt::UtcOffset;
+/// 
+/// let jst = UtcOffset::east(9 * 3600).unwrap();
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct UtcOffset {
+    offset: FixedOffset,
+}
+
+impl UtcOffset {
+    /// An offset of `secs` seconds east of UTC.
+    /// 
+    /// Returns `None` if the offset is a day or more.
+    pub fn east(secs: i32) -> Option<UtcOffset> {
+        FixedOffset::east_opt(secs).map(|offset| UtcOffset { offset: offset })
+    }
+
+    /// An offset of `secs` seconds west of UTC.
+    /// 
+    /// Returns `None` if the offset is a day or more.
+    pub fn west(secs: i32) -> Option<UtcOffset> {
+        FixedOffset::west_opt(secs).map(|offset| UtcOffset { offset: offset })
+    }
+
+    /// The number of seconds east of UTC.
+    pub fn secs_east(&self) -> i32 {
+        self.offset.local_minus_utc()
+    }
+}
+
+/// How to pad log levels to a fixed width.
+/// 
+/// The longest level names are 5 characters, so padded levels are always 5
+/// characters wide.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum LevelPadding {
+    /// Pad levels on the left, so they're aligned to the right.
+    Left,
+    /// Pad levels on the right, so they're aligned to the left.
+    Right,
+    /// Don't pad levels.
+    Off,
+}
+
+impl Default for LevelPadding {
+    fn default() -> Self {
+        LevelPadding::Left
+    }
+}
+
+/// A part of a record written by the default format.
+/// 
+/// Use [`Builder::format_field_order`] to pick which fields are written and
+/// in what order. Each field is written with the same options it has in the
+/// default layout, like the level's padding or the timestamp's precision.
+/// 
+/// [`Builder::format_field_order`]: ../struct.Builder.html#method.format_field_order
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Field {
+    /// The record's sequence number.
+    Sequence,
+    /// The time the record was formatted, if timestamps are enabled.
+    Timestamp,
+    /// The record's level.
+    Level,
+    /// The thread the record was logged on, like `[main]`.
+    Thread,
+    /// The record's target.
+    Target,
+    /// The module path the record was logged from, if it has one.
+    ModulePath,
+    /// The file and line the record was logged from, like `src/main.rs:10`,
+    /// if it has a file.
+    File,
+    /// The record's message, followed by its key-values and any metadata.
+    Message,
+}
+
+/// A log level padded to a fixed width.
+/// 
+/// It is the result of calling [`Formatter::padded_level`].
+/// The level implements [`Display`] and can be written to a [`Formatter`].
+/// 
+/// [`Formatter::padded_level`]: struct.Formatter.html#method.padded_level
+/// [`Display`]: https://doc.rust-lang.org/stable/std/fmt/trait.Display.html
+/// [`Formatter`]: struct.Formatter.html
+#[derive(Clone, Copy, Debug)]
+pub struct PaddedLevel {
+    level: Level,
+    padding: LevelPadding,
+    lowercase: bool,
+}
+
+/// A number that's right-aligned to a fixed width.
+/// 
+/// It is the result of calling [`Formatter::padded_int`].
+/// The number implements [`Display`] and can be written to a [`Formatter`].
+/// 
+/// [`Formatter::padded_int`]: struct.Formatter.html#method.padded_int
+/// [`Display`]: https://doc.rust-lang.org/stable/std/fmt/trait.Display.html
+/// [`Formatter`]: struct.Formatter.html
+#[derive(Clone, Copy, Debug)]
+pub struct PaddedInt<T> {
+    value: T,
+    width: usize,
+}
+
+/// The thread a record is being formatted on.
+/// 
+/// It is the result of calling [`Formatter::current_thread`].
+/// The thread implements [`Display`] and can be written to a [`Formatter`].
+/// It's written as its name if it has one, or its numeric id otherwise.
+/// Control characters in the name are escaped.
+/// 
+/// [`Formatter::current_thread`]: struct.Formatter.html#method.current_thread
+/// [`Display`]: https://doc.rust-lang.org/stable/std/fmt/trait.Display.html
+/// [`Formatter`]: struct.Formatter.html
+#[derive(Debug)]
+pub struct CurrentThread {
+    thread: thread::Thread,
+    id: u64,
+}
+
+impl CurrentThread {
+    /// The name of the thread, if it has one.
+    pub fn name(&self) -> Option<&str> {
+        self.thread.name()
+    }
+
+    /// The numeric id of the thread.
+    /// 
+    /// Ids are unique among the threads of a process for as long as it runs.
+    /// They're numbered from 1 in the order threads first ask for theirs, so
+    /// they don't match the ids in the `Debug` output of `std`'s `ThreadId`.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Get a value that writes the name of the thread with control characters
+    /// escaped, or its numeric id if it doesn't have a name.
+    fn name_or_id(&self) -> ThreadName {
+        ThreadName(self)
+    }
+}
+
+struct ThreadName<'a>(&'a CurrentThread);
+
+/// The numeric id of the current thread.
+/// 
+/// `ThreadId` doesn't expose a number, so threads are given their own.
+fn current_thread_id() -> u64 {
+    // The id isn't available while the thread is being torn down
+    THREAD_ID.try_with(|&id| id as u64).unwrap_or(0)
+}
+
+/// A timestamp formatted with a custom format string.
+/// 
+/// It is the result of calling [`Formatter::timestamp_custom`].
+/// The timestamp implements [`Display`] and can be written to a [`Formatter`].
+/// 
+/// [`Formatter::timestamp_custom`]: struct.Formatter.html#method.timestamp_custom
+/// [`Display`]: https://doc.rust-lang.org/stable/std/fmt/trait.Display.html
+/// [`Formatter`]: struct.Formatter.html
+pub struct CustomTimestamp<'a> {
+    time: DateTime<FixedOffset>,
+    format: &'a str,
+}
+
+/// A timestamp written as the number of seconds or milliseconds since the
+/// Unix epoch.
+/// 
+/// It is the result of calling [`Formatter::timestamp_unix`] or
+/// [`Formatter::timestamp_unix_millis`].
+/// The timestamp implements [`Display`] and can be written to a [`Formatter`].
+/// 
+/// [`Formatter::timestamp_unix`]: struct.Formatter.html#method.timestamp_unix
+/// [`Formatter::timestamp_unix_millis`]: struct.Formatter.html#method.timestamp_unix_millis
+/// [`Display`]: https://doc.rust-lang.org/stable/std/fmt/trait.Display.html
+/// [`Formatter`]: struct.Formatter.html
+pub struct UnixTimestamp {
+    time: DateTime<Utc>,
+    millis: bool,
+}
+
+/// The time since the previous record, written in a compact human form like
+/// `+1.2s`.
+/// 
+/// It is the result of calling [`Formatter::timestamp_human_relative`].
+/// The timestamp implements [`Display`] and can be written to a [`Formatter`].
+/// 
+/// [`Formatter::timestamp_human_relative`]: struct.Formatter.html#method.timestamp_human_relative
+/// [`Display`]: https://doc.rust-lang.org/stable/std/fmt/trait.Display.html
+/// [`Formatter`]: struct.Formatter.html
+pub struct RelativeTimestamp {
+    elapsed: Duration,
+}
+
+/// A nested scope that indents the records logged inside it.
+/// 
+/// Entering a scope increases the current thread's scope depth by one, and
+/// dropping the guard restores it. The default format indents the message
+/// of each record by the depth, so the records of nested operations read
+/// like a tree. Custom formats can use [`Formatter::scope_depth`].
+/// 
+/// The depth belongs to the thread that entered the scope, so the guard
+/// can't be sent to another thread.
+/// 
+/// # Examples
+/// 
+/// ```
+/// # #[macro_use] extern crate log;
+/// # extern crate env_logger;
+/// use env_logger::fmt::Scope;
+/// 
+/// # fn main() {
+/// info!("loading config");
+/// 
+/// {
+///     // Bind the guard to a name: `let _ = Scope::enter()` drops it, and
+///     // leaves the scope, straight away
+///     let _scope = Scope::enter();
+/// 
+///     // The message is indented by two spaces
+///     info!("reading config.toml");
+/// }
+/// 
+/// info!("config loaded");
+/// # }
+/// ```
+/// 
+/// [`Formatter::scope_depth`]: struct.Formatter.html#method.scope_depth
+#[derive(Debug)]
+pub struct Scope {
+    // Scopes must be left on the thread that entered them
+    _not_send: PhantomData<*const ()>,
+}
+
+/// How the date of a timestamp is written.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum TimestampDate {
+    /// Write the year, month and day, like `2024-01-16`.
+    Calendar,
+    /// Write the ISO week-numbering year, week and weekday, like `2024-W03-2`.
+    Week,
+    /// Write the year and the day of the year, like `2024-016`.
+    Ordinal,
+}
+
+/// How the offset of a timestamp is written.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum TimestampOffset {
+    /// Write a `Z` for UTC.
+    Zulu,
+    /// Write the numeric offset, like `+02:00`.
+    Numeric,
+}
+
+/// Log target, either `stdout`, `stderr`, both, a custom pipe or nothing.
+/// 
+/// Each record is formatted into its own buffer, no matter how many writes
+/// the format makes, and the whole buffer is written to the target while
+/// holding a lock. Records logged from different threads are never
+/// interleaved, even if the target only accepts part of a buffer at a time.
+/// Standard output and standard error are locked for each record, and pipes
+/// are guarded by a mutex.
+pub enum Target {
+    /// Logs will be sent to standard output.
+    Stdout,
+    /// Logs will be sent to standard error.
+    Stderr,
+    /// Logs at the given level or more severe will be sent to standard error,
+    /// and all other logs will be sent to standard output.
+    /// 
+    /// `Target::Split(Level::Warn)` is the conventional choice, which writes
+    /// warnings and errors to standard error. Each stream detects whether or
+    /// not it supports styles independently.
+    Split(Level),
+    /// Logs will be sent to a custom pipe, like a file or an in-memory buffer.
+    /// 
+    /// Pipes aren't terminals, so styles are only written to them if the
+    /// write style is `WriteStyle::Always`.
+    Pipe(Box<Write + Send>),
+    /// Logs will be formatted and then discarded.
+    /// 
+    /// Records are still formatted as usual, so this is useful for measuring
+    /// the cost of formatting without any I/O, or for silencing a logger
+    /// that's otherwise fully configured. Styles are only written if the
+    /// write style is `WriteStyle::Always`.
+    Null,
+    /// Logs will be sent to the Windows debug output with
+    /// `OutputDebugStringW`.
+    /// 
+    /// The debug output is shown by an attached debugger, like the Output
+    /// window in Visual Studio, or a viewer like DebugView. It's useful for
+    /// GUI applications that don't have a console. Records are dropped if
+    /// nothing is listening.
+    /// 
+    /// Debuggers don't render escape sequences, so styles are only written
+    /// if the write style is `WriteStyle::Always`. This target only exists
+    /// on Windows.
+    #[cfg(windows)]
+    DebugOutput,
+    /// Logs will be sent to each of the given targets.
+    /// 
+    /// Records are formatted once and then printed to every target in order.
+    /// Each target decides whether or not it supports styles on its own, so
+    /// a terminal can be colored while a file isn't. A target that fails
+    /// doesn't stop records from being printed to the others; the first
+    /// error is returned after trying all of them.
+    Multi(Vec<Target>),
+}
+
+impl Default for Target {
+    fn default() -> Self {
+        Target::Stderr
+    }
+}
+
+/// Whether or not to print styles to the target.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum WriteStyle {
+    /// Try to print styles, but don't force the issue.
+    Auto,
+    /// Try very hard to print styles.
+    Always,
+    /// Never print styles.
+    Never,
+}
+
+impl Default for WriteStyle {
+    fn default() -> Self {
+        WriteStyle::Auto
+    }
+}
+
+/// How bold text is written to consoles on Windows.
+/// 
+/// Windows consoles that understand escape sequences usually render bold
+/// text in a brighter color rather than a heavier weight, while the legacy
+/// console API ignores bold altogether. The same style can look different
+/// on each of them. This has no effect on other platforms.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum WindowsBold {
+    /// Write bold text as it is, and leave it to the console.
+    Unchanged,
+    /// Write bold text in an intense color too, so it stands out on every
+    /// console, including the legacy console API.
+    Intense,
+    /// Don't write bold, so it never changes the color of text.
+    Ignore,
+}
+
+impl Default for WindowsBold {
+    fn default() -> Self {
+        WindowsBold::Unchanged
+    }
+}
+
+/// What to do when a record can't be formatted or printed.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ErrorPolicy {
+    /// Drop the record and carry on.
+    Ignore,
+    /// Write the error and the record's message to standard error.
+    Stderr,
+    /// Write the error to standard error and abort the process.
+    Abort,
+}
+
+impl Default for ErrorPolicy {
+    fn default() -> Self {
+        ErrorPolicy::Ignore
+    }
+}
+
+/// A terminal target with color awareness.
+pub(crate) struct Writer {
+    output: Arc<Output>,
+    write_style: WriteStyle,
+    target_write_styles: Vec<(String, WriteStyle)>,
+    flush_level: LevelFilter,
+    windows_bold: WindowsBold,
+    previous_record: Arc<PreviousRecord>,
+    #[cfg(feature = "background")]
+    worker: Option<background::Worker>,
+}
+
+/// The target records are printed to.
+/// 
+/// It's shared with the background thread if there is one.
+struct Output {
+    inner: WritableTarget,
+    ignore_broken_pipe: bool,
+    strip_ansi: bool,
+    html: bool,
+    // Whether a UTF-8 byte order mark still needs to be written to a pipe
+    bom: AtomicBool,
+    // Whether each stream of the target was closed by its reader
+    closed: [AtomicBool; 2],
+}
+
+/// The built target a writer prints to.
+enum WritableTarget {
+    Stdout(BufferWriter),
+    Stderr(BufferWriter),
+    Split {
+        stdout: BufferWriter,
+        stderr: BufferWriter,
+        stderr_level: Level,
+    },
+    Pipe(Mutex<Box<Write + Send>>),
+    Null,
+    #[cfg(windows)]
+    DebugOutput,
+    // Each output is paired with its own style choice
+    Multi(Vec<(Output, WriteStyle)>),
+}
+
+impl Writer {
+    #[cfg(test)]
+    pub(crate) fn write_style(&self) -> WriteStyle {
+        self.write_style
+    }
+
+    pub(crate) fn windows_bold(&self) -> WindowsBold {
+        self.windows_bold
+    }
+
+    /// The time of the previous record printed with a relative timestamp.
+    pub(crate) fn previous_record(&self) -> Arc<PreviousRecord> {
+        self.previous_record.clone()
+    }
+
+    /// The style choice for records with `target`, if it's been replaced.
+    /// 
+    /// The choice for the longest prefix of the target is used. An `Auto`
+    /// choice uses the writer's own style choice.
+    fn target_write_style(&self, target: &str) -> Option<WriteStyle> {
+        let write_style = self.target_write_styles
+            .iter()
+            .filter(|&&(ref prefix, _)| target.starts_with(&**prefix))
+            .max_by_key(|&&(ref prefix, _)| prefix.len())
+            .map(|&(_, write_style)| write_style);
+
+        match write_style {
+            Some(WriteStyle::Auto) | None => None,
+            write_style => write_style,
+        }
+    }
+
+    /// The style choice for records with `target`.
+    pub(crate) fn write_style_for(&self, target: &str) -> WriteStyle {
+        self.target_write_style(target).unwrap_or(self.write_style)
+    }
+
+    /// Create a buffer that can be printed to the target for records at
+    /// `level` with `target`.
+    fn buffer(&self, level: Level, target: &str) -> Buffer {
+        match self.target_write_style(target) {
+            Some(write_style) => non_terminal_buffer(write_style),
+            None => self.output.buffer(self.write_style, level),
+        }
+    }
+
+    /// Whether or not records at `level` are dropped because the stream they're
+    /// printed to was closed by its reader.
+    pub(crate) fn is_closed(&self, level: Level) -> bool {
+        self.output.is_closed(level)
+    }
+
+    /// The standard stream records at `level` are printed to, if there is one.
+    fn terminal(&self, level: Level) -> Option<terminal::Stream> {
+        self.output.terminal(level)
+    }
+
+    #[cfg(test)]
+    fn print(&self, buf: &Buffer, level: Level) -> io::Result<()> {
+        self.print_at(buf, level, Instant::now())
+    }
+
+    /// Print a record that was logged at `logged_at`.
+    /// 
+    /// The time is only used to put records back in order before they're
+    /// printed.
+    #[cfg(feature = "background")]
+    fn print_at(&self, buf: &Buffer, level: Level, logged_at: Instant) -> io::Result<()> {
+        match self.worker {
+            Some(ref worker) => {
+                worker.send(buf, level, logged_at);
+                Ok(())
+            },
+            None => self.output.print(buf, level),
+        }
+    }
+
+    #[cfg(not(feature = "background"))]
+    fn print_at(&self, buf: &Buffer, level: Level, _: Instant) -> io::Result<()> {
+        self.output.print(buf, level)
+    }
+
+    /// Whether or not records are printed on a background thread.
+    #[cfg(feature = "background")]
+    pub(crate) fn has_worker(&self) -> bool {
+        self.worker.is_some()
+    }
+
+    /// Whether or not the target should be flushed after writing a record.
+    pub(crate) fn should_flush(&self, level: Level) -> bool {
+        level <= self.flush_level
+    }
+
+    /// Flush any output buffered by the target.
+    /// 
+    /// If records are printed on a background thread, this waits until
+    /// they've all been printed.
+    pub(crate) fn flush(&self) -> io::Result<()> {
+        #[cfg(feature = "background")]
+        {
+            if let Some(ref worker) = self.worker {
+                worker.wait();
+            }
+        }
+
+        self.output.flush()
+    }
+}
+
+impl Output {
+    /// Create a buffer that can be printed to the target for records at `level`.
+    fn buffer(&self, write_style: WriteStyle, level: Level) -> Buffer {
+        // Styles are converted to HTML before the record is printed
+        if self.html {
+            return Buffer::ansi();
+        }
+
+        match self.inner {
+            WritableTarget::Stdout(ref writer) | WritableTarget::Stderr(ref writer) => writer.buffer(),
+            WritableTarget::Split { ref stdout, ref stderr, stderr_level } => {
+                split_writer(stdout, stderr, stderr_level, level).buffer()
+            },
+            WritableTarget::Pipe(_) | WritableTarget::Null => non_terminal_buffer(write_style),
+            #[cfg(windows)]
+            WritableTarget::DebugOutput => non_terminal_buffer(write_style),
+            // Styles are stripped again for the outputs that don't support them
+            WritableTarget::Multi(ref outputs) => {
+                if outputs.iter().any(|&(ref output, write_style)| output.buffer(write_style, level).supports_color()) {
+                    Buffer::ansi()
+                }
+                else {
+                    Buffer::no_color()
+                }
+            },
+        }
+    }
+
+    /// The standard stream records at `level` are printed to, if there is one.
+    /// 
+    /// Multiple targets use the first standard stream among them.
+    fn terminal(&self, level: Level) -> Option<terminal::Stream> {
+        match self.inner {
+            WritableTarget::Stdout(_) => Some(terminal::Stream::Stdout),
+            WritableTarget::Stderr(_) => Some(terminal::Stream::Stderr),
+            WritableTarget::Split { stderr_level, .. } if level <= stderr_level => Some(terminal::Stream::Stderr),
+            WritableTarget::Split { .. } => Some(terminal::Stream::Stdout),
+            WritableTarget::Multi(ref outputs) => outputs.iter().filter_map(|&(ref output, _)| output.terminal(level)).next(),
+            _ => None,
+        }
+    }
+
+    /// Whether or not records at `level` are dropped because the stream they're
+    /// printed to was closed by its reader.
+    /// 
+    /// Multiple targets are only closed once all of them are.
+    fn is_closed(&self, level: Level) -> bool {
+        match self.inner {
+            WritableTarget::Multi(ref outputs) => outputs.iter().all(|&(ref output, _)| output.is_closed(level)),
+            _ => self.closed[self.stream(level)].load(Ordering::Relaxed),
+        }
+    }
+
+    /// The index of the stream records at `level` are printed to.
+    /// 
+    /// Only split targets have more than one stream.
+    fn stream(&self, level: Level) -> usize {
+        match self.inner {
+            WritableTarget::Split { stderr_level, .. } if level <= stderr_level => 1,
+            _ => 0,
+        }
+    }
+
+    fn print(&self, buf: &Buffer, level: Level) -> io::Result<()> {
+        if self.is_closed(level) {
+            return Ok(());
+        }
+
+        let print = if self.html {
+            let mut html = Vec::with_capacity(buf.len());
+            html::convert(buf.as_slice(), &mut html);
+
+            let mut buf = Buffer::no_color();
+            buf.write_all(&html)?;
+
+            self.print_to_target(&buf, level)
+        }
+        else if self.strip_ansi && !buf.supports_color() {
+            // Escape codes written by a custom format wouldn't be dropped otherwise
+            let mut stripped = Vec::with_capacity(buf.len());
+            ansi::strip(buf.as_slice(), &mut stripped);
+
+            let mut buf = Buffer::no_color();
+            buf.write_all(&stripped)?;
+
+            self.print_to_target(&buf, level)
+        }
+        else {
+            self.print_to_target(buf, level)
+        };
+
+        match print {
+            // The reader went away, like `head` after it's read enough lines,
+            // so stop printing to the stream instead of failing every record
+            Err(ref e) if self.ignore_broken_pipe && e.kind() == io::ErrorKind::BrokenPipe => {
+                self.closed[self.stream(level)].store(true, Ordering::Relaxed);
+
+                Ok(())
+            },
+            print => print,
+        }
+    }
+
+    fn print_to_target(&self, buf: &Buffer, level: Level) -> io::Result<()> {
+        match self.inner {
+            WritableTarget::Stdout(ref writer) | WritableTarget::Stderr(ref writer) => writer.print(buf),
+            WritableTarget::Split { ref stdout, ref stderr, stderr_level } => {
+                split_writer(stdout, stderr, stderr_level, level).print(buf)
+            },
+            WritableTarget::Pipe(ref pipe) => {
+                // A panic while writing doesn't leave the pipe in an invalid state
+                let mut pipe = pipe.lock().unwrap_or_else(|e| e.into_inner());
+
+                // The mark is written under the lock, so it's only written once
+                if self.bom.load(Ordering::Relaxed) {
+                    pipe.write_all(b"\xef\xbb\xbf")?;
+                    self.bom.store(false, Ordering::Relaxed);
+                }
+
+                pipe.write_all(buf.as_slice())
+            },
+            WritableTarget::Null => Ok(()),
+            #[cfg(windows)]
+            WritableTarget::DebugOutput => {
+                debug_output::print(buf.as_slice());
+                Ok(())
+            },
+            WritableTarget::Multi(ref outputs) => {
+                let mut result = Ok(());
+
+                for &(ref output, write_style) in outputs {
+                    let print = if buf.supports_color() && !output.buffer(write_style, level).supports_color() {
+                        let mut stripped = Vec::with_capacity(buf.len());
+                        ansi::strip(buf.as_slice(), &mut stripped);
+
+                        let mut buf = Buffer::no_color();
+                        buf.write_all(&stripped)?;
+
+                        output.print(&buf, level)
+                    }
+                    else {
+                        output.print(buf, level)
+                    };
+
+                    // Keep printing to the other outputs if one of them fails
+                    if result.is_ok() {
+                        result = print;
+                    }
+                }
+
+                result
+            },
+        }
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        match self.inner {
+            WritableTarget::Stdout(_) => io::stdout().flush(),
+            WritableTarget::Stderr(_) => io::stderr().flush(),
+            WritableTarget::Split { .. } => {
+                io::stdout().flush()?;
+                io::stderr().flush()
+            },
+            WritableTarget::Pipe(ref pipe) => {
+                let mut pipe = pipe.lock().unwrap_or_else(|e| e.into_inner());
+
+                pipe.flush()
+            },
+            WritableTarget::Null => Ok(()),
+            #[cfg(windows)]
+            WritableTarget::DebugOutput => Ok(()),
+            WritableTarget::Multi(ref outputs) => {
+                let mut result = Ok(());
+
+                for &(ref output, _) in outputs {
+                    let flush = output.flush();
+
+                    if result.is_ok() {
+                        result = flush;
+                    }
+                }
+
+                result
+            },
+        }
+    }
+}
+
+/// The RGB value of a color, if it has a fixed one.
+fn color_rgb(color: &Color) -> Option<(u8, u8, u8)> {
+    match *color {
+        Color::Black => Some((0, 0, 0)),
+        Color::Blue => Some((0, 0, 255)),
+        Color::Green => Some((0, 255, 0)),
+        Color::Red => Some((255, 0, 0)),
+        Color::Cyan => Some((0, 255, 255)),
+        Color::Magenta => Some((255, 0, 255)),
+        Color::Yellow => Some((255, 255, 0)),
+        Color::White => Some((255, 255, 255)),
+        Color::Rgb(r, g, b) => Some((r, g, b)),
+        _ => None,
+    }
+}
+
+/// The color `step` steps of `steps` along the path from `from` to `to`.
+fn blend_rgb(from: (u8, u8, u8), to: (u8, u8, u8), step: u32, steps: u32) -> Color {
+    let blend = |from: u8, to: u8| {
+        let (from, to) = (from as i32, to as i32);
+
+        (from + (to - from) * step as i32 / steps as i32) as u8
+    };
+
+    Color::Rgb(blend(from.0, to.0), blend(from.1, to.1), blend(from.2, to.2))
+}
+
+/// The style choice for a target that isn't a terminal.
+/// 
+/// Styles are only written if we're forced to.
+fn non_terminal_write_style(write_style: WriteStyle) -> WriteStyle {
+    match write_style {
+        WriteStyle::Always => WriteStyle::Always,
+        WriteStyle::Auto | WriteStyle::Never => WriteStyle::Never,
+    }
+}
+
+/// A buffer for a target that isn't a terminal.
+/// The style to write to a console for `spec`.
+#[cfg(windows)]
+fn console_spec(spec: &ColorSpec, windows_bold: WindowsBold) -> Cow<ColorSpec> {
+    windows_bold_spec(spec, windows_bold)
+}
+
+#[cfg(not(windows))]
+fn console_spec(spec: &ColorSpec, _: WindowsBold) -> Cow<ColorSpec> {
+    Cow::Borrowed(spec)
+}
+
+/// The style to write to a Windows console for `spec`.
+#[cfg(any(windows, test))]
+fn windows_bold_spec(spec: &ColorSpec, windows_bold: WindowsBold) -> Cow<ColorSpec> {
+    if !spec.bold() {
+        return Cow::Borrowed(spec);
+    }
+
+    match windows_bold {
+        WindowsBold::Unchanged => Cow::Borrowed(spec),
+        WindowsBold::Intense => {
+            let mut spec = spec.clone();
+            spec.set_intense(true);
+
+            Cow::Owned(spec)
+        },
+        WindowsBold::Ignore => {
+            let mut spec = spec.clone();
+            spec.set_bold(false);
+
+            Cow::Owned(spec)
+        },
+    }
+}
+
+fn non_terminal_buffer(write_style: WriteStyle) -> Buffer {
+    match write_style {
+        WriteStyle::Always => Buffer::ansi(),
+        WriteStyle::Auto | WriteStyle::Never => Buffer::no_color(),
+    }
+}
+
+/// The style choice that matches a `termcolor` color choice.
+fn write_style_color_choice(write_style: WriteStyle) -> ColorChoice {
+    match write_style {
+        WriteStyle::Auto => ColorChoice::Auto,
+        WriteStyle::Always => ColorChoice::Always,
+        WriteStyle::Never => ColorChoice::Never,
+    }
+}
+
+fn color_choice_write_style(color_choice: ColorChoice) -> WriteStyle {
+    match color_choice {
+        ColorChoice::Always | ColorChoice::AlwaysAnsi => WriteStyle::Always,
+        ColorChoice::Auto => WriteStyle::Auto,
+        ColorChoice::Never => WriteStyle::Never,
+    }
+}
+
+/// Pick the stream a split target writes records at `level` to.
+fn split_writer<'a>(stdout: &'a BufferWriter, stderr: &'a BufferWriter, stderr_level: Level, level: Level) -> &'a BufferWriter {
+    if level <= stderr_level {
+        stderr
+    }
+    else {
+        stdout
+    }
+}
+
+/// A builder for a terminal writer.
+/// 
+/// The target and style choice can be configured before building.
+pub(crate) struct Builder {
+    target: Target,
+    // A file to write to instead of the target
+    file: Option<FileTarget>,
+    write_style: WriteStyle,
+    color_choice: Option<ColorChoice>,
+    stdout_write_style: Option<WriteStyle>,
+    stderr_write_style: Option<WriteStyle>,
+    target_write_styles: Vec<(String, WriteStyle)>,
+    flush_level: LevelFilter,
+    ignore_broken_pipe: bool,
+    strip_ansi: bool,
+    html: bool,
+    bom: bool,
+    windows_bold: WindowsBold,
+    #[cfg(feature = "background")]
+    background: Option<(usize, Overflow)>,
+    #[cfg(feature = "background")]
+    reorder: Option<(Duration, usize)>,
+}
+
+impl Builder {
+    /// Initialize the writer builder with defaults.
+    pub fn new() -> Self {
+        Builder {
+            target: Default::default(),
+            file: None,
+            write_style: Default::default(),
+            color_choice: None,
+            stdout_write_style: None,
+            stderr_write_style: None,
+            target_write_styles: Vec::new(),
+            flush_level: LevelFilter::Error,
+            ignore_broken_pipe: true,
+            strip_ansi: false,
+            html: false,
+            bom: false,
+            windows_bold: Default::default(),
+            #[cfg(feature = "background")]
+            background: None,
+            #[cfg(feature = "background")]
+            reorder: None,
+        }
+    }
+
+    /// Set the target to write to.
+    /// 
+    /// A `Target::Pipe` is moved into the writer when it's built, so the
+    /// target is reset to the default afterwards.
+    pub fn target(&mut self, target: Target) -> &mut Self {
+        self.target = target;
+        self.file = None;
+        self
+    }
+
+    /// Set a file to write to.
+    /// 
+    /// The file writes its own byte order mark, so it's only written at the
+    /// start of new files.
+    pub(crate) fn target_file(&mut self, file: FileTarget) -> &mut Self {
+        self.target = Default::default();
+        self.file = Some(file);
+        self
+    }
+
+    /// Parses a style choice string.
+    /// 
+    /// See the [Disabling colors] section for more details.
+    /// 
+    /// [Disabling colors]: ../index.html#disabling-colors
+    pub fn parse(&mut self, write_style: &str) -> &mut Self {
+        self.write_style(parse_write_style(write_style))
+    }
+
+    /// Whether or not to print style characters when writing.
+    /// 
+    /// This replaces any color choice.
+    pub fn write_style(&mut self, write_style: WriteStyle) -> &mut Self {
+        self.write_style = write_style;
+        self.color_choice = None;
+        self
+    }
+
+    /// Use a `termcolor` color choice as-is.
+    /// 
+    /// The color choice isn't resolved using environment variables, and
+    /// replaces any style choice.
+    pub fn color_choice(&mut self, color_choice: ColorChoice) -> &mut Self {
+        self.write_style = color_choice_write_style(color_choice);
+        self.color_choice = Some(color_choice);
+        self
+    }
+
+    /// Whether or not to print style characters to standard output.
+    /// 
+    /// This replaces the overall style or color choice for that stream.
+    pub fn stdout_write_style(&mut self, write_style: WriteStyle) -> &mut Self {
+        self.stdout_write_style = Some(write_style);
+        self
+    }
+
+    /// Whether or not to print style characters to standard error.
+    /// 
+    /// This replaces the overall style or color choice for that stream.
+    pub fn stderr_write_style(&mut self, write_style: WriteStyle) -> &mut Self {
+        self.stderr_write_style = Some(write_style);
+        self
+    }
+
+    /// Whether or not to print style characters for records whose target
+    /// starts with `target`.
+    /// 
+    /// This replaces any previous choice for the same target.
+    pub fn target_write_style(&mut self, target: &str, write_style: WriteStyle) -> &mut Self {
+        self.target_write_styles.retain(|&(ref prefix, _)| prefix != target);
+        self.target_write_styles.push((target.to_owned(), write_style));
+        self
+    }
+
+    /// Flush the target after writing records at or above the given level.
+    pub fn flush_level(&mut self, flush_level: LevelFilter) -> &mut Self {
+        self.flush_level = flush_level;
+        self
+    }
+
+    /// Stop printing to a stream once its reader has closed it.
+    pub fn ignore_broken_pipe(&mut self, ignore_broken_pipe: bool) -> &mut Self {
+        self.ignore_broken_pipe = ignore_broken_pipe;
+        self
+    }
+
+    /// Strip escape sequences from records when styles aren't written.
+    pub fn strip_ansi(&mut self, strip_ansi: bool) -> &mut Self {
+        self.strip_ansi = strip_ansi;
+        self
+    }
+
+    /// Print records as HTML, with styles written as inline styles.
+    pub fn html(&mut self, html: bool) -> &mut Self {
+        self.html = html;
+        self
+    }
+
+    /// Write a UTF-8 byte order mark before the first record printed to a pipe,
+    /// or at the start of each new file.
+    pub fn bom(&mut self, bom: bool) -> &mut Self {
+        self.bom = bom;
+        self
+    }
+
+    /// Set how bold text is written to consoles on Windows.
+    pub fn windows_bold(&mut self, windows_bold: WindowsBold) -> &mut Self {
+        self.windows_bold = windows_bold;
+        self
+    }
+
+    /// Print records on a background thread through a queue of `capacity` records.
+    #[cfg(feature = "background")]
+    pub fn background(&mut self, capacity: usize, overflow: Overflow) -> &mut Self {
+        self.background = Some((capacity, overflow));
+        self
+    }
+
+    /// Print records in the order they were logged on a background thread,
+    /// holding up to `capacity` records for `window`.
+    /// 
+    /// This replaces printing records through a background queue.
+    #[cfg(feature = "background")]
+    pub fn reorder(&mut self, window: Duration, capacity: usize) -> &mut Self {
+        self.reorder = Some((window, capacity));
+        self
+    }
+
+    /// Get the configured style choice.
+    pub(crate) fn get_write_style(&self) -> WriteStyle {
+        self.write_style
+    }
+
+    /// Build a terminal writer, returning an error if the target can't be
+    /// written to.
+    /// 
+    /// An `Auto` style choice is resolved using conventional environment
+    /// variables, like `NO_COLOR` and `CLICOLOR`, and then for each standard
+    /// stream by whether or not it's a terminal.
+    #[cfg(test)]
+    pub fn try_build(&mut self) -> io::Result<Writer> {
+        self.try_build_styled(true)
+    }
+
+    /// Build a terminal writer that never writes styles unless `styles` is
+    /// set.
+    /// 
+    /// Styles are turned off after every other style choice is resolved, so
+    /// choices for a stream or a record target can't turn them back on.
+    pub(crate) fn try_build_styled(&mut self, styles: bool) -> io::Result<Writer> {
+        let (write_style, stdout_color_choice, stderr_color_choice) = if styles {
+            self.color_choices(|name| env::var_os(name), terminal::is_terminal)
+        }
+        else {
+            (WriteStyle::Never, ColorChoice::Never, ColorChoice::Never)
+        };
+
+        // Files write their own byte order mark
+        let (target, bom) = match self.file.take() {
+            Some(file) => (Target::Pipe(file.into_writer(self.bom)), false),
+            None => (mem::replace(&mut self.target, Default::default()), self.bom),
+        };
+
+        let (writer, write_style) = self.build_target(target, write_style, stdout_color_choice, stderr_color_choice);
+
+        // Styles are always written as HTML, no matter what the target supports
+        let write_style = if self.html && styles {
+            WriteStyle::Always
+        }
+        else {
+            write_style
+        };
+
+        let target_write_styles = if styles {
+            self.target_write_styles.clone()
+        }
+        else {
+            Vec::new()
+        };
+
+        let output = Arc::new(Output {
+            inner: writer,
+            ignore_broken_pipe: self.ignore_broken_pipe,
+            strip_ansi: self.strip_ansi,
+            html: self.html,
+            bom: AtomicBool::new(bom),
+            closed: [AtomicBool::new(false), AtomicBool::new(false)],
+        });
+
+        Ok(Writer {
+            #[cfg(feature = "background")]
+            worker: match (self.background, self.reorder) {
+                // A full reorder buffer prints its oldest record to make room
+                (_, Some((window, capacity))) => Some(background::Worker::spawn(output.clone(), Box::new(Reorder::new(window)), capacity, Overflow::Block)?),
+                (Some((capacity, overflow)), None) => Some(background::Worker::spawn(output.clone(), Box::new(Fifo::new()), capacity, overflow)?),
+                (None, None) => None,
+            },
+            output: output,
+            write_style: write_style,
+            target_write_styles: target_write_styles,
+            flush_level: self.flush_level,
+            windows_bold: self.windows_bold,
+            previous_record: Arc::new(Mutex::new(None)),
+        })
+    }
+}
+
+impl Builder {
+    /// Resolve the overall style choice, and the color choice for each
+    /// standard stream.
+    /// 
+    /// Each stream's `Auto` choice is resolved separately, so redirecting
+    /// standard output to a file doesn't turn styles off for standard error
+    /// when it's still a terminal. Color choices set with `color_choice` are
+    /// used as-is.
+    fn color_choices<F, T>(&self, var: F, is_terminal: T) -> (WriteStyle, ColorChoice, ColorChoice)
+    where
+        F: Fn(&str) -> Option<OsString>,
+        T: Fn(terminal::Stream) -> bool,
+    {
+        let write_style = match self.color_choice {
+            Some(_) => self.write_style,
+            None => env_write_style(self.write_style, &var),
+        };
+
+        // Streams with their own style choice ignore the overall one
+        let stream_color_choice = |stream_write_style: Option<WriteStyle>, stream: terminal::Stream| {
+            let write_style = match (stream_write_style, self.color_choice) {
+                (Some(stream_write_style), _) => env_write_style(stream_write_style, &var),
+                (None, Some(color_choice)) => return color_choice,
+                (None, None) => write_style,
+            };
+
+            // `termcolor` doesn't check whether the stream is a terminal itself
+            if write_style == WriteStyle::Auto && !is_terminal(stream) {
+                ColorChoice::Never
+            }
+            else {
+                write_style_color_choice(write_style)
+            }
+        };
+
+        let stdout_color_choice = stream_color_choice(self.stdout_write_style, terminal::Stream::Stdout);
+        let stderr_color_choice = stream_color_choice(self.stderr_write_style, terminal::Stream::Stderr);
+
+        (write_style, stdout_color_choice, stderr_color_choice)
+    }
+
+    /// Build the writable target for `target` and resolve its style choice.
+    fn build_target(&self, target: Target, write_style: WriteStyle, stdout_color_choice: ColorChoice, stderr_color_choice: ColorChoice) -> (WritableTarget, WriteStyle) {
+        match target {
+            Target::Stderr => (WritableTarget::Stderr(BufferWriter::stderr(stderr_color_choice)), write_style),
+            Target::Stdout => (WritableTarget::Stdout(BufferWriter::stdout(stdout_color_choice)), write_style),
+            Target::Split(stderr_level) => {
+                let writer = WritableTarget::Split {
+                    stdout: BufferWriter::stdout(stdout_color_choice),
+                    stderr: BufferWriter::stderr(stderr_color_choice),
+                    stderr_level: stderr_level,
+                };
+
+                (writer, write_style)
+            },
+            Target::Pipe(pipe) => (WritableTarget::Pipe(Mutex::new(pipe)), non_terminal_write_style(write_style)),
+            Target::Null => (WritableTarget::Null, non_terminal_write_style(write_style)),
+            #[cfg(windows)]
+            Target::DebugOutput => (WritableTarget::DebugOutput, non_terminal_write_style(write_style)),
+            Target::Multi(targets) => {
+                let outputs = targets
+                    .into_iter()
+                    .map(|target| {
+                        let (writer, write_style) = self.build_target(target, write_style, stdout_color_choice, stderr_color_choice);
+
+                        // Records are only converted to HTML once, before they're
+                        // printed to any of the outputs
+                        let output = Output {
+                            inner: writer,
+                            ignore_broken_pipe: self.ignore_broken_pipe,
+                            strip_ansi: self.strip_ansi,
+                            html: false,
+                            bom: AtomicBool::new(self.bom),
+                            closed: [AtomicBool::new(false), AtomicBool::new(false)],
+                        };
+
+                        (output, write_style)
+                    })
+                    .collect();
+
+                (WritableTarget::Multi(outputs), write_style)
+            },
+        }
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Builder::new()
+    }
+}
+
+/// A function used to format a log record.
+pub(crate) type FormatFn = Fn(&mut Formatter, &Record) -> io::Result<()> + Sync + Send;
+
+/// The sequence number of the next record written with a sequence number.
+/// 
+/// It's shared by every logger in the process.
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// The id of the next thread to ask for one.
+static NEXT_THREAD_ID: AtomicUsize = AtomicUsize::new(1);
+
+thread_local! {
+    /// The number of scopes entered on this thread.
+    static SCOPE_DEPTH: Cell<usize> = Cell::new(0);
+
+    /// The numeric id of this thread.
+    static THREAD_ID: usize = NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed);
+}
+
+/// A function used to pick the color of a log level.
+pub(crate) type LevelColorFn = Fn(Level) -> Color + Sync + Send;
+
+/// A function used to get the current date and time for timestamps.
+pub(crate) type ClockFn = Fn() -> SystemTime + Sync + Send;
+
+/// The format to write log records with.
+/// 
+/// This is either a custom format function or the default format
+/// with some configurable options.
+pub(crate) struct Format {
+    pub(crate) layout: Layout,
+    pub(crate) json_fields: bool,
+    pub(crate) sd_daemon: bool,
+    pub(crate) notify_level: Option<Level>,
+    pub(crate) level_padding: LevelPadding,
+    pub(crate) level_compact: bool,
+    pub(crate) level_lowercase: bool,
+    pub(crate) full_line_color: bool,
+    pub(crate) message_styles: Vec<(Level, StyleSpec)>,
+    pub(crate) sanitize_messages: bool,
+    pub(crate) level_delimiters: Option<(String, String)>,
+    pub(crate) target_delimiters: Option<(String, String)>,
+    pub(crate) color_delimiters: bool,
+    pub(crate) module_path: bool,
+    pub(crate) target_width: usize,
+    pub(crate) target_right_align: bool,
+    pub(crate) file: bool,
+    pub(crate) thread_name: bool,
+    pub(crate) thread_id: bool,
+    pub(crate) sequence: bool,
+    pub(crate) field_order: Option<Vec<Field>>,
+    pub(crate) field_separator: String,
+    pub(crate) indent: Option<usize>,
+    pub(crate) scope_indent: usize,
+    pub(crate) max_line_width: Option<usize>,
+    pub(crate) timestamp: Option<TimestampPrecision>,
+    pub(crate) timestamp_cache: Option<Duration>,
+    pub(crate) timestamp_offset: Option<UtcOffset>,
+    pub(crate) timestamp_time_only: bool,
+    pub(crate) line_terminator: Vec<u8>,
+    pub(crate) metadata: Vec<(String, String)>,
+    pub(crate) line_prefix: Option<Box<FormatFn>>,
+    pub(crate) line_suffix: Option<Box<FormatFn>>,
+    pub(crate) custom_format: Option<Box<FormatFn>>,
+}
+
+/// The layout of records written by the built-in formats.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Layout {
+    /// The human-readable default format.
+    Text,
+    /// A single-line JSON object.
+    Json,
+    /// A single line of `logfmt` pairs.
+    Logfmt,
+}
+
+impl Format {
+    /// Build the format function.
+    /// 
+    /// The builder is reset to defaults afterwards.
+    pub(crate) fn build(&mut self) -> Box<FormatFn> {
+        let built = mem::replace(self, Default::default());
+
+        // Notifications would corrupt records that are parsed, like JSON
+        let notify_level = match (&built.custom_format, built.layout) {
+            (&None, Layout::Json) | (&None, Layout::Logfmt) => None,
+            _ => built.notify_level,
+        };
+
+        let format: Box<FormatFn> = match built.custom_format {
+            Some(format) => format,
+            None => {
+                let metadata = built.metadata;
+                let level_padding = built.level_padding;
+                let level_compact = built.level_compact;
+                let level_lowercase = built.level_lowercase;
+                let full_line_color = built.full_line_color;
+                let message_styles = built.message_styles;
+                let sanitize_messages = built.sanitize_messages;
+                let level_delimiters = built.level_delimiters;
+                let target_delimiters = built.target_delimiters;
+                let color_delimiters = built.color_delimiters;
+                let module_path = built.module_path;
+                let target_width = built.target_width;
+                let target_right_align = built.target_right_align;
+                let file = built.file;
+                let thread_name = built.thread_name;
+                let thread_id = built.thread_id;
+                let sequence = built.sequence;
+                let field_order = built.field_order;
+                let field_separator = built.field_separator;
+                let indent = built.indent;
+                let scope_indent = built.scope_indent;
+                let max_line_width = built.max_line_width;
+                let timestamp = built.timestamp;
+                let timestamp_cache = built.timestamp_cache;
+                let timestamp_offset = built.timestamp_offset;
+                let timestamp_time_only = built.timestamp_time_only;
+                let line_terminator = built.line_terminator;
+                let line_prefix = built.line_prefix;
+                let line_suffix = built.line_suffix;
+                let json_fields = built.json_fields;
+
+                match built.layout {
+                    Layout::Text => Box::new(move |buf, record| {
+                        // Anything already written, like a priority prefix,
+                        // isn't part of the visible line
+                        let record_start = buf.buf.borrow().len();
+
+                        let fmt = DefaultFormat {
+                            level_padding: level_padding,
+                            level_compact: level_compact,
+                            level_lowercase: level_lowercase,
+                            full_line_color: full_line_color,
+                            message_styles: &message_styles,
+                            sanitize_messages: sanitize_messages,
+                            level_delimiters: level_delimiters.as_ref(),
+                            target_delimiters: target_delimiters.as_ref(),
+                            color_delimiters: color_delimiters,
+                            module_path: module_path,
+                            target_width: target_width,
+                            target_right_align: target_right_align,
+                            file: file,
+                            thread_name: thread_name,
+                            thread_id: thread_id,
+                            sequence: sequence,
+                            field_order: field_order.as_ref().map(|order| &**order),
+                            field_separator: &field_separator,
+                            indent: indent,
+                            scope_indent: scope_indent,
+                            max_line_width: max_line_width,
+                            timestamp: timestamp,
+                            timestamp_cache: timestamp_cache,
+                            timestamp_offset: timestamp_offset,
+                            timestamp_time_only: timestamp_time_only,
+                            line_terminator: &line_terminator,
+                            line_prefix: line_prefix.as_ref().map(|f| &**f),
+                            line_suffix: line_suffix.as_ref().map(|f| &**f),
+                            metadata: &metadata,
+                            record_start: record_start,
+                            buf: buf,
+                        };
+
+                        fmt.write(record)
+                    }),
+                    Layout::Json => Box::new(move |buf, record| {
+                        let fmt = JsonFormat {
+                            timestamp: timestamp,
+                            timestamp_cache: timestamp_cache,
+                            timestamp_offset: timestamp_offset,
+                            fields: json_fields,
+                            line_terminator: &line_terminator,
+                            metadata: &metadata,
+                            buf: buf,
+                        };
+
+                        fmt.write(record)
+                    }),
+                    Layout::Logfmt => Box::new(move |buf, record| {
+                        let fmt = LogfmtFormat {
+                            timestamp: timestamp,
+                            timestamp_cache: timestamp_cache,
+                            timestamp_offset: timestamp_offset,
+                            line_terminator: &line_terminator,
+                            metadata: &metadata,
+                            buf: buf,
+                        };
+
+                        fmt.write(record)
+                    }),
+                }
+            },
+        };
+
+        let format: Box<FormatFn> = match notify_level {
+            Some(notify_level) => Box::new(move |buf, record| {
+                format(buf, record)?;
+
+                if record.level() <= notify_level {
+                    write_notification(buf, record)?;
+                }
+
+                Ok(())
+            }),
+            None => format,
+        };
+
+        if built.sd_daemon {
+            Box::new(move |buf, record| {
+                write!(buf, "<{}>", sd_daemon_priority(record.level()))?;
+                format(buf, record)
+            })
+        } else {
+            format
+        }
+    }
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Format {
+            layout: Layout::Text,
+            json_fields: false,
+            sd_daemon: false,
+            notify_level: None,
+            level_padding: Default::default(),
+            level_compact: false,
+            level_lowercase: false,
+            full_line_color: false,
+            message_styles: Vec::new(),
+            sanitize_messages: false,
+            level_delimiters: None,
+            target_delimiters: None,
+            color_delimiters: false,
+            module_path: true,
+            target_width: 0,
+            target_right_align: false,
+            file: false,
+            thread_name: false,
+            thread_id: false,
+            sequence: false,
+            field_order: None,
+            field_separator: " ".to_owned(),
+            indent: None,
+            scope_indent: 2,
+            max_line_width: None,
+            timestamp: Some(Default::default()),
+            timestamp_cache: None,
+            timestamp_offset: None,
+            timestamp_time_only: false,
+            line_terminator: b"\n".to_vec(),
+            metadata: Vec::new(),
+            line_prefix: None,
+            line_suffix: None,
+            custom_format: None,
+        }
+    }
+}
+
+/// The default format for log records.
+struct DefaultFormat<'a> {
+    level_padding: LevelPadding,
+    level_compact: bool,
+    level_lowercase: bool,
+    full_line_color: bool,
+    message_styles: &'a [(Level, StyleSpec)],
+    sanitize_messages: bool,
+    level_delimiters: Option<&'a (String, String)>,
+    target_delimiters: Option<&'a (String, String)>,
+    color_delimiters: bool,
+    module_path: bool,
+    target_width: usize,
+    target_right_align: bool,
+    file: bool,
+    thread_name: bool,
+    thread_id: bool,
+    sequence: bool,
+    field_order: Option<&'a [Field]>,
+    field_separator: &'a str,
+    indent: Option<usize>,
+    scope_indent: usize,
+    max_line_width: Option<usize>,
+    timestamp: Option<TimestampPrecision>,
+    timestamp_cache: Option<Duration>,
+    timestamp_offset: Option<UtcOffset>,
+    timestamp_time_only: bool,
+    line_terminator: &'a [u8],
+    line_prefix: Option<&'a FormatFn>,
+    line_suffix: Option<&'a FormatFn>,
+    metadata: &'a [(String, String)],
+    // Where the record starts in the buffer
+    record_start: usize,
+    buf: &'a mut Formatter,
+}
+
+impl<'a> DefaultFormat<'a> {
+    fn write(mut self, record: &Record) -> io::Result<()> {
+        if self.full_line_color {
+            let line_style = self.buf.default_level_style(record.level());
+            self.buf.buf.set_color(&line_style.spec)?;
+
+            // Always try to reset the terminal style, even if writing failed
+            let write = self.write_line(record);
+            let reset = self.buf.buf.borrow_mut().reset();
+
+            write.and(reset)?;
+        }
+        else {
+            self.write_line(record)?;
+        }
+
+        self.buf.write_all(self.line_terminator)
+    }
+
+    /// Write the record without its line terminator.
+    fn write_line(&mut self, record: &Record) -> io::Result<()> {
+        if let Some(line_prefix) = self.line_prefix {
+            line_prefix(self.buf, record)?;
+        }
+
+        match self.field_order {
+            Some(field_order) => self.write_fields(field_order, record)?,
+            None => {
+                self.write_header(record)?;
+                self.write_message_field(record)?;
+            },
+        }
+
+        if let Some(line_suffix) = self.line_suffix {
+            line_suffix(self.buf, record)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_header(&mut self, record: &Record) -> io::Result<()> {
+        if self.sequence {
+            write!(self.buf, "{} ", self.buf.sequence())?;
+        }
+
+        let ts = self.buf.record_timestamp(self.timestamp, self.timestamp_cache, self.timestamp_offset, self.timestamp_time_only);
+
+        match self.level_delimiters {
+            Some(&(ref open, ref close)) => self.write_delimited_level(record.level(), open, close)?,
+            None => self.write_level(record.level())?,
+        }
+
+        if let Some(ts) = ts {
+            write!(self.buf, " {}", ts)?;
+        }
+
+        self.write_thread()?;
+
+        // Delimiters already separate the level from the rest of the record
+        if self.level_delimiters.is_some() {
+            write!(self.buf, " ")?;
+        }
+        else {
+            write!(self.buf, ": ")?;
+        }
+
+        if self.module_path {
+            if let Some(module_path) = record.module_path() {
+                self.write_target(module_path)?;
+            }
+        }
+
+        if self.file {
+            match (record.file(), record.line()) {
+                (Some(file), Some(line)) => write!(self.buf, "{}:{}: ", file, line)?,
+                (Some(file), None) => write!(self.buf, "{}: ", file)?,
+                (None, _) => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write the given fields, separated by the field separator.
+    /// 
+    /// Fields that the record doesn't have, like a module path, are skipped
+    /// along with their separator.
+    fn write_fields(&mut self, field_order: &[Field], record: &Record) -> io::Result<()> {
+        let mut first = true;
+
+        for &field in field_order {
+            let ts = match field {
+                Field::Timestamp => match self.buf.record_timestamp(self.timestamp, self.timestamp_cache, self.timestamp_offset, self.timestamp_time_only) {
+                    Some(ts) => Some(ts),
+                    None => continue,
+                },
+                Field::ModulePath if record.module_path().is_none() => continue,
+                Field::File if record.file().is_none() => continue,
+                _ => None,
+            };
+
+            if !first {
+                write!(self.buf, "{}", self.field_separator)?;
+            }
+            first = false;
+
+            match field {
+                Field::Sequence => write!(self.buf, "{}", self.buf.sequence())?,
+                Field::Timestamp => write!(self.buf, "{}", ts.expect("the timestamp was checked"))?,
+                Field::Level => match self.level_delimiters {
+                    Some(&(ref open, ref close)) => self.write_delimited_level(record.level(), open, close)?,
+                    None => self.write_level(record.level())?,
+                },
+                Field::Thread => self.write_thread_name()?,
+                Field::Target => self.write_padded_target(record.target())?,
+                Field::ModulePath => self.write_padded_target(record.module_path().expect("the module path was checked"))?,
+                Field::File => match record.line() {
+                    Some(line) => write!(self.buf, "{}:{}", record.file().expect("the file was checked"), line)?,
+                    None => write!(self.buf, "{}", record.file().expect("the file was checked"))?,
+                },
+                Field::Message => self.write_message_field(record)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write the message, followed by its key-values and metadata.
+    fn write_message_field(&mut self, record: &Record) -> io::Result<()> {
+        self.write_args(record)?;
+        self.write_kvs(record)?;
+        self.write_metadata()
+    }
+
+    fn write_level(&mut self, level: Level) -> io::Result<()> {
+        let level_style = self.buf.default_level_style(level);
+
+        // Resetting the level's style would end the color of the whole line
+        match (self.level_compact, self.full_line_color) {
+            (true, false) => write!(self.buf, "{}", level_style.value(self.compact_level(level))),
+            (true, true) => write!(self.buf, "{}", self.compact_level(level)),
+            (false, false) => write!(self.buf, "{}", level_style.value(self.padded_level(level, self.level_padding))),
+            (false, true) => write!(self.buf, "{}", self.padded_level(level, self.level_padding)),
+        }
+    }
+
+    fn compact_level(&self, level: Level) -> char {
+        let letter = self.buf.compact_level(level);
+
+        if self.level_lowercase {
+            letter.to_ascii_lowercase()
+        }
+        else {
+            letter
+        }
+    }
+
+    fn padded_level(&self, level: Level, padding: LevelPadding) -> PaddedLevel {
+        let level = self.buf.padded_level(level, padding);
+
+        if self.level_lowercase {
+            level.lowercase()
+        }
+        else {
+            level
+        }
+    }
+
+    /// Write the level between delimiters, like `[INFO]`.
+    /// 
+    /// The level is padded outside of the delimiters, so they stay next to it.
+    fn write_delimited_level(&mut self, level: Level, open: &str, close: &str) -> io::Result<()> {
+        let level_style = self.buf.default_level_style(level);
+
+        let text = if self.level_compact {
+            self.compact_level(level).to_string()
+        }
+        else {
+            self.padded_level(level, LevelPadding::Off).to_string()
+        };
+
+        let padding = match self.level_padding {
+            _ if self.level_compact => "",
+            LevelPadding::Off => "",
+            _ => &"     "[text.len()..],
+        };
+
+        if self.level_padding == LevelPadding::Left {
+            write!(self.buf, "{}", padding)?;
+        }
+
+        match (self.full_line_color, self.color_delimiters) {
+            (true, _) => write!(self.buf, "{}{}{}", open, text, close)?,
+            (false, true) => write!(self.buf, "{}", level_style.value(format!("{}{}{}", open, text, close)))?,
+            (false, false) => write!(self.buf, "{}{}{}", open, level_style.value(text), close)?,
+        }
+
+        if self.level_padding == LevelPadding::Right {
+            write!(self.buf, "{}", padding)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_target(&mut self, target: &str) -> io::Result<()> {
+        self.write_padded_target(target)?;
+
+        match self.target_delimiters {
+            Some(_) => write!(self.buf, " "),
+            None => write!(self.buf, ": "),
+        }
+    }
+
+    /// Write a target or module path with its delimiters and padding.
+    fn write_padded_target(&mut self, target: &str) -> io::Result<()> {
+        let (open, close) = match self.target_delimiters {
+            Some(&(ref open, ref close)) => (&**open, &**close),
+            None => ("", ""),
+        };
+
+        let width = self.target_width;
+
+        if width == 0 {
+            return write!(self.buf, "{}{}{}", open, target, close);
+        }
+
+        // The delimiters are padded along with the target so they stay next to it
+        let target = format!("{}{}{}", open, truncate(target, width), close);
+        let width = width + open.chars().count() + close.chars().count();
+
+        if self.target_right_align {
+            write!(self.buf, "{:>width$}", target, width = width)
+        }
+        else {
+            write!(self.buf, "{:<width$}", target, width = width)
+        }
+    }
+
+    fn write_thread(&mut self) -> io::Result<()> {
+        if !self.thread_name && !self.thread_id {
+            return Ok(());
+        }
+
+        write!(self.buf, " ")?;
+        self.write_thread_name()
+    }
+
+    /// Write the thread in brackets, like `[main]`.
+    /// 
+    /// The thread's name is written unless only its id is enabled.
+    fn write_thread_name(&mut self) -> io::Result<()> {
+        let thread = self.buf.current_thread();
+
+        match (self.thread_name, self.thread_id, thread.name().is_some()) {
+            (true, true, true) => write!(self.buf, "[{}:{}]", thread.name_or_id(), thread.id()),
+            (false, true, _) => write!(self.buf, "[{}]", thread.id()),
+            (_, _, _) => write!(self.buf, "[{}]", thread.name_or_id()),
+        }
+    }
+
+    fn write_args(&mut self, record: &Record) -> io::Result<()> {
+        let message_style = self.message_styles.iter().find(|&&(level, _)| level == record.level());
+
+        let &(_, ref style) = match message_style {
+            Some(message_style) => message_style,
+            None => return self.write_unstyled_args(record),
+        };
+
+        self.buf.buf.set_color(&style.spec)?;
+
+        // Always try to reset the terminal style, even if writing failed
+        let write = self.write_unstyled_args(record);
+        let reset = self.buf.buf.borrow_mut().reset();
+
+        write.and(reset)?;
+
+        // The rest of the line goes back to the line's color
+        if self.full_line_color {
+            let line_style = self.buf.default_level_style(record.level());
+            self.buf.buf.set_color(&line_style.spec)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_unstyled_args(&mut self, record: &Record) -> io::Result<()> {
+        // Records logged in nested scopes are indented
+        let scope_indent = self.scope_indent * Scope::depth();
+
+        if scope_indent > 0 {
+            write!(self.buf, "{:width$}", "", width = scope_indent)?;
+        }
+
+        let sanitized = Sanitized(record.args());
+        let message: &fmt::Display = if self.sanitize_messages {
+            &sanitized
+        }
+        else {
+            record.args()
+        };
+
+        match self.max_line_width {
+            None => self.write_message(message),
+            Some(width) => {
+                let message = message.to_string();
+                let available = width.saturating_sub(self.written_width());
+
+                self.write_message(&truncate(&message, available))
+            },
+        }
+    }
+
+    fn write_message(&mut self, message: &fmt::Display) -> io::Result<()> {
+        match self.indent {
+            None => write!(self.buf, "{}", message),
+            Some(indent) => {
+                // Continuation lines stay indented with the first line
+                let mut wrapper = IndentWrapper {
+                    buf: self.buf,
+                    indent: indent + self.scope_indent * Scope::depth(),
+                };
+
+                write!(wrapper, "{}", message)
+            },
+        }
+    }
+
+    /// The number of columns written for the record so far, ignoring any
+    /// escape sequences.
+    fn written_width(&self) -> usize {
+        let mut written = Vec::new();
+        ansi::strip(&self.buf.buf.borrow().as_slice()[self.record_start..], &mut written);
+
+        String::from_utf8_lossy(&written).chars().count()
+    }
+
+    #[cfg(feature = "kv")]
+    fn write_kvs(&mut self, record: &Record) -> io::Result<()> {
+        self.buf.write_kvs(record)
+    }
+
+    #[cfg(not(feature = "kv"))]
+    fn write_kvs(&mut self, _: &Record) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn write_metadata(&mut self) -> io::Result<()> {
+        for &(ref key, ref value) in self.metadata {
+            write_text_pair(self.buf, key, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A message with its control characters escaped, except for newlines.
+/// 
+/// Escape sequences in untrusted input could otherwise restyle the terminal
+/// or overwrite earlier records.
+struct Sanitized<T>(T);
+
+impl<T: fmt::Display> fmt::Display for Sanitized<T> {
+    fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
+        /// A writer that escapes control characters as they're written.
+        struct EscapeControl<'a, 'b: 'a>(&'a mut fmt::Formatter<'b>);
+
+        impl<'a, 'b> fmt::Write for EscapeControl<'a, 'b> {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                for c in s.chars() {
+                    if c.is_control() && c != '\n' {
+                        for c in c.escape_default() {
+                            self.0.write_char(c)?;
+                        }
+                    }
+                    else {
+                        self.0.write_char(c)?;
+                    }
+                }
+
+                Ok(())
+            }
+        }
+
+        fmt::write(&mut EscapeControl(f), format_args!("{}", self.0))
+    }
+}
+
+/// A writer that indents every line after the first.
+struct IndentWrapper<'a> {
+    buf: &'a mut Formatter,
+    indent: usize,
+}
+
+impl<'a> Write for IndentWrapper<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut first = true;
+
+        for chunk in buf.split(|&b| b == b'\n') {
+            if !first {
+                write!(self.buf, "\n{:width$}", "", width = self.indent)?;
+            }
+
+            self.buf.write_all(chunk)?;
+            first = false;
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.buf.flush()
+    }
+}
+
+/// Cut a message short so it fits in `width` columns, ending it with `…`.
+fn truncate(message: &str, width: usize) -> Cow<str> {
+    if message.chars().count() <= width {
+        return Cow::Borrowed(message);
+    }
+
+    let mut truncated: String = message.chars().take(width.saturating_sub(1)).collect();
+
+    if width > 0 {
+        truncated.push('…');
+    }
+
+    Cow::Owned(truncated)
+}
+
+/// Write a ` key=value` pair for the default format.
+fn write_text_pair(buf: &mut Formatter, key: &str, value: &str) -> io::Result<()> {
+    buf.write_all(b" ")?;
+    write_text_part(buf, key)?;
+    buf.write_all(b"=")?;
+    write_text_part(buf, value)
+}
+
+/// Write a key or value for the default format.
+/// 
+/// Keys and values that could be confused with the surrounding pairs are
+/// quoted and escaped.
+fn write_text_part(buf: &mut Formatter, part: &str) -> io::Result<()> {
+    let quote = part.is_empty() || part.contains(|c: char| c.is_whitespace() || c.is_control() || c == '=' || c == '"');
+
+    if quote {
+        write!(buf, "{:?}", part)
+    }
+    else {
+        buf.write_all(part.as_bytes())
+    }
+}
+
+/// The longest message written in a notification.
+const NOTIFICATION_WIDTH: usize = 100;
+
+/// Write a record's message as an OSC 9 desktop notification.
+/// 
+/// Terminals that don't support notifications ignore the escape sequence.
+/// Nothing is written if styles are disabled.
+fn write_notification(buf: &mut Formatter, record: &Record) -> io::Result<()> {
+    if !buf.style_enabled() {
+        return Ok(());
+    }
+
+    // Control characters would end the escape sequence early
+    let message: String = record.args().to_string()
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+
+    write!(buf, "\x1b]9;{}\x1b\\", truncate(&message, NOTIFICATION_WIDTH))
+}
+
+/// The `sd-daemon` priority for a log level.
+/// 
+/// These are the syslog severities that most closely match each level.
+fn sd_daemon_priority(level: Level) -> u8 {
+    match level {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7,
+    }
+}
+
+impl Style {
+    /// Set the text color.
+    /// 
+    /// # Examples
+    /// 
+    /// Create a style with red text:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// use env_logger::fmt::Color;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let mut style = buf.style();
+    /// 
+    ///     style.set_color(Color::Red);
+    /// 
+    ///     writeln!(buf, "{}", style.value(record.args()))
+    /// });
+    /// ```
+    pub fn set_color(&mut self, color: Color) -> &mut Style {
+        self.spec.set_fg(Some(color));
+        self
+    }
+
+    /// Set the text weight.
+    /// 
+    /// If `yes` is true then text will be written in bold.
+    /// If `yes` is false then text will be written in the default weight.
+    /// 
+    /// # Examples
+    /// 
+    /// Create a style with bold text:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let mut style = buf.style();
+    /// 
+    ///     style.set_bold(true);
+    /// 
+    ///     writeln!(buf, "{}", style.value(record.args()))
+    /// });
+    /// ```
+    pub fn set_bold(&mut self, yes: bool) -> &mut Style {
+        self.spec.set_bold(yes);
+        self
+    }
+
+    /// Set whether the text is underlined.
+    /// 
+    /// If `yes` is true then text will be written underlined.
+    /// If `yes` is false then text will be written without an underline.
+    /// 
+    /// # Examples
+    /// 
+    /// Create a style with underlined text:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let mut style = buf.style();
+    /// 
+    ///     style.set_underline(true);
+    /// 
+    ///     writeln!(buf, "{}", style.value(record.args()))
+    /// });
+    /// ```
+    pub fn set_underline(&mut self, yes: bool) -> &mut Style {
+        self.spec.set_underline(yes);
+        self
+    }
+
+    /// Set whether the text is italicized.
+    /// 
+    /// If `yes` is true then text will be written in italics.
+    /// If `yes` is false then text will be written in the default font style.
+    /// 
+    /// Not all terminals support italic text.
+    /// 
+    /// # Examples
+    /// 
+    /// Create a style with italic text:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let mut style = buf.style();
+    /// 
+    ///     style.set_italic(true);
+    /// 
+    ///     writeln!(buf, "{}", style.value(record.args()))
+    /// });
+    /// ```
+    pub fn set_italic(&mut self, yes: bool) -> &mut Style {
+        self.spec.set_italic(yes);
+        self
+    }
+
+    /// Set the text intensity.
+    /// 
+    /// If `yes` is true then text will be written with high intensity.
+    /// If `yes` is false then text will be written with normal intensity.
+    /// 
+    /// On most terminals intense text is written with a brighter color.
+    /// 
+    /// # Examples
+    /// 
+    /// Create a style with intense text:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let mut style = buf.style();
+    /// 
+    ///     style.set_intense(true);
+    /// 
+    ///     writeln!(buf, "{}", style.value(record.args()))
+    /// });
+    /// ```
+    pub fn set_intense(&mut self, yes: bool) -> &mut Style {
+        self.spec.set_intense(yes);
+        self
+    }
+
+    /// Set whether the text is dimmed.
+    /// 
+    /// If `yes` is true then text will be written dimmed.
+    /// If `yes` is false then text will be written with normal intensity.
+    /// 
+    /// Not all terminals support dimmed text.
+    /// 
+    /// # Examples
+    /// 
+    /// Create a style with dimmed text:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let mut style = buf.style();
+    /// 
+    ///     style.set_dimmed(true);
+    /// 
+    ///     writeln!(buf, "{}", style.value(record.args()))
+    /// });
+    /// ```
+    pub fn set_dimmed(&mut self, yes: bool) -> &mut Style {
+        self.spec.set_dimmed(yes);
+        self
+    }
+
+    /// Set the background color.
+    /// 
+    /// # Examples
+    /// 
+    /// Create a style with a yellow background:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// use env_logger::fmt::Color;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let mut style = buf.style();
+    /// 
+    ///     style.set_bg(Color::Yellow);
+    /// 
+    ///     writeln!(buf, "{}", style.value(record.args()))
+    /// });
+    /// ```
+    pub fn set_bg(&mut self, color: Color) -> &mut Style {
+        self.spec.set_bg(Some(color));
+        self
+    }
+
+    /// Set the text color to one of the 256 ANSI colors.
+    /// 
+    /// This is the same as calling [`set_color`] with `Color::Ansi256(color)`.
+    /// 
+    /// # Examples
+    /// 
+    /// Create a style with orange text:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let mut style = buf.style();
+    /// 
+    ///     style.set_color_256(208);
+    /// 
+    ///     writeln!(buf, "{}", style.value(record.args()))
+    /// });
+    /// ```
+    /// 
+    /// [`set_color`]: #method.set_color
+    pub fn set_color_256(&mut self, color: u8) -> &mut Style {
+        self.set_color(Color::Ansi256(color))
+    }
+
+    /// Set the text color to a 24-bit RGB color.
+    /// 
+    /// This is the same as calling [`set_color`] with `Color::Rgb(r, g, b)`.
+    /// Not all terminals support 24-bit colors.
+    /// 
+    /// # Examples
+    /// 
+    /// Create a style with teal text:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let mut style = buf.style();
+    /// 
+    ///     style.set_color_rgb(0, 128, 128);
+    /// 
+    ///     writeln!(buf, "{}", style.value(record.args()))
+    /// });
+    /// ```
+    /// 
+    /// [`set_color`]: #method.set_color
+    pub fn set_color_rgb(&mut self, r: u8, g: u8, b: u8) -> &mut Style {
+        self.set_color(Color::Rgb(r, g, b))
+    }
+
+    /// Set the text color from its name, like `yellow` or `bright-red`.
+    /// 
+    /// This is useful for letting users pick colors in a configuration file.
+    /// The names of the 8 basic colors are accepted, ignoring case, and can
+    /// be prefixed with `bright-` to use their [intense] variant. Numbers
+    /// from `0` to `255` are one of the 256 ANSI colors, and triples like
+    /// `0,128,128` or `0x0,0x80,0x80` are 24-bit RGB colors.
+    /// 
+    /// # Errors
+    /// 
+    /// This method returns an error if `name` isn't a color, which lists the
+    /// accepted names when it's an unknown color name. The style isn't
+    /// changed.
+    /// 
+    /// # Examples
+    /// 
+    /// Create a style with a color read from the environment:
+    /// 
+    /// ```
+    /// use std::env;
+    /// use std::io::Write;
+    /// 
+    /// let color = env::var("MY_APP_COLOR").unwrap_or("bright-red".to_owned());
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(move |buf, record| {
+    ///     let mut style = buf.style();
+    /// 
+    ///     style
+    ///         .set_color_named(&color)
+    ///         .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    /// 
+    ///     writeln!(buf, "{}", style.value(record.args()))
+    /// });
+    /// ```
+    /// 
+    /// [intense]: #method.set_intense
+    pub fn set_color_named(&mut self, name: &str) -> Result<&mut Style, ParseColorError> {
+        let bright = name.get(..7).map_or(false, |prefix| prefix.eq_ignore_ascii_case("bright-"));
+
+        let (bright, name) = if bright {
+            (true, &name[7..])
+        }
+        else {
+            (false, name)
+        };
+
+        let color = name.parse()?;
+
+        Ok(self.set_color(color).set_intense(bright))
+    }
+
+    /// Set the background color to one of the 256 ANSI colors.
+    /// 
+    /// This is the same as calling [`set_bg`] with `Color::Ansi256(color)`.
+    /// 
+    /// # Examples
+    /// 
+    /// Create a style with a grey background:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let mut style = buf.style();
+    /// 
+    ///     style.set_bg_256(240);
+    /// 
+    ///     writeln!(buf, "{}", style.value(record.args()))
+    /// });
+    /// ```
+    /// 
+    /// [`set_bg`]: #method.set_bg
+    pub fn set_bg_256(&mut self, color: u8) -> &mut Style {
+        self.set_bg(Color::Ansi256(color))
+    }
+
+    /// Set the background color to a 24-bit RGB color.
+    /// 
+    /// This is the same as calling [`set_bg`] with `Color::Rgb(r, g, b)`.
+    /// Not all terminals support 24-bit colors.
+    /// 
+    /// # Examples
+    /// 
+    /// Create a style with a navy background:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let mut style = buf.style();
+    /// 
+    ///     style.set_bg_rgb(0, 0, 128);
+    /// 
+    ///     writeln!(buf, "{}", style.value(record.args()))
+    /// });
+    /// ```
+    /// 
+    /// [`set_bg`]: #method.set_bg
+    pub fn set_bg_rgb(&mut self, r: u8, g: u8, b: u8) -> &mut Style {
+        self.set_bg(Color::Rgb(r, g, b))
+    }
+
+    /// Set whether the terminal style is reset before the style is applied.
+    /// 
+    /// This defaults to `true`, so a styled value doesn't inherit anything
+    /// from the terminal's prior state. Set it to `false` to layer the style
+    /// on top of whatever was applied before, like when composing a style
+    /// inside a [`Formatter::style_scope`].
+    /// 
+    /// The terminal style is still always reset after a styled value is
+    /// written, regardless of this setting.
+    /// 
+    /// # Examples
+    /// 
+    /// Write the level in bold without clearing the color of its scope:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// use env_logger::fmt::Color;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let mut red = buf.style();
+    ///     red.set_color(Color::Red);
+    /// 
+    ///     let mut bold = buf.style();
+    ///     bold.set_bold(true).set_reset(false);
+    /// 
+    ///     buf.style_scope(&red, |buf| {
+    ///         write!(buf, "{}: {}", bold.value(record.level()), record.args())
+    ///     })?;
+    /// 
+    ///     writeln!(buf)
+    /// });
+    /// ```
+    /// 
+    /// [`Formatter::style_scope`]: struct.Formatter.html#method.style_scope
+    pub fn set_reset(&mut self, yes: bool) -> &mut Style {
+        self.spec.set_reset(yes);
+        self
+    }
+
+    /// Wrap a value in the style.
+    /// 
+    /// The same `Style` can be used to print multiple different values.
+    /// 
+    /// # Examples
+    /// 
+    /// Create a bold, red colored style and use it to print the log level:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// use env_logger::fmt::Color;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let mut style = buf.style();
+    /// 
+    ///     style.set_color(Color::Red).set_bold(true);
+    /// 
+    ///     writeln!(buf, "{}: {}",
+    ///         style.value(record.level()),
+    ///         record.args())
+    /// });
+    /// ```
+    pub fn value<T>(&self, value: T) -> StyledValue<T> {
+        StyledValue {
+            style: Cow::Borrowed(self),
+            value
+        }
+    }
+
+    /// Write raw bytes in the style.
+    /// 
+    /// The style is set, the bytes are written as-is, and the style is reset.
+    /// Unlike [`value`], the bytes don't go through `fmt`, so they don't need
+    /// to be valid UTF-8. The style is reset even if writing fails.
+    /// 
+    /// # Examples
+    /// 
+    /// Write a binary payload in a dimmed style:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let mut style = buf.style();
+    ///     style.set_dimmed(true);
+    /// 
+    ///     write!(buf, "{}: ", record.args())?;
+    ///     style.write_bytes(b"\x00\xff\xfe")?;
+    ///     writeln!(buf)
+    /// });
+    /// ```
+    /// 
+    /// [`value`]: #method.value
+    pub fn write_bytes(&self, bytes: &[u8]) -> io::Result<()> {
+        self.buf.set_color(&self.spec)?;
+
+        let mut buf = self.buf.borrow_mut();
+
+        // Always try to reset the terminal style, even if writing failed
+        let write = buf.write_all(bytes);
+        let reset = buf.reset();
+
+        write.and(reset)
+    }
+}
+
+impl StyleSpec {
+    /// Create a style that writes text without any styling.
+    pub fn new() -> StyleSpec {
+        StyleSpec::default()
+    }
+
+    /// Set the text color.
+    /// 
+    /// See [`Style::set_color`](struct.Style.html#method.set_color).
+    pub fn set_color(&mut self, color: Color) -> &mut StyleSpec {
+        self.spec.set_fg(Some(color));
+        self
+    }
+
+    /// Set the background color.
+    /// 
+    /// See [`Style::set_bg`](struct.Style.html#method.set_bg).
+    pub fn set_bg(&mut self, color: Color) -> &mut StyleSpec {
+        self.spec.set_bg(Some(color));
+        self
+    }
+
+    /// Set whether the text is bold.
+    /// 
+    /// See [`Style::set_bold`](struct.Style.html#method.set_bold).
+    pub fn set_bold(&mut self, yes: bool) -> &mut StyleSpec {
+        self.spec.set_bold(yes);
+        self
+    }
+
+    /// Set whether the text is underlined.
+    /// 
+    /// See [`Style::set_underline`](struct.Style.html#method.set_underline).
+    pub fn set_underline(&mut self, yes: bool) -> &mut StyleSpec {
+        self.spec.set_underline(yes);
+        self
+    }
+
+    /// Set whether the text is italicized.
+    /// 
+    /// See [`Style::set_italic`](struct.Style.html#method.set_italic).
+    pub fn set_italic(&mut self, yes: bool) -> &mut StyleSpec {
+        self.spec.set_italic(yes);
+        self
+    }
+
+    /// Set whether the text is written with its intense color.
+    /// 
+    /// See [`Style::set_intense`](struct.Style.html#method.set_intense).
+    pub fn set_intense(&mut self, yes: bool) -> &mut StyleSpec {
+        self.spec.set_intense(yes);
+        self
+    }
+
+    /// Set whether the text is dimmed.
+    /// 
+    /// See [`Style::set_dimmed`](struct.Style.html#method.set_dimmed).
+    pub fn set_dimmed(&mut self, yes: bool) -> &mut StyleSpec {
+        self.spec.set_dimmed(yes);
+        self
+    }
+}
+
+impl Formatter {
+    /// Create a formatter that can be printed to the writer for records at
+    /// `level` with `target`.
+    pub(crate) fn new(writer: &Writer, level: Level, target: &str) -> Self {
+        Formatter {
+            buf: SharedBuffer::new(writer.buffer(level, target), writer.windows_bold()),
+            write_style: writer.write_style_for(target),
+            timestamp_cache: None,
+            level_color: None,
+            clock: None,
+            previous_record: writer.previous_record(),
+            prints: true,
+            terminal: writer.terminal(level),
+        }
+    }
+
+    /// Whether or not the formatter can be printed to the writer for records
+    /// at `level` with `target`.
+    /// 
+    /// Targets that split records between streams might support styles on one
+    /// stream but not the other, and some record targets might have their own
+    /// style choice.
+    pub(crate) fn matches(&self, writer: &Writer, level: Level, target: &str) -> bool {
+        self.write_style() == writer.write_style_for(target)
+            && self.buf.borrow().supports_color() == writer.buffer(level, target).supports_color()
+            && self.terminal == writer.terminal(level)
+            && self.buf.windows_bold == writer.windows_bold()
+    }
+
+    /// Create a formatter that writes into an in-memory buffer.
+    /// 
+    /// Styles are only written if `write_style` is `Always`, because there's no
+    /// terminal to detect color support on. They're always written as ANSI
+    /// escape codes, even on Windows where records printed to a console are
+    /// styled through the console API instead, so the same bytes are written
+    /// on every platform.
+    /// 
+    /// This is useful for testing a custom format without a logger. Pass the
+    /// formatter and a record to the format, then read what was written with
+    /// [`buffer_bytes`].
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// # extern crate log;
+    /// # extern crate env_logger;
+    /// use std::io::{self, Write};
+    /// use log::{Level, Record};
+    /// use env_logger::fmt::{Formatter, WriteStyle};
+    /// 
+    /// fn format(buf: &mut Formatter, record: &Record) -> io::Result<()> {
+    ///     writeln!(buf, "[{}] {}", record.level(), record.args())
+    /// }
+    /// 
+    /// # fn main() {
+    /// let mut buf = Formatter::in_memory(WriteStyle::Never);
+    /// 
+    /// format(&mut buf, &Record::builder()
+    ///     .level(Level::Warn)
+    ///     .args(format_args!("disk almost full"))
+    ///     .build()).unwrap();
+    /// 
+    /// assert_eq!(b"[WARN] disk almost full\n", &*buf.buffer_bytes());
+    /// # }
+    /// ```
+    /// 
+    /// Compare the colors written by a format against a snapshot:
+    /// 
+    /// ```
+    /// # extern crate log;
+    /// # extern crate env_logger;
+    /// use std::io::{self, Write};
+    /// use log::{Level, Record};
+    /// use env_logger::fmt::{Color, Formatter, WriteStyle};
+    /// 
+    /// fn format(buf: &mut Formatter, record: &Record) -> io::Result<()> {
+    ///     let mut style = buf.style();
+    ///     style.set_color(Color::Yellow);
+    /// 
+    ///     writeln!(buf, "{} {}", style.value(record.level()), record.args())
+    /// }
+    /// 
+    /// # fn main() {
+    /// let mut buf = Formatter::in_memory(WriteStyle::Always);
+    /// 
+    /// format(&mut buf, &Record::builder()
+    ///     .level(Level::Warn)
+    ///     .args(format_args!("disk almost full"))
+    ///     .build()).unwrap();
+    /// 
+    /// assert_eq!(b"\x1b[0m\x1b[33mWARN\x1b[0m disk almost full\n", &*buf.buffer_bytes());
+    /// # }
+    /// ```
+    /// 
+    /// [`buffer_bytes`]: #method.buffer_bytes
+    pub fn in_memory(write_style: WriteStyle) -> Self {
+        let buf = match write_style {
+            WriteStyle::Always => Buffer::ansi(),
+            WriteStyle::Auto | WriteStyle::Never => Buffer::no_color(),
+        };
+
+        Formatter {
+            buf: SharedBuffer::new(buf, Default::default()),
+            write_style: write_style,
+            timestamp_cache: None,
+            level_color: None,
+            clock: None,
+            previous_record: Arc::new(Mutex::new(None)),
+            prints: false,
+            terminal: None,
+        }
+    }
+
+    pub(crate) fn write_style(&self) -> WriteStyle {
+        self.write_style
+    }
+
+    /// Use a custom color for each level in the default level styles.
+    pub(crate) fn set_level_color(&mut self, level_color: Option<Arc<LevelColorFn>>) {
+        self.level_color = level_color;
+    }
+
+    /// Use a custom clock for timestamps instead of the system clock.
+    pub(crate) fn set_clock(&mut self, clock: Option<Arc<ClockFn>>) {
+        self.clock = clock;
+    }
+
+    /// Set whether or not the records formatted into an in-memory buffer are
+    /// printed afterwards.
+    pub(crate) fn set_prints(&mut self, prints: bool) {
+        self.prints = prints;
+    }
+
+    /// The sequence number to write for the record being formatted.
+    /// 
+    /// Only records that are printed take a number, so formatting a record
+    /// without printing it doesn't leave a gap. Other records are written
+    /// with the number the next printed record will get.
+    fn sequence(&self) -> u64 {
+        if self.prints {
+            SEQUENCE.fetch_add(1, Ordering::Relaxed)
+        }
+        else {
+            SEQUENCE.load(Ordering::Relaxed)
+        }
+    }
+
+    /// Measure relative timestamps from the previous record of a logger.
+    pub(crate) fn set_previous_record(&mut self, previous_record: Arc<PreviousRecord>) {
+        self.previous_record = previous_record;
+    }
+
+    /// The current date and time in UTC, from the custom clock if there is one.
+    fn now(&self) -> DateTime<Utc> {
+        match self.clock {
+            Some(ref clock) => DateTime::from(clock()),
+            None => Utc::now(),
+        }
+    }
+
+    /// Whether or not styles will actually be written.
+    /// 
+    /// This is the final decision after the [`WriteStyle`] has been resolved
+    /// for the target, including the `RUST_LOG_STYLE`, `NO_COLOR` and
+    /// `CLICOLOR` variables and whether or not the terminal supports colors.
+    /// When it's `false`, [`Style`]s write their values without any styling.
+    /// 
+    /// # Examples
+    /// 
+    /// Mark errors with an ASCII marker when colors are off:
+    /// 
+    /// ```
+    /// # extern crate log;
+    /// # extern crate env_logger;
+    /// use std::io::Write;
+    /// use log::Level;
+    /// 
+    /// # fn main() {
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     if record.level() == Level::Error && !buf.style_enabled() {
+    ///         write!(buf, "!! ")?;
+    ///     }
+    /// 
+    ///     let level_style = buf.default_level_style(record.level());
+    /// 
+    ///     writeln!(buf, "{}: {}", level_style.value(record.level()), record.args())
+    /// });
+    /// # }
+    /// ```
+    /// 
+    /// [`WriteStyle`]: enum.WriteStyle.html
+    /// [`Style`]: struct.Style.html
+    pub fn style_enabled(&self) -> bool {
+        self.buf.borrow().supports_color()
+    }
+
+    /// Write `text` with its color blended from `from` to `to`.
+    /// 
+    /// Each character is colored along a straight path between the RGB values
+    /// of the two colors, which makes for eye-catching banners and status
+    /// lines. Text is colored by `char`, so multi-byte characters are never
+    /// split. The terminal style is reset afterwards.
+    /// 
+    /// Named colors use their conventional RGB values. If either color is an
+    /// `Ansi256` color, which has no fixed RGB value, the whole text is written
+    /// in `from` instead. If styles are disabled, only the text is written.
+    /// 
+    /// # Examples
+    /// 
+    /// Write a banner that fades from blue to green:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// use env_logger::fmt::Color;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     buf.write_gradient("myapp", Color::Rgb(0, 64, 255), Color::Rgb(0, 255, 64))?;
+    /// 
+    ///     writeln!(buf, " {}: {}", record.level(), record.args())
+    /// });
+    /// ```
+    pub fn write_gradient(&mut self, text: &str, from: Color, to: Color) -> io::Result<()> {
+        if !self.style_enabled() {
+            return self.write_all(text.as_bytes());
+        }
+
+        let write = match (color_rgb(&from), color_rgb(&to)) {
+            (Some(from), Some(to)) => self.write_blended(text, from, to),
+            _ => {
+                let mut spec = ColorSpec::new();
+                spec.set_fg(Some(from));
+
+                let mut buf = self.buf.borrow_mut();
+
+                buf.set_color(&spec).and_then(|_| buf.write_all(text.as_bytes()))
+            },
+        };
+
+        // Always try to reset the terminal style, even if writing failed
+        let reset = self.buf.borrow_mut().reset();
+
+        write.and(reset)
+    }
+
+    fn write_blended(&mut self, text: &str, from: (u8, u8, u8), to: (u8, u8, u8)) -> io::Result<()> {
+        let steps = text.chars().count().saturating_sub(1).max(1) as u32;
+
+        let mut buf = self.buf.borrow_mut();
+        let mut spec = ColorSpec::new();
+        let mut encoded = [0; 4];
+
+        for (step, c) in text.chars().enumerate() {
+            spec.set_fg(Some(blend_rgb(from, to, step as u32, steps)));
+
+            buf.set_color(&spec)?;
+            buf.write_all(c.encode_utf8(&mut encoded).as_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Get a value that's written as a clickable hyperlink to `url`.
+    /// 
+    /// Hyperlinks are written using the OSC 8 escape sequence, which modern
+    /// terminals show as `text` that opens `url` when it's clicked. Terminals
+    /// that don't support it usually just show `text`.
+    /// 
+    /// If styles are disabled, or `url` contains control characters that
+    /// would break out of the escape sequence, only `text` is written.
+    /// 
+    /// # Examples
+    /// 
+    /// Link to the documentation in each record:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let docs = buf.hyperlink("https://docs.rs/env_logger", "docs");
+    /// 
+    ///     writeln!(buf, "{}: {} (see {})", record.level(), record.args(), docs)
+    /// });
+    /// ```
+    pub fn hyperlink<'a, T>(&self, url: &'a str, text: T) -> Hyperlink<'a, T> {
+        Hyperlink {
+            url: url,
+            text: text,
+            enabled: self.style_enabled() && !url.contains(|c: char| c.is_control()),
+        }
+    }
+
+    /// Begin a new [`Style`].
+    /// 
+    /// # Examples
+    /// 
+    /// Create a bold, red colored style and use it to print the log level:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// use env_logger::fmt::Color;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let mut level_style = buf.style();
+    /// 
+    ///     level_style.set_color(Color::Red).set_bold(true);
+    /// 
+    ///     writeln!(buf, "{}: {}",
+    ///         level_style.value(record.level()),
+    ///         record.args())
+    /// });
+    /// ```
+    /// 
+    /// [`Style`]: struct.Style.html
+    pub fn style(&self) -> Style {
+        self.style_spec(ColorSpec::new())
+    }
+
+    /// Wrap a value in a style that's defined by a [`StyleSpec`].
+    /// 
+    /// Unlike a [`Style`], a `StyleSpec` isn't tied to a formatter, so it can
+    /// be defined once and applied to every record.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// use env_logger::fmt::StyleSpec;
+    /// 
+    /// let mut bold = StyleSpec::new();
+    /// bold.set_bold(true);
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(move |buf, record| {
+    ///     writeln!(buf, "{}", buf.apply(&bold, record.args()))
+    /// });
+    /// ```
+    /// 
+    /// [`StyleSpec`]: struct.StyleSpec.html
+    /// [`Style`]: struct.Style.html
+    pub fn apply<T>(&self, style: &StyleSpec, value: T) -> StyledValue<'static, T> {
+        StyledValue {
+            style: Cow::Owned(self.style_spec(style.spec.clone())),
+            value: value,
+        }
+    }
+
+    fn style_spec(&self, spec: ColorSpec) -> Style {
+        Style {
+            buf: self.buf.clone(),
+            spec: spec,
+        }
+    }
+
+    /// Write a run of output with a single [`Style`].
+    /// 
+    /// The style is applied before `write` is called, and the terminal style
+    /// is always reset afterwards, even if `write` fails. This avoids wrapping
+    /// every value in a segment that shares a style with [`Style::value`].
+    /// 
+    /// Styled values written inside `write` reset the terminal style when
+    /// they're done, so the rest of the segment is written without any style.
+    /// 
+    /// # Examples
+    /// 
+    /// Write the level and target of a record in bold:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let mut bold = buf.style();
+    ///     bold.set_bold(true);
+    /// 
+    ///     buf.style_scope(&bold, |buf| write!(buf, "{} {}", record.level(), record.target()))?;
+    /// 
+    ///     writeln!(buf, ": {}", record.args())
+    /// });
+    /// ```
+    /// 
+    /// [`Style`]: struct.Style.html
+    /// [`Style::value`]: struct.Style.html#method.value
+    pub fn style_scope<F>(&mut self, style: &Style, write: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut Formatter) -> io::Result<()>,
+    {
+        self.buf.set_color(&style.spec)?;
+
+        // Always try to reset the terminal style, even if writing failed
+        let write = write(self);
+        let reset = self.buf.borrow_mut().reset();
+
+        write.and(reset)
+    }
+
+    /// Get a [`Style`] with the conventional color for a log level.
+    /// 
+    /// The colors are:
+    /// 
+    /// | Level   | Style         |
+    /// | ------- | ------------- |
+    /// | `Error` | Bold red      |
+    /// | `Warn`  | Yellow        |
+    /// | `Info`  | Green         |
+    /// | `Debug` | Blue          |
+    /// | `Trace` | White         |
+    /// 
+    /// These are the same colors used by the default format. Like any other
+    /// `Style`, nothing is written for them if styles are disabled.
+    /// 
+    /// The colors can be replaced with [`Builder::format_level_color`]. Errors
+    /// are still bold. Use [`level_styled`] to write a level in its style.
+    /// 
+    /// # Examples
+    /// 
+    /// Color the level of each record:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let level_style = buf.default_level_style(record.level());
+    /// 
+    ///     writeln!(buf, "{}: {}",
+    ///         level_style.value(record.level()),
+    ///         record.args())
+    /// });
+    /// ```
+    /// 
+    /// [`Style`]: struct.Style.html
+    /// [`Builder::format_level_color`]: ../struct.Builder.html#method.format_level_color
+    /// [`level_styled`]: #method.level_styled
+    pub fn default_level_style(&self, level: Level) -> Style {
+        let mut level_style = self.style();
+
+        let color = match self.level_color {
+            Some(ref level_color) => level_color(level),
+            None => match level {
+                Level::Trace => Color::White,
+                Level::Debug => Color::Blue,
+                Level::Info => Color::Green,
+                Level::Warn => Color::Yellow,
+                Level::Error => Color::Red,
+            },
+        };
+
+        level_style.set_color(color).set_bold(level == Level::Error);
+
+        level_style
+    }
+
+    /// Get a level that's written in its [`default_level_style`].
+    /// 
+    /// This is a shorthand for writing
+    /// `buf.default_level_style(level).value(level)`, so the level is colored
+    /// the same way as in the default format, including any colors set with
+    /// [`Builder::format_level_color`]. Nothing is colored if styles are
+    /// disabled.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     writeln!(buf, "{}: {}", buf.level_styled(record.level()), record.args())
+    /// });
+    /// ```
+    /// 
+    /// [`default_level_style`]: #method.default_level_style
+    /// [`Builder::format_level_color`]: ../struct.Builder.html#method.format_level_color
+    pub fn level_styled(&self, level: Level) -> StyledValue<'static, Level> {
+        StyledValue {
+            style: Cow::Owned(self.default_level_style(level)),
+            value: level,
+        }
+    }
+
+    /// Get a level that's padded to a fixed width.
+    /// 
+    /// Padding is applied to the level itself, so when the level is written
+    /// as a [`Style`]d value the escape codes for the style don't count
+    /// towards its width. This is how the default format pads levels.
+    /// 
+    /// # Examples
+    /// 
+    /// Align levels to the left in a column of 5 characters:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// use env_logger::fmt::LevelPadding;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let level_style = buf.default_level_style(record.level());
+    ///     let level = buf.padded_level(record.level(), LevelPadding::Right);
+    /// 
+    ///     writeln!(buf, "{} {}",
+    ///         level_style.value(level),
+    ///         record.args())
+    /// });
+    /// ```
+    /// 
+    /// [`Style`]: struct.Style.html
+    pub fn padded_level(&self, level: Level, padding: LevelPadding) -> PaddedLevel {
+        PaddedLevel {
+            level: level,
+            padding: padding,
+            lowercase: false,
+        }
+    }
+
+    /// Get a number that's right-aligned to `width` characters.
+    /// 
+    /// Numbers that are wider than `width` are written in full. Like
+    /// [`padded_level`], padding is applied to the number itself, so it can
+    /// be written as a [`Style`]d value and still line up.
+    /// 
+    /// # Examples
+    /// 
+    /// Write the size of a request in a column of 8 characters:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// use env_logger::fmt::Color;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let mut style = buf.style();
+    ///     style.set_color(Color::Cyan);
+    /// 
+    ///     let bytes: u64 = 1024;
+    /// 
+    ///     writeln!(buf, "{} bytes: {}", style.value(buf.padded_int(bytes, 8)), record.args())
+    /// });
+    /// ```
+    /// 
+    /// [`padded_level`]: #method.padded_level
+    /// [`Style`]: struct.Style.html
+    pub fn padded_int<T: fmt::Display>(&self, value: T, width: usize) -> PaddedInt<T> {
+        PaddedInt {
+            value: value,
+            width: width,
+        }
+    }
+
+    /// Get the number of columns in the terminal the record is printed to.
+    /// 
+    /// This is `None` if the record isn't printed to standard output or
+    /// standard error, or if the stream isn't a terminal, like when it's
+    /// redirected to a file. The width is checked each time this is called,
+    /// so it changes when the terminal is resized.
+    /// 
+    /// # Examples
+    /// 
+    /// Draw a rule under errors that spans the terminal:
+    /// 
+    /// ```
+    /// # extern crate log;
+    /// # extern crate env_logger;
+    /// use std::io::Write;
+    /// use log::Level;
+    /// 
+    /// # fn main() {
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     writeln!(buf, "{}: {}", record.level(), record.args())?;
+    /// 
+    ///     match buf.terminal_width() {
+    ///         Some(width) if record.level() == Level::Error => writeln!(buf, "{}", "-".repeat(width)),
+    ///         _ => Ok(()),
+    ///     }
+    /// });
+    /// # }
+    /// ```
+    pub fn terminal_width(&self) -> Option<usize> {
+        self.terminal.and_then(terminal::width)
+    }
+
+    /// Get the thread that the record is being formatted on.
+    /// 
+    /// Records are formatted on the thread that logged them, so this is also
+    /// the thread the record came from.
+    /// 
+    /// # Examples
+    /// 
+    /// Include the thread with each record:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let thread = buf.current_thread();
+    /// 
+    ///     writeln!(buf, "[{}] {}: {}", thread, record.level(), record.args())
+    /// });
+    /// ```
+    pub fn current_thread(&self) -> CurrentThread {
+        CurrentThread {
+            thread: thread::current(),
+            id: current_thread_id(),
+        }
+    }
+
+    /// Get the single-letter name of a level.
+    /// 
+    /// Levels are written as `E`, `W`, `I`, `D` and `T`. Like other values,
+    /// the letter can be written with a [`Style`], such as the one from
+    /// [`default_level_style`].
+    /// 
+    /// # Examples
+    /// 
+    /// Write levels as colored letters:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let level_style = buf.default_level_style(record.level());
+    ///     let level = buf.compact_level(record.level());
+    /// 
+    ///     writeln!(buf, "{} {}", level_style.value(level), record.args())
+    /// });
+    /// ```
+    /// 
+    /// [`Style`]: struct.Style.html
+    /// [`default_level_style`]: #method.default_level_style
+    pub fn compact_level(&self, level: Level) -> char {
+        match level {
+            Level::Error => 'E',
+            Level::Warn => 'W',
+            Level::Info => 'I',
+            Level::Debug => 'D',
+            Level::Trace => 'T',
+        }
+    }
+
+    /// Get a [`Timestamp`] for the current date and time in UTC.
+    /// 
+    /// # Examples
+    /// 
+    /// Include the current timestamp with the log record:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let ts = buf.timestamp();
+    /// 
+    ///     writeln!(buf, "{}: {}: {}", ts, record.level(), record.args())
+    /// });
+    /// ```
+    /// 
+    /// [`Timestamp`]: struct.Timestamp.html
+    pub fn timestamp(&self) -> Timestamp {
+        Timestamp::new(self.now(), TimestampOffset::Zulu)
+    }
+
+    /// Get the timestamp for a record written by one of the built-in formats.
+    /// 
+    /// There's no timestamp if `precision` is `None`. If `refresh` is set, the
+    /// formatted timestamp is reused until it's older than `refresh`, so the
+    /// clock is only formatted once in that period. Timestamps are in UTC
+    /// unless an `offset` is given, and only include the time of day if
+    /// `time_only` is set.
+    pub(crate) fn record_timestamp(&mut self, precision: Option<TimestampPrecision>, refresh: Option<Duration>, offset: Option<UtcOffset>, time_only: bool) -> Option<RecordTimestamp> {
+        let precision = match precision {
+            Some(precision) => precision,
+            None => return None,
+        };
+
+        let timestamp = |buf: &Formatter| {
+            let timestamp = match offset {
+                Some(offset) => buf.timestamp_offset(offset).precision(precision),
+                None => buf.timestamp().precision(precision),
+            };
+
+            if time_only {
+                timestamp.time_only()
+            }
+            else {
+                timestamp
+            }
+        };
+
+        // A custom clock might not move forward with the system clock
+        let refresh = match refresh {
+            Some(refresh) if self.clock.is_none() => refresh,
+            _ => return Some(RecordTimestamp::Fresh(timestamp(self))),
+        };
+
+        let now = Instant::now();
+
+        if let Some(ref cache) = self.timestamp_cache {
+            if cache.precision == precision && cache.offset == offset && cache.time_only == time_only && now.duration_since(cache.refreshed) < refresh {
+                return Some(RecordTimestamp::Cached(cache.formatted.clone()));
+            }
+        }
+
+        let formatted: Arc<str> = Arc::from(timestamp(self).to_string());
+
+        self.timestamp_cache = Some(TimestampCache {
+            precision: precision,
+            offset: offset,
+            time_only: time_only,
+            refreshed: now,
+            formatted: formatted.clone(),
+        });
+
+        Some(RecordTimestamp::Cached(formatted))
+    }
+
+    /// Get a [`Timestamp`] for the current date and time in UTC with
+    /// millisecond precision.
+    /// 
+    /// The fractional seconds are zero-padded to 3 digits, like
+    /// `2018-02-13T23:08:32.045Z`.
+    /// 
+    /// # Examples
+    /// 
+    /// Include the current timestamp with the log record:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let ts = buf.timestamp_millis();
+    /// 
+    ///     writeln!(buf, "{}: {}: {}", ts, record.level(), record.args())
+    /// });
+    /// ```
+    /// 
+    /// [`Timestamp`]: struct.Timestamp.html
+    pub fn timestamp_millis(&self) -> Timestamp {
+        self.timestamp().precision(TimestampPrecision::Millis)
+    }
+
+    /// Get a [`Timestamp`] for the current date and time in UTC with
+    /// microsecond precision.
+    /// 
+    /// The fractional seconds are zero-padded to 6 digits, like
+    /// `2018-02-13T23:08:32.045123Z`.
+    /// 
+    /// [`Timestamp`]: struct.Timestamp.html
+    pub fn timestamp_micros(&self) -> Timestamp {
+        self.timestamp().precision(TimestampPrecision::Micros)
+    }
+
+    /// Get a [`Timestamp`] for the current date and time in UTC with
+    /// nanosecond precision.
+    /// 
+    /// The fractional seconds are zero-padded to 9 digits, like
+    /// `2018-02-13T23:08:32.045123456Z`.
+    /// 
+    /// [`Timestamp`]: struct.Timestamp.html
+    pub fn timestamp_nanos(&self) -> Timestamp {
+        self.timestamp().precision(TimestampPrecision::Nanos)
+    }
+
+    /// Get a [`CustomTimestamp`] for the current date and time in UTC, formatted
+    /// using the given format string.
+    /// 
+    /// The format string uses `strftime`-style specifiers, see the
+    /// [`chrono::format::strftime`] module for the full list.
+    /// 
+    /// # Errors
+    /// 
+    /// This method returns an error with the kind `io::ErrorKind::InvalidInput`
+    /// if the format string is invalid. Returning it from the format function
+    /// reports it like any other write error.
+    /// 
+    /// # Examples
+    /// 
+    /// Include the current timestamp with the log record:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let ts = buf.timestamp_custom("%Y/%m/%d %H:%M:%S")?;
+    /// 
+    ///     writeln!(buf, "{}: {}: {}", ts, record.level(), record.args())
+    /// });
+    /// ```
+    /// 
+    /// [`CustomTimestamp`]: struct.CustomTimestamp.html
+    /// [`chrono::format::strftime`]: https://docs.rs/chrono/0.4/chrono/format/strftime/index.html
+    pub fn timestamp_custom<'a>(&self, format: &'a str) -> io::Result<CustomTimestamp<'a>> {
+        CustomTimestamp::new(self.now(), format)
+    }
+
+    /// Get a [`Timestamp`] for the current date and time in the local timezone.
+    /// 
+    /// The timestamp is written with the local timezone's offset from UTC
+    /// instead of a `Z`.
+    /// 
+    /// # Examples
+    /// 
+    /// Include the current local timestamp with the log record:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let ts = buf.timestamp_local();
+    /// 
+    ///     writeln!(buf, "{}: {}: {}", ts, record.level(), record.args())
+    /// });
+    /// ```
+    /// 
+    /// [`Timestamp`]: struct.Timestamp.html
+    pub fn timestamp_local(&self) -> Timestamp {
+        Timestamp::new(self.now().with_timezone(&Local), TimestampOffset::Numeric)
+    }
+
+    /// Get a [`Timestamp`] for the current date and time at a fixed offset
+    /// from UTC.
+    /// 
+    /// The timestamp is written with the given offset instead of a `Z`, like
+    /// `2018-02-14T08:08:32+09:00`, no matter what the local timezone is.
+    /// Use [`Timestamp::precision`] to include fractional seconds.
+    /// 
+    /// # Examples
+    /// 
+    /// Include the current time in Japan with the log record:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// use env_logger::fmt::{TimestampPrecision, UtcOffset};
+    /// 
+    /// let jst = UtcOffset::east(9 * 3600).unwrap();
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(move |buf, record| {
+    ///     let ts = buf.timestamp_offset(jst).precision(TimestampPrecision::Millis);
+    /// 
+    ///     writeln!(buf, "{}: {}: {}", ts, record.level(), record.args())
+    /// });
+    /// ```
+    /// 
+    /// [`Timestamp`]: struct.Timestamp.html
+    /// [`Timestamp::precision`]: struct.Timestamp.html#method.precision
+    pub fn timestamp_offset(&self, offset: UtcOffset) -> Timestamp {
+        Timestamp::new(self.now().with_timezone(&offset.offset), TimestampOffset::Numeric)
+    }
+
+    /// Get a [`Timestamp`] for the current time of day in UTC, like
+    /// `23:08:32`, without the date.
+    /// 
+    /// This is the same as calling [`Timestamp::time_only`] on
+    /// [`timestamp`](#method.timestamp). Use [`Timestamp::precision`] to
+    /// include fractional seconds.
+    /// 
+    /// # Examples
+    /// 
+    /// Include the current time of day with the log record:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// use env_logger::fmt::TimestampPrecision;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let ts = buf.timestamp_time_only().precision(TimestampPrecision::Millis);
+    /// 
+    ///     writeln!(buf, "{}: {}: {}", ts, record.level(), record.args())
+    /// });
+    /// ```
+    /// 
+    /// [`Timestamp`]: struct.Timestamp.html
+    /// [`Timestamp::time_only`]: struct.Timestamp.html#method.time_only
+    /// [`Timestamp::precision`]: struct.Timestamp.html#method.precision
+    pub fn timestamp_time_only(&self) -> Timestamp {
+        self.timestamp().time_only()
+    }
+
+    /// Get a [`Timestamp`] for the current date and time in UTC with the
+    /// date written as an ISO 8601 week date, like `2024-W03-2T23:08:32Z`.
+    /// 
+    /// This is the same as calling [`Timestamp::week_date`] on
+    /// [`timestamp`](#method.timestamp).
+    /// 
+    /// Week dates are part of ISO 8601 but not RFC3339, so they're meant for
+    /// pipelines that schedule or report by ISO week, such as ones parsing
+    /// dates with Java's `DateTimeFormatter.ISO_WEEK_DATE` or Python's
+    /// `datetime.fromisoformat` (3.11 and later). Parsers that only accept
+    /// RFC3339, like chrono's `DateTime::parse_from_rfc3339`, reject them.
+    /// 
+    /// # Examples
+    /// 
+    /// Include the current week date with the log record:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let ts = buf.timestamp_week_date();
+    /// 
+    ///     writeln!(buf, "{}: {}: {}", ts, record.level(), record.args())
+    /// });
+    /// ```
+    /// 
+    /// [`Timestamp`]: struct.Timestamp.html
+    /// [`Timestamp::week_date`]: struct.Timestamp.html#method.week_date
+    pub fn timestamp_week_date(&self) -> Timestamp {
+        self.timestamp().week_date()
+    }
+
+    /// Get a [`Timestamp`] for the current date and time in UTC with the
+    /// date written as an ISO 8601 ordinal date, like `2024-016T23:08:32Z`.
+    /// 
+    /// This is the same as calling [`Timestamp::ordinal_date`] on
+    /// [`timestamp`](#method.timestamp).
+    /// 
+    /// Ordinal dates are part of ISO 8601 but not RFC3339. They're common in
+    /// scientific and industrial data, and are parsed by Java's
+    /// `DateTimeFormatter.ISO_ORDINAL_DATE` and by `strptime` with `%Y-%jT%H:%M:%S%z`.
+    /// Parsers that only accept RFC3339, like chrono's
+    /// `DateTime::parse_from_rfc3339`, reject them.
+    /// 
+    /// [`Timestamp`]: struct.Timestamp.html
+    /// [`Timestamp::ordinal_date`]: struct.Timestamp.html#method.ordinal_date
+    pub fn timestamp_ordinal_date(&self) -> Timestamp {
+        self.timestamp().ordinal_date()
+    }
+
+    /// Get a [`UnixTimestamp`] for the current time in whole seconds since
+    /// the Unix epoch, like `1518563312`.
+    /// 
+    /// # Examples
+    /// 
+    /// Include the current epoch timestamp with the log record:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let ts = buf.timestamp_unix();
+    /// 
+    ///     writeln!(buf, "{} {}: {}", ts, record.level(), record.args())
+    /// });
+    /// ```
+    /// 
+    /// [`UnixTimestamp`]: struct.UnixTimestamp.html
+    pub fn timestamp_unix(&self) -> UnixTimestamp {
+        UnixTimestamp::new(self.now(), false)
+    }
+
+    /// Get a [`UnixTimestamp`] for the current time in milliseconds since
+    /// the Unix epoch, like `1518563312045`.
+    /// 
+    /// [`UnixTimestamp`]: struct.UnixTimestamp.html
+    pub fn timestamp_unix_millis(&self) -> UnixTimestamp {
+        UnixTimestamp::new(self.now(), true)
+    }
+
+    /// Get a [`RelativeTimestamp`] for the time since the previous record.
+    /// 
+    /// The elapsed time is written in a compact human form that's easy to
+    /// follow while tailing logs:
+    /// 
+    /// - under a second in milliseconds, like `+345ms`
+    /// - under a minute in tenths of a second, like `+1.2s`
+    /// - under an hour in minutes and seconds, like `+2m05s`
+    /// - otherwise in hours and minutes, like `+1h02m`
+    /// 
+    /// The previous record is the last one this method was called for by
+    /// the same logger, on any thread, so the first record is written as
+    /// `+0ms`. A formatter created with [`in_memory`] keeps its own previous
+    /// record.
+    /// 
+    /// # Examples
+    /// 
+    /// Include the time since the previous record with the log record:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let ts = buf.timestamp_human_relative();
+    /// 
+    ///     writeln!(buf, "{:>7} {}: {}", ts, record.level(), record.args())
+    /// });
+    /// ```
+    /// 
+    /// [`RelativeTimestamp`]: struct.RelativeTimestamp.html
+    /// [`in_memory`]: #method.in_memory
+    pub fn timestamp_human_relative(&self) -> RelativeTimestamp {
+        let now = Instant::now();
+
+        let mut previous = self.previous_record.lock().unwrap_or_else(|e| e.into_inner());
+        let elapsed = previous.map(|previous| now.saturating_duration_since(previous)).unwrap_or_default();
+
+        *previous = Some(now);
+
+        RelativeTimestamp::new(elapsed)
+    }
+
+    /// Get the number of [`Scope`]s entered on the thread the record is
+    /// logged on.
+    /// 
+    /// # Examples
+    /// 
+    /// Indent records by two spaces for each scope:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     let depth = buf.scope_depth();
+    /// 
+    ///     writeln!(buf, "{:width$}{}: {}", "", record.level(), record.args(), width = depth * 2)
+    /// });
+    /// ```
+    /// 
+    /// [`Scope`]: struct.Scope.html
+    pub fn scope_depth(&self) -> usize {
+        Scope::depth()
+    }
+
+    /// Write the structured key-value pairs of a record.
+    /// 
+    /// Each pair is written as ` key=value`, including the leading space.
+    /// Keys and values that are empty or contain whitespace, control
+    /// characters, `=` or `"` are quoted and escaped. The default format
+    /// writes these pairs after the record's message.
+    /// 
+    /// With the `kv_serde` feature, maps and sequences are written as compact
+    /// JSON, like `ids=[1,2]`, instead of their `Display` implementation.
+    /// Values that can't be serialized use `Display`.
+    /// 
+    /// This method is only available with the `kv` feature.
+    /// 
+    /// # Examples
+    /// 
+    /// Write the key-value pairs after the message:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     write!(buf, "{}: {}", record.level(), record.args())?;
+    ///     buf.write_kvs(record)?;
+    ///     writeln!(buf)
+    /// });
+    /// ```
+    #[cfg(feature = "kv")]
+    pub fn write_kvs(&mut self, record: &Record) -> io::Result<()> {
+        kv::visit(record, |key, value| {
+            match (value.to_borrowed_str(), kv::nested_json(&value)) {
+                (Some(value), _) => write_text_pair(self, key.as_str(), value),
+                (None, Some(json)) => write_text_pair(self, key.as_str(), &json),
+                (None, None) => write_text_pair(self, key.as_str(), &value.to_string()),
+            }
+        })
+    }
+
+    #[cfg(test)]
+    pub(crate) fn print(&self, writer: &Writer, level: Level) -> io::Result<()> {
+        writer.print(&self.buf.borrow(), level)
+    }
+
+    /// Print a record that was logged at `logged_at`.
+    pub(crate) fn print_at(&self, writer: &Writer, level: Level, logged_at: Instant) -> io::Result<()> {
+        writer.print_at(&self.buf.borrow(), level, logged_at)
+    }
+
+    pub(crate) fn write_to(&self, out: &mut Write) -> io::Result<()> {
+        out.write_all(self.buf.borrow().as_slice())
+    }
+
+    /// Get the bytes written to the formatter so far.
+    /// 
+    /// This lets a custom format inspect a record after writing it, to decide
+    /// whether or not it should be emitted, such as for deduplication or rate
+    /// limiting. The bytes include any escape codes written for styles.
+    /// 
+    /// # Examples
+    /// 
+    /// Drop records that are too long:
+    /// 
+    /// ```
+    /// use std::io::Write;
+    /// 
+    /// let mut builder = env_logger::Builder::new();
+    /// 
+    /// builder.format(|buf, record| {
+    ///     writeln!(buf, "{}: {}", record.level(), record.args())?;
+    /// 
+    ///     let too_long = buf.buffer_bytes().len() > 1024;
+    /// 
+    ///     if too_long {
+    ///         buf.clear();
+    ///     }
+    /// 
+    ///     Ok(())
+    /// });
+    /// ```
+    pub fn buffer_bytes(&self) -> BufferBytes<'_> {
+        BufferBytes {
+            buf: self.buf.borrow(),
+        }
+    }
+
+    /// Discard everything written to the formatter so far.
+    /// 
+    /// A record that's cleared by a custom format isn't emitted at all.
+    /// See [`buffer_bytes`] for an example.
+    /// 
+    /// [`buffer_bytes`]: #method.buffer_bytes
+    pub fn clear(&mut self) {
+        self.buf.borrow_mut().clear()
+    }
+}
+
+impl Write for Formatter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.buf.borrow_mut().flush()
+    }
+}
+
+impl<'a, T> StyledValue<'a, T> {
+    fn write_fmt<F>(&self, f: F) -> fmt::Result
+    where
+        F: FnOnce() -> fmt::Result,
+    {
+        self.style.buf.set_color(&self.style.spec).map_err(|_| fmt::Error)?;
+
+        // Always try to reset the terminal style, even if writing failed
+        let write = f();
+        let reset = self.style.buf.borrow_mut().reset().map_err(|_| fmt::Error);
+
+        write.and(reset)
+    }
+}
+
+impl Timestamp {
+    fn new<Tz: TimeZone>(time: DateTime<Tz>, offset: TimestampOffset) -> Self {
+        let fixed = time.offset().fix();
+
+        Timestamp {
+            time: time.with_timezone(&fixed),
+            offset: offset,
+            precision: Default::default(),
+            time_only: false,
+            date: TimestampDate::Calendar,
+        }
+    }
+
+    /// Set the precision of the timestamp's fractional seconds.
+    pub fn precision(mut self, precision: TimestampPrecision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Write a UTC offset as `+00:00` instead of `Z`.
+    /// 
+    /// Some consumers only accept a numeric offset. Timestamps in other
+    /// timezones always have a numeric offset.
+    pub fn numeric_offset(mut self) -> Self {
+        self.offset = TimestampOffset::Numeric;
+        self
+    }
+
+    /// Only write the time of day, like `23:08:32`.
+    /// 
+    /// The date and the offset are left out. Fractional seconds are still
+    /// written with the timestamp's precision.
+    pub fn time_only(mut self) -> Self {
+        self.time_only = true;
+        self
+    }
+
+    /// Write the date as an ISO 8601 week date, like `2024-W03-2T23:08:32Z`.
+    /// 
+    /// The date is the ISO week-numbering year, the week of that year and
+    /// the day of the week, from `1` for Monday to `7` for Sunday. The year
+    /// can differ from the calendar year in the first and last days of
+    /// January and December.
+    pub fn week_date(mut self) -> Self {
+        self.date = TimestampDate::Week;
+        self
+    }
+
+    /// Write the date as an ISO 8601 ordinal date, like `2024-016T23:08:32Z`.
+    /// 
+    /// The date is the year and the day of the year, from `001` to `366`.
+    pub fn ordinal_date(mut self) -> Self {
+        self.date = TimestampDate::Ordinal;
+        self
+    }
+}
+
+impl fmt::Debug for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        /// A `Debug` wrapper for `Timestamp` that uses the `Display` implementation.
+        struct TimestampValue<'a>(&'a Timestamp);
+
+        impl<'a> fmt::Debug for TimestampValue<'a> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Display::fmt(&self.0, f)
+            }
+        }
+
+        f.debug_tuple("Timestamp")
+         .field(&TimestampValue(&self))
+         .finish()
+    }
+}
+
+impl<'a> CustomTimestamp<'a> {
+    fn new<Tz: TimeZone>(time: DateTime<Tz>, format: &'a str) -> io::Result<Self> {
+        // Check the format up-front so writing the timestamp can't fail
+        if StrftimeItems::new(format).any(|item| item == Item::Error) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid timestamp format `{}`", format)));
+        }
+
+        let fixed = time.offset().fix();
+
+        Ok(CustomTimestamp {
+            time: time.with_timezone(&fixed),
+            format: format,
+        })
+    }
+}
+
+impl<'a> fmt::Debug for CustomTimestamp<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CustomTimestamp")
+         .field("time", &self.time)
+         .field("format", &self.format)
+         .finish()
+    }
+}
+
+impl UnixTimestamp {
+    fn new(time: DateTime<Utc>, millis: bool) -> Self {
+        UnixTimestamp {
+            time: time,
+            millis: millis,
+        }
+    }
+}
+
+impl fmt::Debug for UnixTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        /// A `Debug` wrapper for `UnixTimestamp` that uses the `Display` implementation.
+        struct UnixTimestampValue<'a>(&'a UnixTimestamp);
+
+        impl<'a> fmt::Debug for UnixTimestampValue<'a> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Display::fmt(&self.0, f)
+            }
+        }
+
+        f.debug_tuple("UnixTimestamp")
+         .field(&UnixTimestampValue(&self))
+         .finish()
+    }
+}
+
+impl RelativeTimestamp {
+    fn new(elapsed: Duration) -> Self {
+        RelativeTimestamp {
+            elapsed: elapsed,
+        }
+    }
+}
+
+impl Scope {
+    /// Enter a nested scope on the current thread.
+    /// 
+    /// The scope is left when the returned guard is dropped.
+    #[must_use = "the scope is left as soon as the guard is dropped"]
+    pub fn enter() -> Scope {
+        // The depth can't be changed while the thread is being torn down
+        let _ = SCOPE_DEPTH.try_with(|depth| depth.set(depth.get() + 1));
+
+        Scope {
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Get the number of scopes entered on the current thread.
+    pub fn depth() -> usize {
+        SCOPE_DEPTH.try_with(|depth| depth.get()).unwrap_or(0)
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        let _ = SCOPE_DEPTH.try_with(|depth| depth.set(depth.get().saturating_sub(1)));
+    }
+}
+
+impl fmt::Debug for RelativeTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        /// A `Debug` wrapper for `RelativeTimestamp` that uses the `Display` implementation.
+        struct RelativeTimestampValue<'a>(&'a RelativeTimestamp);
+
+        impl<'a> fmt::Debug for RelativeTimestampValue<'a> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Display::fmt(&self.0, f)
+            }
+        }
+
+        f.debug_tuple("RelativeTimestamp")
+         .field(&RelativeTimestampValue(&self))
+         .finish()
+    }
+}
+
+impl fmt::Debug for Target {
+    fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
+        match *self {
+            Target::Stdout => f.write_str("Stdout"),
+            Target::Stderr => f.write_str("Stderr"),
+            Target::Split(level) => f.debug_tuple("Split").field(&level).finish(),
+            Target::Pipe(_) => f.write_str("Pipe(stream)"),
+            Target::Null => f.write_str("Null"),
+            #[cfg(windows)]
+            Target::DebugOutput => f.write_str("DebugOutput"),
+            Target::Multi(ref targets) => f.debug_tuple("Multi").field(targets).finish(),
+        }
+    }
+}
+
+impl fmt::Debug for Writer {
+    fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
+        f.debug_struct("Writer").finish()
+    }
+}
+
+impl fmt::Debug for Formatter {
+    fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
+        f.debug_struct("Formatter").finish()
+    }
+}
+
+impl fmt::Debug for Builder {
+    fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
+        f.debug_struct("Logger")
+        .field("target", &self.target)
+        .field("write_style", &self.write_style)
+        .field("color_choice", &self.color_choice)
+        .field("stdout_write_style", &self.stdout_write_style)
+        .field("stderr_write_style", &self.stderr_write_style)
+        .field("target_write_styles", &self.target_write_styles)
+        .field("flush_level", &self.flush_level)
+        .field("ignore_broken_pipe", &self.ignore_broken_pipe)
+        .field("strip_ansi", &self.strip_ansi)
+        .field("html", &self.html)
+        .field("windows_bold", &self.windows_bold)
+        .finish()
+    }
+}
+
+impl fmt::Debug for Format {
+    fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
+        f.debug_struct("Format")
+        .field("layout", &self.layout)
+        .field("json_fields", &self.json_fields)
+        .field("sd_daemon", &self.sd_daemon)
+        .field("notify_level", &self.notify_level)
+        .field("level_padding", &self.level_padding)
+        .field("level_compact", &self.level_compact)
+        .field("level_lowercase", &self.level_lowercase)
+        .field("full_line_color", &self.full_line_color)
+        .field("message_styles", &self.message_styles)
+        .field("sanitize_messages", &self.sanitize_messages)
+        .field("level_delimiters", &self.level_delimiters)
+        .field("target_delimiters", &self.target_delimiters)
+        .field("color_delimiters", &self.color_delimiters)
+        .field("module_path", &self.module_path)
+        .field("target_width", &self.target_width)
+        .field("target_right_align", &self.target_right_align)
+        .field("file", &self.file)
+        .field("thread_name", &self.thread_name)
+        .field("thread_id", &self.thread_id)
+        .field("sequence", &self.sequence)
+        .field("field_order", &self.field_order)
+        .field("field_separator", &self.field_separator)
+        .field("indent", &self.indent)
+        .field("scope_indent", &self.scope_indent)
+        .field("timestamp", &self.timestamp)
+        .field("timestamp_cache", &self.timestamp_cache)
+        .field("timestamp_offset", &self.timestamp_offset)
+        .field("timestamp_time_only", &self.timestamp_time_only)
+        .field("line_terminator", &String::from_utf8_lossy(&self.line_terminator))
+        .field("metadata", &self.metadata)
+        .field("line_prefix", &self.line_prefix.is_some())
+        .field("line_suffix", &self.line_suffix.is_some())
+        .finish()
+    }
+}
+
+impl<'a> fmt::Debug for BufferBytes<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
+        f.debug_struct("BufferBytes").field("len", &self.buf.len()).finish()
+    }
+}
+
+impl fmt::Debug for Style {
+    fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
+        f.debug_struct("Style").field("spec", &self.spec).finish()
+    }
+}
+
+macro_rules! impl_styled_value_fmt {
+    ($($fmt_trait:path),*) => {
+        $(
+            impl<'a, T: $fmt_trait> $fmt_trait for StyledValue<'a, T> {
+                fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
+                    self.write_fmt(|| T::fmt(&self.value, f))
+                }
+            }
+        )*
+    };
+}
+
+impl_styled_value_fmt!(
+    fmt::Debug,
+    fmt::Display,
+    fmt::Pointer,
+    fmt::Octal,
+    fmt::Binary,
+    fmt::UpperHex,
+    fmt::LowerHex,
+    fmt::UpperExp,
+    fmt::LowerExp);
+
+impl PaddedLevel {
+    /// Write the level in lowercase, like `info`.
+    pub fn lowercase(mut self) -> Self {
+        self.lowercase = true;
+        self
+    }
+}
+
+impl fmt::Display for PaddedLevel {
+    fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
+        fn pad<T: fmt::Display>(f: &mut fmt::Formatter, level: T, padding: LevelPadding) -> fmt::Result {
+            match padding {
+                LevelPadding::Left => write!(f, "{:>5}", level),
+                LevelPadding::Right => write!(f, "{:<5}", level),
+                LevelPadding::Off => write!(f, "{}", level),
+            }
+        }
+
+        if !self.lowercase {
+            return pad(f, self.level, self.padding);
+        }
+
+        let level = match self.level {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        };
+
+        pad(f, level, self.padding)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for PaddedInt<T> {
+    fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
+        write!(f, "{:>width$}", self.value, width = self.width)
+    }
+}
+
+impl<'a, T: fmt::Display> fmt::Display for Hyperlink<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
+        if self.enabled {
+            write!(f, "\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", self.url, self.text)
+        }
+        else {
+            self.text.fmt(f)
+        }
+    }
+}
+
+impl fmt::Display for CurrentThread {
+    fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
+        self.name_or_id().fmt(f)
+    }
+}
+
+impl<'a> fmt::Display for ThreadName<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
+        use std::fmt::Write;
+
+        match self.0.name() {
+            Some(name) => {
+                for c in name.chars() {
+                    if c.is_control() {
+                        for c in c.escape_default() {
+                            f.write_char(c)?;
+                        }
+                    }
+                    else {
+                        f.write_char(c)?;
+                    }
+                }
+
+                Ok(())
+            },
+            None => write!(f, "{}", self.0.id()),
+        }
+    }
+}
+
+impl fmt::Display for RecordTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
+        match *self {
+            RecordTimestamp::Fresh(ref ts) => ts.fmt(f),
+            RecordTimestamp::Cached(ref ts) => f.write_str(ts),
+        }
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
+        const CALENDAR_DATE: &'static [Item<'static>] = {
+            use chrono::format::Item::*;
+            use chrono::format::Numeric::*;
+            use chrono::format::Pad::*;
+
+            &[
+                Numeric(Year, Zero),
+                Literal("-"),
+                Numeric(Month, Zero),
+                Literal("-"),
+                Numeric(Day, Zero),
+                Literal("T"),
+            ]
+        };
+
+        const WEEK_DATE: &'static [Item<'static>] = {
+            use chrono::format::Item::*;
+            use chrono::format::Numeric::*;
+            use chrono::format::Pad::*;
+
+            &[
+                Numeric(IsoYear, Zero),
+                Literal("-W"),
+                Numeric(IsoWeek, Zero),
+                Literal("-"),
+                Numeric(WeekdayFromMon, Zero),
+                Literal("T"),
+            ]
+        };
+
+        const ORDINAL_DATE: &'static [Item<'static>] = {
+            use chrono::format::Item::*;
+            use chrono::format::Numeric::*;
+            use chrono::format::Pad::*;
+
+            &[
+                Numeric(Year, Zero),
+                Literal("-"),
+                Numeric(Ordinal, Zero),
+                Literal("T"),
+            ]
+        };
+
+        const TIME: &'static [Item<'static>] = {
+            use chrono::format::Item::*;
+            use chrono::format::Numeric::*;
+            use chrono::format::Pad::*;
+
+            &[
+                Numeric(Hour, Zero),
+                Literal(":"),
+                Numeric(Minute, Zero),
+                Literal(":"),
+                Numeric(Second, Zero),
+            ]
+        };
+
+        let (fraction, offset) = {
+            use chrono::format::Item::*;
+            use chrono::format::Fixed::*;
+
+            let fraction = match self.precision {
+                TimestampPrecision::Seconds => None,
+                TimestampPrecision::Millis => Some(Fixed(Nanosecond3)),
+                TimestampPrecision::Micros => Some(Fixed(Nanosecond6)),
+                TimestampPrecision::Nanos => Some(Fixed(Nanosecond9)),
+            };
+
+            let offset = match self.offset {
+                _ if self.time_only => None,
+                TimestampOffset::Zulu => Some(Fixed(TimezoneOffsetZ)),
+                TimestampOffset::Numeric => Some(Fixed(TimezoneOffsetColon)),
+            };
+
+            (fraction, offset)
+        };
+
+        let date = match self.date {
+            _ if self.time_only => &[],
+            TimestampDate::Calendar => CALENDAR_DATE,
+            TimestampDate::Week => WEEK_DATE,
+            TimestampDate::Ordinal => ORDINAL_DATE,
+        };
+
+        let items = date.iter().chain(TIME).cloned().chain(fraction).chain(offset);
+
+        self.time.format_with_items(items).fmt(f)
+    }
+}
+
+impl<'a> fmt::Display for CustomTimestamp<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
+        self.time.format(self.format).fmt(f)
+    }
+}
+
+impl fmt::Display for UnixTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
+        if self.millis {
+            self.time.timestamp_millis().fmt(f)
+        }
+        else {
+            self.time.timestamp().fmt(f)
+        }
+    }
+}
+
+impl fmt::Display for RelativeTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
+        let secs = self.elapsed.as_secs();
+
+        // Format into a string first so the timestamp can be padded
+        let formatted = match secs {
+            0 => format!("+{}ms", self.elapsed.subsec_millis()),
+            1..=59 => format!("+{}.{}s", secs, self.elapsed.subsec_millis() / 100),
+            60..=3599 => format!("+{}m{:02}s", secs / 60, secs % 60),
+            _ => format!("+{}h{:02}m", secs / 3600, secs / 60 % 60),
+        };
+
+        f.pad(&formatted)
+    }
+}
+
+/// Resolve an `Auto` style choice using conventional environment variables.
+/// 
+/// The variables are checked in order:
+/// 
+/// 1. If `CLICOLOR_FORCE` is set to a non-zero value then styles are always written.
+/// 2. If `NO_COLOR` is set to a non-empty value then styles are never written.
+/// 3. If `CLICOLOR` is set to `0` then styles are never written.
+/// 
+/// Explicit `Always` and `Never` choices are returned unchanged.
+fn env_write_style<F>(write_style: WriteStyle, var: F) -> WriteStyle
+where
+    F: Fn(&str) -> Option<OsString>,
+{
+    if write_style != WriteStyle::Auto {
+        return write_style;
+    }
+
+    let var = |name| var(name).filter(|value| !value.is_empty());
+
+    if var("CLICOLOR_FORCE").map_or(false, |force| force != "0") {
+        return WriteStyle::Always;
+    }
+
+    if var("NO_COLOR").is_some() {
+        return WriteStyle::Never;
+    }
+
+    if var("CLICOLOR").map_or(false, |clicolor| clicolor == "0") {
+        return WriteStyle::Never;
+    }
+
+    WriteStyle::Auto
+}
+
+fn parse_write_style(spec: &str) -> WriteStyle {
+    match spec {
+        "auto" => WriteStyle::Auto,
+        "always" => WriteStyle::Always,
+        "never" => WriteStyle::Never,
+        _ => Default::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+    use testing::SharedBuf;
+
+    #[test]
+    fn parse_write_style_valid() {
+        let inputs = vec![
+            ("auto", WriteStyle::Auto),
+            ("always", WriteStyle::Always),
+            ("never", WriteStyle::Never),
+        ];
+
+        for (input, expected) in inputs {
+            assert_eq!(expected, parse_write_style(input));
+        }
+    }
+
+    fn write_record(format: &mut Format, record: &Record) -> String {
+        let mut buf = Formatter::in_memory(WriteStyle::Never);
+
+        format.build()(&mut buf, record).expect("failed to format record");
+
+        let written = buf.buf.borrow().as_slice().to_vec();
+
+        String::from_utf8(written).expect("formatted record isn't utf8")
+    }
+
+    #[test]
+    fn format_sd_daemon_prefix() {
+        let mut format = Format {
+            sd_daemon: true,
+            custom_format: Some(Box::new(|buf, record| writeln!(buf, "{}", record.args()))),
+            ..Default::default()
+        };
+
+        let written = write_record(&mut format, &Record::builder()
+            .level(Level::Warn)
+            .args(format_args!("log message"))
+            .build());
+
+        assert_eq!("<4>log message\n", written);
+    }
+
+    #[test]
+    fn format_notification() {
+        let long = "x".repeat(NOTIFICATION_WIDTH + 1);
+        let truncated = format!("{}…", "x".repeat(NOTIFICATION_WIDTH - 1));
+
+        let inputs = vec![
+            (Level::Error, WriteStyle::Always, "disk\nfull", "disk\nfull\n\x1b]9;disk full\x1b\\".to_owned()),
+            (Level::Warn, WriteStyle::Always, "disk\nfull", "disk\nfull\n".to_owned()),
+            (Level::Error, WriteStyle::Never, "disk\nfull", "disk\nfull\n".to_owned()),
+            (Level::Error, WriteStyle::Always, &long, format!("{}\n\x1b]9;{}\x1b\\", long, truncated)),
+        ];
+
+        for (level, write_style, message, expected) in inputs {
+            let mut format = Format {
+                notify_level: Some(Level::Error),
+                custom_format: Some(Box::new(|buf, record| writeln!(buf, "{}", record.args()))),
+                ..Default::default()
+            };
+
+            let mut buf = Formatter::in_memory(write_style);
+
+            format.build()(&mut buf, &Record::builder()
+                .level(level)
+                .args(format_args!("{}", message))
+                .build()).unwrap();
+
+            assert_eq!(expected, String::from_utf8(buf.buf.borrow().as_slice().to_vec()).unwrap());
+        }
+    }
+
+    #[test]
+    fn format_notification_structured() {
+        let inputs = vec![
+            (Layout::Text, true),
+            (Layout::Json, false),
+            (Layout::Logfmt, false),
+        ];
+
+        for (layout, expected) in inputs {
+            let mut format = Format {
+                layout: layout,
+                notify_level: Some(Level::Error),
+                ..Default::default()
+            };
+
+            let mut buf = Formatter::in_memory(WriteStyle::Always);
+
+            format.build()(&mut buf, &Record::builder()
+                .level(Level::Error)
+                .args(format_args!("disk full"))
+                .build()).unwrap();
+
+            let written = String::from_utf8(buf.buf.borrow().as_slice().to_vec()).unwrap();
+
+            assert_eq!(expected, written.contains("\x1b]9;"));
+        }
+    }
+
+    #[test]
+    fn format_sd_daemon_priorities() {
+        let inputs = vec![
+            (Level::Error, 3),
+            (Level::Warn, 4),
+            (Level::Info, 6),
+            (Level::Debug, 7),
+            (Level::Trace, 7),
+        ];
+
+        for (input, expected) in inputs {
+            assert_eq!(expected, sd_daemon_priority(input));
+        }
+    }
+
+    #[test]
+    fn default_format_metadata() {
+        let mut format = Format {
+            metadata: vec![
+                ("region".to_owned(), "us-east-1".to_owned()),
+                ("instance".to_owned(), "web 01".to_owned()),
+                ("empty".to_owned(), "".to_owned()),
+                ("host name".to_owned(), "web".to_owned()),
+                ("tag".to_owned(), "a\u{1b}b".to_owned()),
+            ],
+            ..Default::default()
+        };
+
+        let written = write_record(&mut format, &Record::builder()
+            .level(Level::Info)
+            .args(format_args!("log message"))
+            .build());
+
+        assert!(written.ends_with(": log message region=us-east-1 instance=\"web 01\" empty=\"\" \"host name\"=web tag=\"a\\u{1b}b\"\n"));
+    }
+
+    #[test]
+    fn style_set_reset() {
+        let inputs = vec![
+            (true, &b"\x1b[0m\x1b[33mWARN\x1b[0m"[..]),
+            (false, &b"\x1b[33mWARN\x1b[0m"[..]),
+        ];
+
+        for (reset, expected) in inputs {
+            let mut buf = Formatter::in_memory(WriteStyle::Always);
+
+            let mut style = buf.style();
+            style.set_color(Color::Yellow).set_reset(reset);
+
+            write!(buf, "{}", style.value("WARN")).unwrap();
+
+            assert_eq!(expected, buf.buf.borrow().as_slice());
+        }
+    }
+
+    #[test]
+    fn style_scope_resets() {
+        let mut buf = Formatter::in_memory(WriteStyle::Always);
+
+        let mut style = buf.style();
+        style.set_color(Color::Yellow);
+
+        buf.style_scope(&style, |buf| write!(buf, "a {}", "b")).unwrap();
+
+        let err = buf.style_scope(&style, |_| Err(io::Error::new(io::ErrorKind::Other, "failed")));
+
+        assert!(err.is_err());
+        assert_eq!(b"\x1b[0m\x1b[33ma b\x1b[0m\x1b[0m\x1b[33m\x1b[0m", buf.buf.borrow().as_slice());
+    }
+
+    #[test]
+    fn apply_style_spec() {
+        let mut style = StyleSpec::new();
+        style.set_color(Color::Yellow).set_bg(Color::Blue).set_underline(true);
+
+        let inputs = vec![
+            (WriteStyle::Always, &b"\x1b[0m\x1b[4m\x1b[33m\x1b[44ma\x1b[0m b"[..]),
+            (WriteStyle::Never, &b"a b"[..]),
+        ];
+
+        for (write_style, expected) in inputs {
+            let mut buf = Formatter::in_memory(write_style);
+
+            write!(buf, "{} b", buf.apply(&style, "a")).unwrap();
+
+            assert_eq!(expected, buf.buf.borrow().as_slice());
+        }
+    }
+
+    #[test]
+    fn style_enabled() {
+        let inputs = vec![
+            (WriteStyle::Always, true),
+            (WriteStyle::Auto, false),
+            (WriteStyle::Never, false),
+        ];
+
+        for (write_style, expected) in inputs {
+            assert_eq!(expected, Formatter::in_memory(write_style).style_enabled());
+        }
+    }
+
+    #[test]
+    fn hyperlink() {
+        let inputs = vec![
+            (WriteStyle::Always, "https://example.com", "\x1b]8;;https://example.com\x1b\\docs\x1b]8;;\x1b\\"),
+            (WriteStyle::Always, "https://example.com/\x1b[31m", "docs"),
+            (WriteStyle::Never, "https://example.com", "docs"),
+        ];
+
+        for (write_style, url, expected) in inputs {
+            let buf = Formatter::in_memory(write_style);
+
+            assert_eq!(expected, buf.hyperlink(url, "docs").to_string());
+        }
+    }
+
+    #[test]
+    fn write_gradient() {
+        let mut buf = Formatter::in_memory(WriteStyle::Always);
+
+        buf.write_gradient("aé", Color::Rgb(0, 0, 0), Color::Rgb(255, 100, 0)).unwrap();
+
+        let written = buf.buf.borrow().as_slice().to_vec();
+
+        assert_eq!("\x1b[0m\x1b[38;2;0;0;0ma\x1b[0m\x1b[38;2;255;100;0mé\x1b[0m", String::from_utf8(written).unwrap());
+    }
+
+    #[test]
+    fn write_gradient_no_style() {
+        let mut buf = Formatter::in_memory(WriteStyle::Never);
+
+        buf.write_gradient("aé", Color::Red, Color::Blue).unwrap();
+
+        assert_eq!("aé".as_bytes(), buf.buf.borrow().as_slice());
+    }
+
+    #[test]
+    fn blend_rgb_steps() {
+        let inputs = vec![
+            (0, Color::Rgb(0, 200, 100)),
+            (1, Color::Rgb(50, 150, 100)),
+            (4, Color::Rgb(200, 0, 100)),
+        ];
+
+        for (step, expected) in inputs {
+            assert_eq!(expected, blend_rgb((0, 200, 100), (200, 0, 100), step, 4));
+        }
+    }
+
+    #[test]
+    fn buffer_bytes_and_clear() {
+        let mut buf = Formatter::in_memory(WriteStyle::Never);
+
+        write!(buf, "log message").unwrap();
+
+        assert_eq!(b"log message", &*buf.buffer_bytes());
+
+        buf.clear();
+
+        assert!(buf.buffer_bytes().is_empty());
+    }
+
+    #[test]
+    fn padded_level() {
+        let buf = Formatter::in_memory(WriteStyle::Never);
+
+        let inputs = vec![
+            (Level::Info, LevelPadding::Left, " INFO"),
+            (Level::Info, LevelPadding::Right, "INFO "),
+            (Level::Info, LevelPadding::Off, "INFO"),
+            (Level::Error, LevelPadding::Right, "ERROR"),
+        ];
+
+        for (level, padding, expected) in inputs {
+            assert_eq!(expected, buf.padded_level(level, padding).to_string());
+        }
+    }
+
+    #[test]
+    fn padded_level_lowercase() {
+        let buf = Formatter::in_memory(WriteStyle::Never);
+
+        let inputs = vec![
+            (Level::Info, LevelPadding::Left, " info"),
+            (Level::Info, LevelPadding::Right, "info "),
+            (Level::Trace, LevelPadding::Off, "trace"),
+        ];
+
+        for (level, padding, expected) in inputs {
+            assert_eq!(expected, buf.padded_level(level, padding).lowercase().to_string());
+        }
+    }
+
+    #[test]
+    fn padded_level_ignores_styles() {
+        let mut buf = Formatter::in_memory(WriteStyle::Always);
+
+        let style = buf.default_level_style(Level::Warn);
+        let level = buf.padded_level(Level::Warn, LevelPadding::Right);
+
+        write!(buf, "{}", style.value(level)).unwrap();
+
+        assert_eq!(b"\x1b[0m\x1b[33mWARN \x1b[0m", buf.buf.borrow().as_slice());
+    }
+
+    #[test]
+    fn padded_int() {
+        let buf = Formatter::in_memory(WriteStyle::Never);
+
+        let inputs = vec![
+            (7, 4, "   7"),
+            (-42, 4, " -42"),
+            (1024, 4, "1024"),
+            (123456, 4, "123456"),
+            (7, 0, "7"),
+        ];
+
+        for (value, width, expected) in inputs {
+            assert_eq!(expected, buf.padded_int(value, width).to_string());
+        }
+
+        assert_eq!("  18446744073709551615", buf.padded_int(u64::MAX, 22).to_string());
+    }
+
+    #[test]
+    fn padded_int_ignores_styles() {
+        let mut buf = Formatter::in_memory(WriteStyle::Always);
+
+        let mut style = buf.style();
+        style.set_color(Color::Cyan);
+
+        write!(buf, "{}", style.value(buf.padded_int(42, 5))).unwrap();
+
+        assert_eq!(b"\x1b[0m\x1b[36m   42\x1b[0m", buf.buf.borrow().as_slice());
+    }
+
+    #[test]
+    fn default_format_level_compact() {
+        let mut format = Format {
+            level_compact: true,
+            timestamp: None,
+            module_path: false,
+            ..Default::default()
+        };
+
+        let written = write_record(&mut format, &Record::builder()
+            .level(Level::Warn)
+            .args(format_args!("log message"))
+            .build());
+
+        assert_eq!("W: log message\n", written);
+    }
+
+    #[test]
+    fn default_format_level_lowercase() {
+        let brackets = Some(("[".to_owned(), "]".to_owned()));
+
+        let inputs = vec![
+            (false, LevelPadding::Left, None, WriteStyle::Never, " warn: log message\n"),
+            (false, LevelPadding::Right, None, WriteStyle::Never, "warn : log message\n"),
+            (true, LevelPadding::Left, None, WriteStyle::Never, "w: log message\n"),
+            (false, LevelPadding::Off, brackets.clone(), WriteStyle::Never, "[warn] log message\n"),
+            (false, LevelPadding::Off, None, WriteStyle::Always, "\x1b[0m\x1b[33mwarn\x1b[0m: log message\n"),
+        ];
+
+        for (compact, padding, delimiters, write_style, expected) in inputs {
+            let mut format = Format {
+                level_lowercase: true,
+                level_compact: compact,
+                level_padding: padding,
+                level_delimiters: delimiters,
+                timestamp: None,
+                module_path: false,
+                ..Default::default()
+            };
+
+            let mut buf = Formatter::in_memory(write_style);
+
+            format.build()(&mut buf, &Record::builder()
+                .level(Level::Warn)
+                .args(format_args!("log message"))
+                .build()).unwrap();
+
+            assert_eq!(expected, String::from_utf8(buf.buf.borrow().as_slice().to_vec()).unwrap());
+        }
+    }
+
+    #[test]
+    fn compact_level_styled() {
+        let mut buf = Formatter::in_memory(WriteStyle::Always);
+
+        let style = buf.default_level_style(Level::Warn);
+        let level = buf.compact_level(Level::Warn);
+
+        write!(buf, "{}", style.value(level)).unwrap();
+
+        assert_eq!(b"\x1b[0m\x1b[33mW\x1b[0m", buf.buf.borrow().as_slice());
+    }
+
+    #[test]
+    fn default_format_delimiters() {
+        let brackets = Some(("[".to_owned(), "]".to_owned()));
+
+        let inputs = vec![
+            (brackets.clone(), None, LevelPadding::Off, 0, WriteStyle::Never, "[INFO] myapp: log message\n"),
+            (brackets.clone(), brackets.clone(), LevelPadding::Off, 0, WriteStyle::Never, "[INFO] [myapp] log message\n"),
+            (None, brackets.clone(), LevelPadding::Off, 0, WriteStyle::Never, "INFO: [myapp] log message\n"),
+            (brackets.clone(), None, LevelPadding::Left, 0, WriteStyle::Never, " [INFO] myapp: log message\n"),
+            (brackets.clone(), brackets.clone(), LevelPadding::Right, 8, WriteStyle::Never, "[INFO]  [myapp]    log message\n"),
+            (brackets.clone(), None, LevelPadding::Off, 0, WriteStyle::Always, "[\x1b[0m\x1b[32mINFO\x1b[0m] myapp: log message\n"),
+        ];
+
+        for (level_delimiters, target_delimiters, level_padding, target_width, write_style, expected) in inputs {
+            let mut format = Format {
+                level_delimiters: level_delimiters,
+                target_delimiters: target_delimiters,
+                level_padding: level_padding,
+                target_width: target_width,
+                timestamp: None,
+                ..Default::default()
+            };
+
+            let mut buf = Formatter::in_memory(write_style);
+
+            format.build()(&mut buf, &Record::builder()
+                .level(Level::Info)
+                .module_path(Some("myapp"))
+                .args(format_args!("log message"))
+                .build()).unwrap();
+
+            assert_eq!(expected, String::from_utf8(buf.buf.borrow().as_slice().to_vec()).unwrap());
+        }
+    }
+
+    #[test]
+    fn default_format_color_delimiters() {
+        let mut format = Format {
+            level_delimiters: Some(("[".to_owned(), "]".to_owned())),
+            color_delimiters: true,
+            level_padding: LevelPadding::Off,
+            timestamp: None,
+            module_path: false,
+            ..Default::default()
+        };
+
+        let mut buf = Formatter::in_memory(WriteStyle::Always);
+
+        format.build()(&mut buf, &Record::builder()
+            .level(Level::Info)
+            .args(format_args!("log message"))
+            .build()).unwrap();
+
+        assert_eq!(&b"\x1b[0m\x1b[32m[INFO]\x1b[0m log message\n"[..], buf.buf.borrow().as_slice());
+    }
+
+    #[test]
+    fn default_format_full_line_color() {
+        let inputs = vec![
+            (WriteStyle::Always, "\x1b[0m\x1b[1m\x1b[31mERROR: log message\x1b[0m\n"),
+            (WriteStyle::Never, "ERROR: log message\n"),
+        ];
+
+        for (write_style, expected) in inputs {
+            let mut format = Format {
+                full_line_color: true,
+                timestamp: None,
+                module_path: false,
+                ..Default::default()
+            };
+
+            let mut buf = Formatter::in_memory(write_style);
+
+            format.build()(&mut buf, &Record::builder()
+                .level(Level::Error)
+                .args(format_args!("log message"))
+                .build()).unwrap();
+
+            assert_eq!(expected.as_bytes(), buf.buf.borrow().as_slice());
+        }
+    }
+
+    #[test]
+    fn default_format_message_style() {
+        let mut bold = StyleSpec::new();
+        bold.set_bold(true);
+
+        let inputs = vec![
+            (Level::Error, false, WriteStyle::Always, "\x1b[0m\x1b[1m\x1b[31mERROR\x1b[0m: \x1b[0m\x1b[1mlog message\x1b[0m\n"),
+            (Level::Warn, false, WriteStyle::Always, "\x1b[0m\x1b[33mWARN\x1b[0m: log message\n"),
+            (Level::Error, true, WriteStyle::Always, "\x1b[0m\x1b[1m\x1b[31mERROR: \x1b[0m\x1b[1mlog message\x1b[0m\x1b[0m\x1b[1m\x1b[31m\x1b[0m\n"),
+            (Level::Error, false, WriteStyle::Never, "ERROR: log message\n"),
+        ];
+
+        for (level, full_line_color, write_style, expected) in inputs {
+            let mut format = Format {
+                message_styles: vec![(Level::Error, bold.clone())],
+                full_line_color: full_line_color,
+                level_padding: LevelPadding::Off,
+                timestamp: None,
+                module_path: false,
+                ..Default::default()
+            };
+
+            let mut buf = Formatter::in_memory(write_style);
+
+            format.build()(&mut buf, &Record::builder()
+                .level(level)
+                .args(format_args!("log message"))
+                .build()).unwrap();
+
+            assert_eq!(expected, String::from_utf8(buf.buf.borrow().as_slice().to_vec()).unwrap());
+        }
+    }
+
+    #[test]
+    fn default_format_scope_indent() {
+        let inputs = vec![
+            (2, vec!["INFO: outer\n", "INFO:   inner\n", "INFO:     innermost\n", "INFO:   inner\n", "INFO: outer\n"]),
+            (0, vec!["INFO: outer\n", "INFO: inner\n", "INFO: innermost\n", "INFO: inner\n", "INFO: outer\n"]),
+        ];
+
+        for (scope_indent, expected) in inputs {
+            let format = Format {
+                scope_indent: scope_indent,
+                level_padding: LevelPadding::Off,
+                timestamp: None,
+                module_path: false,
+                ..Default::default()
+            }.build();
+
+            let write = |message: &str| {
+                let mut buf = Formatter::in_memory(WriteStyle::Never);
+
+                format(&mut buf, &Record::builder()
+                    .level(Level::Info)
+                    .args(format_args!("{}", message))
+                    .build()).unwrap();
+
+                let written = buf.buf.borrow().as_slice().to_vec();
+
+                String::from_utf8(written).unwrap()
+            };
+
+            let mut written = vec![write("outer")];
+
+            {
+                let _outer = Scope::enter();
+                written.push(write("inner"));
+
+                {
+                    let _inner = Scope::enter();
+                    written.push(write("innermost"));
+                }
+
+                written.push(write("inner"));
+            }
+
+            written.push(write("outer"));
+
+            assert_eq!(expected, written);
+            assert_eq!(0, Scope::depth());
+        }
+    }
+
+    #[test]
+    fn default_format_scope_indent_continuation_lines() {
+        let mut format = Format {
+            indent: Some(2),
+            level_padding: LevelPadding::Off,
+            timestamp: None,
+            module_path: false,
+            ..Default::default()
+        };
+
+        let _scope = Scope::enter();
+
+        let written = write_record(&mut format, &Record::builder()
+            .level(Level::Info)
+            .args(format_args!("first\nsecond"))
+            .build());
+
+        assert_eq!("INFO:   first\n    second\n", written);
+    }
+
+    #[test]
+    fn default_format_sanitize_messages() {
+        let inputs = vec![
+            (false, "ERROR: \x1b[2Jcleared\rhidden\n  next line\n"),
+            (true, "ERROR: \\u{1b}[2Jcleared\\rhidden\n  next line\n"),
+        ];
+
+        for (sanitize_messages, expected) in inputs {
+            let mut format = Format {
+                sanitize_messages: sanitize_messages,
+                indent: Some(2),
+                level_padding: LevelPadding::Off,
+                timestamp: None,
+                module_path: false,
+                ..Default::default()
+            };
+
+            let mut buf = Formatter::in_memory(WriteStyle::Never);
+
+            format.build()(&mut buf, &Record::builder()
+                .level(Level::Error)
+                .args(format_args!("{}", "\x1b[2Jcleared\rhidden\nnext line"))
+                .build()).unwrap();
+
+            assert_eq!(expected, String::from_utf8(buf.buf.borrow().as_slice().to_vec()).unwrap());
+        }
+
+        // Styles written by the format itself are kept
+        let mut format = Format {
+            sanitize_messages: true,
+            level_padding: LevelPadding::Off,
+            timestamp: None,
+            module_path: false,
+            ..Default::default()
+        };
+
+        let mut buf = Formatter::in_memory(WriteStyle::Always);
+
+        format.build()(&mut buf, &Record::builder()
+            .level(Level::Error)
+            .args(format_args!("{}", "\x1b[31m"))
+            .build()).unwrap();
+
+        assert_eq!("\x1b[0m\x1b[1m\x1b[31mERROR\x1b[0m: \\u{1b}[31m\n", String::from_utf8(buf.buf.borrow().as_slice().to_vec()).unwrap());
+    }
+
+    #[test]
+    fn default_format_sequence() {
+        let mut format = Format {
+            sequence: true,
+            ..Default::default()
+        };
+        let format = format.build();
+
+        let sequence = |prints| {
+            let mut buf = Formatter::in_memory(WriteStyle::Never);
+            buf.set_prints(prints);
+
+            format(&mut buf, &Record::builder()
+                .level(Level::Info)
+                .args(format_args!("log message"))
+                .build()).unwrap();
+
+            let written = String::from_utf8(buf.buf.borrow().as_slice().to_vec()).unwrap();
+
+            written.split(' ').next().unwrap().parse::<u64>().unwrap()
+        };
+
+        let first = sequence(true);
+        let second = sequence(true);
+
+        assert_eq!(first + 1, second);
+
+        // Records that aren't printed don't take a number
+        assert_eq!(second + 1, sequence(false));
+        assert_eq!(second + 1, sequence(false));
+        assert_eq!(second + 1, sequence(true));
+    }
+
+    #[test]
+    fn default_format_target_width() {
+        let inputs = vec![
+            (0, false, "myapp::net", "myapp::net: log message\n"),
+            (12, false, "myapp::net", "myapp::net  : log message\n"),
+            (12, true, "myapp::net", "  myapp::net: log message\n"),
+            (8, false, "myapp::net", "myapp::…: log message\n"),
+            (10, true, "myapp::net", "myapp::net: log message\n"),
+        ];
+
+        for (target_width, target_right_align, record_module_path, expected) in inputs {
+            let mut format = Format {
+                level_padding: LevelPadding::Off,
+                timestamp: None,
+                target_width: target_width,
+                target_right_align: target_right_align,
+                ..Default::default()
+            };
+
+            let written = write_record(&mut format, &Record::builder()
+                .level(Level::Info)
+                .module_path(Some(record_module_path))
+                .args(format_args!("log message"))
+                .build());
+
+            assert_eq!(format!("INFO: {}", expected), written);
+        }
+    }
+
+    #[test]
+    fn default_format_field_order() {
+        let inputs = vec![
+            (vec![Field::Level, Field::Message], " ", Some("myapp"), " INFO log message\n"),
+            (vec![Field::Message, Field::Level], " ", Some("myapp"), "log message  INFO\n"),
+            (vec![Field::ModulePath, Field::Level, Field::Message], " | ", Some("myapp"), "myapp |  INFO | log message\n"),
+            (vec![Field::ModulePath, Field::Level, Field::Message], " | ", None, " INFO | log message\n"),
+            (vec![Field::Target, Field::File, Field::Message], " ", None, "myapp::net src/net.rs:10 log message\n"),
+            (vec![Field::Timestamp, Field::Message], " ", None, "log message\n"),
+            (vec![], " ", None, "\n"),
+        ];
+
+        for (field_order, field_separator, module_path, expected) in inputs {
+            let mut format = Format {
+                field_order: Some(field_order),
+                field_separator: field_separator.to_owned(),
+                timestamp: None,
+                ..Default::default()
+            };
+
+            let written = write_record(&mut format, &Record::builder()
+                .level(Level::Info)
+                .target("myapp::net")
+                .module_path(module_path)
+                .file(Some("src/net.rs"))
+                .line(Some(10))
+                .args(format_args!("log message"))
+                .build());
+
+            assert_eq!(expected, written);
+        }
+    }
+
+    #[test]
+    fn default_format_module_path() {
+        let inputs = vec![
+            (true, Some("myapp::net"), ": myapp::net: log message\n"),
+            (true, None, "Z: log message\n"),
+            (false, Some("myapp::net"), "Z: log message\n"),
+        ];
+
+        for (module_path, record_module_path, expected) in inputs {
+            let mut format = Format {
+                module_path: module_path,
+                ..Default::default()
+            };
+
+            let written = write_record(&mut format, &Record::builder()
+                .level(Level::Info)
+                .module_path(record_module_path)
+                .args(format_args!("log message"))
+                .build());
+
+            assert!(written.ends_with(expected), "{:?} doesn't end with {:?}", written, expected);
+        }
+    }
+
+    #[test]
+    fn default_format_file() {
+        let inputs = vec![
+            (Some("src/main.rs"), Some(12), "Z: src/main.rs:12: log message\n"),
+            (Some("src/main.rs"), None, "Z: src/main.rs: log message\n"),
+            (None, Some(12), "Z: log message\n"),
+        ];
+
+        for (file, line, expected) in inputs {
+            let mut format = Format {
+                file: true,
+                ..Default::default()
+            };
+
+            let written = write_record(&mut format, &Record::builder()
+                .level(Level::Info)
+                .file(file)
+                .line(line)
+                .args(format_args!("log message"))
+                .build());
+
+            assert!(written.ends_with(expected), "{:?} doesn't end with {:?}", written, expected);
+        }
+    }
+
+    #[test]
+    fn default_format_thread() {
+        let inputs = vec![
+            (true, false, Some("worker"), "Z [worker]: "),
+            (true, false, None, "Z [{id}]: "),
+            (true, true, Some("worker"), "Z [worker:{id}]: "),
+            (true, true, None, "Z [{id}]: "),
+            (false, true, Some("worker"), "Z [{id}]: "),
+            (true, false, Some("bad\nname"), "Z [bad\\nname]: "),
+        ];
+
+        for (thread_name, thread_id, name, expected) in inputs {
+            let mut builder = thread::Builder::new();
+
+            if let Some(name) = name {
+                builder = builder.name(name.to_owned());
+            }
+
+            let (written, id) = builder.spawn(move || {
+                let mut format = Format {
+                    thread_name: thread_name,
+                    thread_id: thread_id,
+                    module_path: false,
+                    ..Default::default()
+                };
+
+                let written = write_record(&mut format, &Record::builder()
+                    .level(Level::Info)
+                    .args(format_args!("log message"))
+                    .build());
+
+                (written, thread_id_of_current())
+            }).unwrap().join().unwrap();
+
+            let expected = format!("{}log message\n", expected.replace("{id}", &id.to_string()));
+
+            assert!(written.ends_with(&expected), "{:?} doesn't end with {:?}", written, expected);
+        }
+    }
+
+    fn thread_id_of_current() -> u64 {
+        Formatter::in_memory(WriteStyle::Never).current_thread().id()
+    }
+
+    #[test]
+    fn thread_ids_differ() {
+        let main = thread_id_of_current();
+        let other = thread::spawn(thread_id_of_current).join().unwrap();
+
+        assert_ne!(0, main);
+        assert_ne!(main, other);
+        assert_eq!(main, thread_id_of_current());
+    }
+
+    #[test]
+    fn format_line_terminator() {
+        let inputs = vec![
+            (Layout::Text, &b"\r\n"[..], ": log message\r\n"),
+            (Layout::Text, &b""[..], ": log message"),
+            (Layout::Json, &b"\r\n"[..], "\"message\":\"log message\"}\r\n"),
+            (Layout::Logfmt, &b""[..], "msg=\"log message\""),
+        ];
+
+        for (layout, line_terminator, expected) in inputs {
+            let mut format = Format {
+                layout: layout,
+                line_terminator: line_terminator.to_vec(),
+                ..Default::default()
+            };
+
+            let written = write_record(&mut format, &Record::builder()
+                .level(Level::Info)
+                .args(format_args!("log message"))
+                .build());
+
+            assert!(written.ends_with(expected), "{:?} doesn't end with {:?}", written, expected);
+        }
+    }
+
+    #[test]
+    fn format_timestamp() {
+        let inputs = vec![
+            (Layout::Text, None, "INFO: log message\n"),
+            (Layout::Text, Some(TimestampPrecision::Micros), "Z: log message\n"),
+            (Layout::Json, None, "{\"level\":\"INFO\""),
+            (Layout::Logfmt, None, "level=info "),
+        ];
+
+        for (layout, timestamp, expected) in inputs {
+            let mut format = Format {
+                layout: layout,
+                timestamp: timestamp,
+                ..Default::default()
+            };
+
+            let written = write_record(&mut format, &Record::builder()
+                .level(Level::Info)
+                .args(format_args!("log message"))
+                .build());
+
+            assert!(written.contains(expected), "{:?} doesn't contain {:?}", written, expected);
+        }
+    }
+
+    #[test]
+    fn format_timestamp_precision() {
+        let mut format = Format {
+            timestamp: Some(TimestampPrecision::Micros),
+            ..Default::default()
+        };
+
+        let written = write_record(&mut format, &Record::builder()
+            .level(Level::Info)
+            .args(format_args!("log message"))
+            .build());
+
+        // A level, a space, then a timestamp with 6 fractional digits
+        assert_eq!(Some(' '), written.chars().nth(5));
+        assert_eq!(Some('.'), written.chars().nth(25));
+        assert_eq!(Some('Z'), written.chars().nth(32));
+    }
+
+    #[test]
+    fn default_format_line_prefix_suffix() {
+        let mut format = Format {
+            timestamp: None,
+            sd_daemon: true,
+            line_prefix: Some(Box::new(|buf, _| write!(buf, "[host=web01] "))),
+            line_suffix: Some(Box::new(|buf, record| write!(buf, " ({})", record.target()))),
+            ..Default::default()
+        };
+
+        let written = write_record(&mut format, &Record::builder()
+            .level(Level::Info)
+            .target("myapp")
+            .args(format_args!("log message"))
+            .build());
+
+        assert_eq!("<6>[host=web01]  INFO: log message (myapp)\n", written);
+    }
+
+    #[test]
+    fn default_format_indent() {
+        let mut format = Format {
+            indent: Some(4),
+            ..Default::default()
+        };
+
+        let written = write_record(&mut format, &Record::builder()
+            .level(Level::Info)
+            .args(format_args!("log\nmessage\n"))
+            .build());
+
+        assert!(written.ends_with(": log\n    message\n    \n"));
+    }
+
+    #[test]
+    fn default_format_max_line_width() {
+        let inputs = vec![
+            (Some(15), "short", "I: short\n"),
+            (Some(15), "a long message", "I: a long mess…\n"),
+            (Some(15), "123456789012", "I: 123456789012\n"),
+            (Some(15), "1234567890123", "I: 12345678901…\n"),
+            (Some(3), "message", "I: \n"),
+            (None, "a long message", "I: a long message\n"),
+        ];
+
+        for (width, message, expected) in inputs {
+            let mut format = Format {
+                level_compact: true,
+                timestamp: None,
+                max_line_width: width,
+                ..Default::default()
+            };
+
+            let written = write_record(&mut format, &Record::builder()
+                .level(Level::Info)
+                .args(format_args!("{}", message))
+                .build());
+
+            assert_eq!(expected, written);
+        }
+    }
+
+    #[test]
+    fn default_format_max_line_width_ignores_prefix() {
+        let inputs = vec![
+            (true, "", "<6>I: a long mess…\n"),
+            (false, "written: ", "written: I: a long mess…\n"),
+        ];
+
+        for (sd_daemon, written, expected) in inputs {
+            let mut format = Format {
+                sd_daemon: sd_daemon,
+                level_compact: true,
+                timestamp: None,
+                max_line_width: Some(15),
+                ..Default::default()
+            };
+
+            let mut buf = Formatter::in_memory(WriteStyle::Never);
+            write!(buf, "{}", written).unwrap();
+
+            format.build()(&mut buf, &Record::builder()
+                .level(Level::Info)
+                .args(format_args!("a long message"))
+                .build()).unwrap();
+
+            assert_eq!(expected, String::from_utf8(buf.buffer_bytes().to_vec()).unwrap());
+        }
+    }
+
+    #[test]
+    fn truncate_message() {
+        let inputs = vec![
+            ("message", 7, "message"),
+            ("message", 4, "mes…"),
+            ("caf\u{e9} au lait", 5, "caf\u{e9}…"),
+            ("message", 1, "…"),
+            ("message", 0, ""),
+        ];
+
+        for (message, width, expected) in inputs {
+            assert_eq!(expected, truncate(message, width));
+        }
+    }
+
+    #[test]
+    fn default_format_no_indent() {
+        let written = write_record(&mut Default::default(), &Record::builder()
+            .level(Level::Info)
+            .args(format_args!("log\nmessage"))
+            .build());
+
+        assert!(written.ends_with(": log\nmessage\n"));
+    }
+
+    #[test]
+    fn record_timestamp_cache() {
+        let mut buf = Formatter::in_memory(WriteStyle::Never);
+
+        let cached = |ts| match ts {
+            Some(RecordTimestamp::Cached(ts)) => ts,
+            _ => panic!("expected a cached timestamp"),
+        };
+
+        let seconds = Some(TimestampPrecision::Seconds);
+        let hour = Some(Duration::from_secs(3600));
+
+        let jst = UtcOffset::east(9 * 3600);
+
+        let first = cached(buf.record_timestamp(seconds, hour, None, false));
+        let second = cached(buf.record_timestamp(seconds, hour, None, false));
+        let refreshed = cached(buf.record_timestamp(seconds, Some(Duration::from_secs(0)), None, false));
+        let millis = cached(buf.record_timestamp(Some(TimestampPrecision::Millis), hour, None, false));
+        let offset = cached(buf.record_timestamp(Some(TimestampPrecision::Millis), hour, jst, false));
+        let time_only = cached(buf.record_timestamp(Some(TimestampPrecision::Millis), hour, jst, true));
+
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(!Arc::ptr_eq(&first, &refreshed));
+        assert!(!Arc::ptr_eq(&refreshed, &millis));
+        assert_eq!(24, millis.len());
+        assert!(offset.ends_with("+09:00"));
+        assert!(!Arc::ptr_eq(&offset, &time_only));
+        assert_eq!(12, time_only.len());
+    }
+
+    #[test]
+    fn timestamp_custom_clock() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_518_563_312_045);
+
+        let mut buf = Formatter::in_memory(WriteStyle::Never);
+        buf.set_clock(Some(Arc::new(move || time)));
+
+        let jst = UtcOffset::east(9 * 3600).unwrap();
+
+        let inputs = vec![
+            (buf.timestamp().to_string(), "2018-02-13T23:08:32Z"),
+            (buf.timestamp_millis().to_string(), "2018-02-13T23:08:32.045Z"),
+            (buf.timestamp_offset(jst).to_string(), "2018-02-14T08:08:32+09:00"),
+            (buf.timestamp_custom("%H:%M").unwrap().to_string(), "23:08"),
+            (buf.timestamp_unix().to_string(), "1518563312"),
+        ];
+
+        for (actual, expected) in inputs {
+            assert_eq!(expected, actual);
+        }
+
+        // Timestamps from a custom clock aren't cached
+        match buf.record_timestamp(Some(TimestampPrecision::Seconds), Some(Duration::from_secs(3600)), None, false) {
+            Some(RecordTimestamp::Fresh(ts)) => assert_eq!("2018-02-13T23:08:32Z", ts.to_string()),
+            _ => panic!("expected a fresh timestamp"),
+        }
+    }
+
+    #[test]
+    fn record_timestamp_no_cache() {
+        let mut buf = Formatter::in_memory(WriteStyle::Never);
+
+        match buf.record_timestamp(Some(TimestampPrecision::Seconds), None, None, false) {
+            Some(RecordTimestamp::Fresh(_)) => (),
+            _ => panic!("expected a fresh timestamp"),
+        }
+
+        assert!(buf.record_timestamp(None, None, None, false).is_none());
+    }
+
+    #[test]
+    fn timestamp_fixed_offset() {
+        let buf = Formatter::in_memory(WriteStyle::Never);
+
+        let inputs = vec![
+            (9 * 3600, TimestampPrecision::Seconds, 25, "+09:00"),
+            (-(5 * 3600 + 30 * 60), TimestampPrecision::Millis, 29, "-05:30"),
+            (0, TimestampPrecision::Seconds, 25, "+00:00"),
+        ];
+
+        for (secs, precision, len, suffix) in inputs {
+            let ts = buf.timestamp_offset(UtcOffset::east(secs).unwrap()).precision(precision).to_string();
+
+            assert_eq!(len, ts.len());
+            assert!(ts.ends_with(suffix), "{:?} doesn't end with {:?}", ts, suffix);
+        }
+    }
+
+    #[test]
+    fn timestamp_time_only() {
+        let time = DateTime::parse_from_rfc3339("2018-02-13T23:08:32.045678+02:00").unwrap();
+
+        let inputs = vec![
+            (TimestampPrecision::Seconds, "23:08:32"),
+            (TimestampPrecision::Millis, "23:08:32.045"),
+            (TimestampPrecision::Micros, "23:08:32.045678"),
+        ];
+
+        for (precision, expected) in inputs {
+            let ts = Timestamp::new(time, TimestampOffset::Numeric).precision(precision).time_only();
+
+            assert_eq!(expected, ts.to_string());
+        }
+    }
+
+    #[test]
+    fn timestamp_week_and_ordinal_date() {
+        let inputs = vec![
+            ("2024-01-16T23:08:32Z", "2024-W03-2T23:08:32Z", "2024-016T23:08:32Z"),
+            // The first days of a year can be in the last week of the year before
+            ("2021-01-03T00:00:00Z", "2020-W53-7T00:00:00Z", "2021-003T00:00:00Z"),
+            ("2024-12-31T12:00:00+02:00", "2025-W01-2T12:00:00+02:00", "2024-366T12:00:00+02:00"),
+        ];
+
+        for (input, week_date, ordinal_date) in inputs {
+            let time = DateTime::parse_from_rfc3339(input).unwrap();
+            let offset = if input.ends_with('Z') { TimestampOffset::Zulu } else { TimestampOffset::Numeric };
+
+            assert_eq!(week_date, Timestamp::new(time, offset).week_date().to_string());
+            assert_eq!(ordinal_date, Timestamp::new(time, offset).ordinal_date().to_string());
+        }
+
+        let time = DateTime::parse_from_rfc3339("2024-01-16T23:08:32.045Z").unwrap();
+        let ts = Timestamp::new(time, TimestampOffset::Zulu).ordinal_date().precision(TimestampPrecision::Millis);
+
+        assert_eq!("2024-016T23:08:32.045Z", ts.to_string());
+        assert_eq!("23:08:32", Timestamp::new(time, TimestampOffset::Zulu).week_date().time_only().to_string());
+    }
+
+    #[test]
+    fn timestamp_utc_offset() {
+        let time = DateTime::parse_from_rfc3339("2018-02-13T23:08:32Z").unwrap().with_timezone(&Utc);
+        let ts = Timestamp::new(time, TimestampOffset::Zulu);
+
+        assert_eq!("2018-02-13T23:08:32Z", ts.to_string());
+    }
+
+    #[test]
+    fn timestamp_numeric_offset() {
+        let time = DateTime::parse_from_rfc3339("2018-02-13T23:08:32+02:00").unwrap();
+        let ts = Timestamp::new(time, TimestampOffset::Numeric);
+
+        assert_eq!("2018-02-13T23:08:32+02:00", ts.to_string());
+    }
+
+    #[test]
+    fn timestamp_utc_numeric_offset() {
+        let time = DateTime::parse_from_rfc3339("2018-02-13T23:08:32.045Z").unwrap().with_timezone(&Utc);
+
+        let inputs = vec![
+            (Timestamp::new(time, TimestampOffset::Zulu).numeric_offset(), "2018-02-13T23:08:32+00:00"),
+            (Timestamp::new(time, TimestampOffset::Zulu).precision(TimestampPrecision::Millis).numeric_offset(), "2018-02-13T23:08:32.045+00:00"),
+            (Timestamp::new(time, TimestampOffset::Zulu).numeric_offset().time_only(), "23:08:32"),
+        ];
+
+        for (input, expected) in inputs {
+            assert_eq!(expected, input.to_string());
+        }
+    }
+
+    #[test]
+    fn timestamp_precision() {
+        let time = DateTime::parse_from_rfc3339("2018-02-13T23:08:32.045000678Z").unwrap().with_timezone(&Utc);
+
+        let inputs = vec![
+            (TimestampPrecision::Seconds, "2018-02-13T23:08:32Z"),
+            (TimestampPrecision::Millis, "2018-02-13T23:08:32.045Z"),
+            (TimestampPrecision::Micros, "2018-02-13T23:08:32.045000Z"),
+            (TimestampPrecision::Nanos, "2018-02-13T23:08:32.045000678Z"),
+        ];
+
+        for (input, expected) in inputs {
+            let ts = Timestamp::new(time, TimestampOffset::Zulu).precision(input);
+
+            assert_eq!(expected, ts.to_string());
+        }
+    }
+
+    #[test]
+    fn timestamp_unix() {
+        let time = DateTime::parse_from_rfc3339("2018-02-13T23:08:32.045678Z").unwrap().with_timezone(&Utc);
+
+        let inputs = vec![
+            (false, "1518563312"),
+            (true, "1518563312045"),
+        ];
+
+        for (input, expected) in inputs {
+            let ts = UnixTimestamp::new(time, input);
+
+            assert_eq!(expected, ts.to_string());
+            assert_eq!(format!("UnixTimestamp({})", expected), format!("{:?}", ts));
+        }
+    }
+
+    #[test]
+    fn timestamp_human_relative() {
+        let inputs = vec![
+            (Duration::from_millis(0), "+0ms"),
+            (Duration::from_millis(345), "+345ms"),
+            (Duration::from_millis(999), "+999ms"),
+            (Duration::from_millis(1000), "+1.0s"),
+            (Duration::from_millis(1250), "+1.2s"),
+            (Duration::from_millis(59_999), "+59.9s"),
+            (Duration::from_secs(125), "+2m05s"),
+            (Duration::from_secs(3599), "+59m59s"),
+            (Duration::from_secs(3720), "+1h02m"),
+            (Duration::from_secs(100 * 3600), "+100h00m"),
+        ];
+
+        for (input, expected) in inputs {
+            let ts = RelativeTimestamp::new(input);
+
+            assert_eq!(expected, ts.to_string());
+            assert_eq!(format!("RelativeTimestamp({})", expected), format!("{:?}", ts));
+        }
+
+        assert_eq!("  +1.2s", format!("{:>7}", RelativeTimestamp::new(Duration::from_millis(1250))));
+    }
+
+    #[test]
+    fn timestamp_human_relative_per_formatter() {
+        let first = Formatter::in_memory(WriteStyle::Never);
+        let second = Formatter::in_memory(WriteStyle::Never);
+
+        assert_eq!("+0ms", first.timestamp_human_relative().to_string());
+
+        thread::sleep(Duration::from_millis(5));
+
+        assert_eq!("+0ms", second.timestamp_human_relative().to_string());
+        assert_ne!("+0ms", first.timestamp_human_relative().to_string());
+    }
+
+    #[test]
+    fn timestamp_custom_format() {
+        let time = DateTime::parse_from_rfc3339("2018-02-13T23:08:32Z").unwrap();
+        let ts = CustomTimestamp::new(time, "%Y/%m/%d %H:%M:%S").unwrap();
+
+        assert_eq!("2018/02/13 23:08:32", ts.to_string());
+    }
+
+    #[test]
+    fn timestamp_custom_format_invalid() {
+        let time = DateTime::parse_from_rfc3339("2018-02-13T23:08:32Z").unwrap();
+        let err = CustomTimestamp::new(time, "%Y %!").unwrap_err();
+
+        assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+    }
+
+    /// A pipe that only accepts a single byte on each write.
+    struct TrickleBuf(SharedBuf);
+
+    impl Write for TrickleBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            thread::yield_now();
+
+            self.0.write(&buf[..buf.len().min(1)])
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn pipe_target_records_not_interleaved() {
+        let pipe = SharedBuf::default();
+
+        let writer = Arc::new(Builder::new()
+            .target(Target::Pipe(Box::new(TrickleBuf(pipe.clone()))))
+            .try_build()
+            .unwrap());
+
+        let threads: Vec<_> = (0..8).map(|t| {
+            let writer = writer.clone();
+
+            thread::spawn(move || {
+                for i in 0..50 {
+                    let mut buf = Formatter::new(&writer, Level::Info, "");
+
+                    // Write each record in several pieces
+                    write!(buf, "thread {}", t).unwrap();
+                    write!(buf, " record {}", i).unwrap();
+                    writeln!(buf, " done").unwrap();
+
+                    buf.print(&writer, Level::Info).unwrap();
+                }
+            })
+        }).collect();
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let written = String::from_utf8(pipe.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<&str> = written.lines().collect();
+
+        assert_eq!(8 * 50, lines.len());
+
+        for line in lines {
+            let parts: Vec<&str> = line.split(' ').collect();
+
+            assert_eq!(5, parts.len(), "interleaved record: {:?}", line);
+            assert_eq!(("thread", "record", "done"), (parts[0], parts[2], parts[4]), "interleaved record: {:?}", line);
+        }
+    }
+
+    #[test]
+    fn pipe_target() {
+        let pipe = SharedBuf::default();
+
+        let writer = Builder::new()
+            .target(Target::Pipe(Box::new(pipe.clone())))
+            .try_build()
+            .unwrap();
+
+        assert_eq!(WriteStyle::Never, writer.write_style());
+
+        let mut buf = Formatter::new(&writer, Level::Info, "");
+
+        let mut style = buf.style();
+        style.set_bold(true);
+
+        writeln!(buf, "log {}", style.value("message")).unwrap();
+        buf.print(&writer, Level::Info).unwrap();
+
+        assert_eq!(b"log message\n", &pipe.0.lock().unwrap()[..]);
+    }
+
+    #[test]
+    fn html_target() {
+        let pipe = SharedBuf::default();
+
+        let writer = Builder::new()
+            .target(Target::Pipe(Box::new(pipe.clone())))
+            .html(true)
+            .try_build()
+            .unwrap();
+
+        let mut buf = Formatter::new(&writer, Level::Error, "");
+        let style = buf.default_level_style(Level::Error);
+        writeln!(buf, "{}: <b>failed</b>", style.value(Level::Error)).unwrap();
+
+        buf.print(&writer, Level::Error).unwrap();
+
+        assert_eq!("<span style=\"color:#cd0000;font-weight:bold\">ERROR</span>: &lt;b&gt;failed&lt;/b&gt;\n", String::from_utf8(pipe.0.lock().unwrap().clone()).unwrap());
+    }
+
+    #[test]
+    fn strip_ansi_without_styles() {
+        let inputs = vec![
+            (true, WriteStyle::Never, &b"ERROR: log message\n"[..]),
+            (false, WriteStyle::Never, &b"\x1b[31mERROR\x1b[0m: log message\n"[..]),
+            (true, WriteStyle::Always, &b"\x1b[31mERROR\x1b[0m: log message\n"[..]),
+        ];
+
+        for (strip_ansi, write_style, expected) in inputs {
+            let pipe = SharedBuf::default();
+
+            let writer = Builder::new()
+                .target(Target::Pipe(Box::new(pipe.clone())))
+                .write_style(write_style)
+                .strip_ansi(strip_ansi)
+                .try_build()
+                .unwrap();
+
+            let mut buf = Formatter::new(&writer, Level::Error, "");
+
+            write!(buf, "\x1b[31mERROR\x1b[0m: log message\n").unwrap();
+            buf.print(&writer, Level::Error).unwrap();
+
+            assert_eq!(expected, &pipe.0.lock().unwrap()[..]);
+        }
+    }
+
+    #[test]
+    fn pipe_target_bom() {
+        let inputs = vec![
+            (true, &b"\xef\xbb\xbffirst\nsecond\n"[..]),
+            (false, &b"first\nsecond\n"[..]),
+        ];
+
+        for (bom, expected) in inputs {
+            let pipe = SharedBuf::default();
+
+            let writer = Builder::new()
+                .target(Target::Pipe(Box::new(pipe.clone())))
+                .bom(bom)
+                .try_build()
+                .unwrap();
+
+            for record in &["first", "second"] {
+                let mut buf = Formatter::new(&writer, Level::Info, "");
+
+                writeln!(buf, "{}", record).unwrap();
+                buf.print(&writer, Level::Info).unwrap();
+            }
+
+            assert_eq!(expected, &pipe.0.lock().unwrap()[..]);
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct BrokenPipe(Arc<Mutex<usize>>);
+
+    impl Write for BrokenPipe {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            *self.0.lock().unwrap() += 1;
+
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn broken_pipe() {
+        let inputs = vec![
+            (true, false, 1),
+            (false, true, 2),
+        ];
+
+        for (ignore_broken_pipe, expected_err, expected_writes) in inputs {
+            let pipe = BrokenPipe::default();
+
+            let writer = Builder::new()
+                .target(Target::Pipe(Box::new(pipe.clone())))
+                .ignore_broken_pipe(ignore_broken_pipe)
+                .try_build()
+                .unwrap();
+
+            let mut buf = Formatter::new(&writer, Level::Info, "");
+
+            writeln!(buf, "log message").unwrap();
+
+            assert_eq!(expected_err, buf.print(&writer, Level::Info).is_err());
+            assert_eq!(expected_err, buf.print(&writer, Level::Info).is_err());
+            assert_eq!(ignore_broken_pipe, writer.is_closed(Level::Info));
+            assert_eq!(expected_writes, *pipe.0.lock().unwrap());
+        }
+    }
+
+    #[test]
+    fn multi_target() {
+        let inputs = vec![
+            (WriteStyle::Always, "\x1b[0m\x1b[1mlog message\x1b[0m\n"),
+            (WriteStyle::Never, "log message\n"),
+        ];
+
+        for (write_style, expected) in inputs {
+            let first = SharedBuf::default();
+            let second = SharedBuf::default();
+
+            let writer = Builder::new()
+                .target(Target::Multi(vec![
+                    Target::Pipe(Box::new(first.clone())),
+                    Target::Null,
+                    Target::Pipe(Box::new(second.clone())),
+                ]))
+                .write_style(write_style)
+                .try_build()
+                .unwrap();
+
+            let mut buf = Formatter::new(&writer, Level::Info, "");
+
+            let mut style = buf.style();
+            style.set_bold(true);
+
+            writeln!(buf, "{}", style.value("log message")).unwrap();
+            buf.print(&writer, Level::Info).unwrap();
+
+            assert_eq!(expected, String::from_utf8(first.0.lock().unwrap().clone()).unwrap());
+            assert_eq!(expected, String::from_utf8(second.0.lock().unwrap().clone()).unwrap());
+        }
+    }
+
+    #[test]
+    fn multi_target_keeps_printing_after_errors() {
+        let broken = BrokenPipe::default();
+        let pipe = SharedBuf::default();
+
+        let writer = Builder::new()
+            .target(Target::Multi(vec![
+                Target::Pipe(Box::new(broken.clone())),
+                Target::Pipe(Box::new(pipe.clone())),
+            ]))
+            .ignore_broken_pipe(false)
+            .try_build()
+            .unwrap();
+
+        let mut buf = Formatter::new(&writer, Level::Info, "");
+
+        writeln!(buf, "log message").unwrap();
+
+        assert!(buf.print(&writer, Level::Info).is_err());
+        assert!(buf.print(&writer, Level::Info).is_err());
+        assert_eq!(b"log message\nlog message\n", &pipe.0.lock().unwrap()[..]);
+    }
+
+    #[test]
+    fn null_target() {
+        let writer = Builder::new()
+            .target(Target::Null)
+            .write_style(WriteStyle::Always)
+            .try_build()
+            .unwrap();
+
+        let mut buf = Formatter::new(&writer, Level::Info, "");
+
+        let mut style = buf.style();
+        style.set_bold(true);
+
+        writeln!(buf, "log {}", style.value("message")).unwrap();
+
+        assert_eq!(b"log \x1b[0m\x1b[1mmessage\x1b[0m\n", &*buf.buffer_bytes());
+
+        buf.print(&writer, Level::Info).unwrap();
+        writer.flush().unwrap();
+    }
+
+    #[test]
+    fn split_target_levels() {
+        let stdout = BufferWriter::stdout(ColorChoice::Never);
+        let stderr = BufferWriter::stderr(ColorChoice::Never);
+
+        let inputs = vec![
+            (Level::Error, true),
+            (Level::Warn, true),
+            (Level::Info, false),
+            (Level::Debug, false),
+            (Level::Trace, false),
+        ];
+
+        for (level, expected) in inputs {
+            let writer = split_writer(&stdout, &stderr, Level::Warn, level);
+
+            assert_eq!(expected, ::std::ptr::eq(writer, &stderr), "{}", level);
+        }
+    }
+
+    #[test]
+    fn color_choice_replaces_write_style() {
+        let mut builder = Builder::new();
+
+        builder.write_style(WriteStyle::Never).color_choice(ColorChoice::AlwaysAnsi);
+
+        assert_eq!(WriteStyle::Always, builder.get_write_style());
+        assert_eq!(WriteStyle::Always, builder.try_build().unwrap().write_style());
+
+        builder.color_choice(ColorChoice::AlwaysAnsi).write_style(WriteStyle::Never);
+
+        assert_eq!(None, builder.color_choice);
+        assert_eq!(WriteStyle::Never, builder.get_write_style());
+    }
+
+    #[test]
+    fn split_target_stream_write_styles() {
+        let writer = Builder::new()
+            .target(Target::Split(Level::Warn))
+            .write_style(WriteStyle::Never)
+            .stderr_write_style(WriteStyle::Always)
+            .try_build()
+            .unwrap();
+
+        assert!(writer.buffer(Level::Error, "").supports_color());
+        assert!(!writer.buffer(Level::Info, "").supports_color());
+
+        let writer = Builder::new()
+            .target(Target::Split(Level::Warn))
+            .color_choice(ColorChoice::Always)
+            .stdout_write_style(WriteStyle::Never)
+            .try_build()
+            .unwrap();
+
+        assert!(writer.buffer(Level::Error, "").supports_color());
+        assert!(!writer.buffer(Level::Info, "").supports_color());
+    }
+
+    #[test]
+    fn target_write_styles() {
+        let writer = Builder::new()
+            .target(Target::Pipe(Box::new(Vec::new())))
+            .write_style(WriteStyle::Always)
+            .target_write_style("hyper", WriteStyle::Never)
+            .target_write_style("hyper::server", WriteStyle::Auto)
+            .target_write_style("quiet", WriteStyle::Always)
+            .target_write_style("quiet", WriteStyle::Never)
+            .try_build()
+            .unwrap();
+
+        let inputs = vec![
+            ("app", WriteStyle::Always),
+            ("hyper", WriteStyle::Never),
+            ("hyper::client", WriteStyle::Never),
+            // The longest prefix is used, and `Auto` goes back to the writer's choice
+            ("hyper::server", WriteStyle::Always),
+            ("hyper::server::conn", WriteStyle::Always),
+            ("quiet", WriteStyle::Never),
+        ];
+
+        for (target, expected) in inputs {
+            assert_eq!(expected, writer.write_style_for(target), "{}", target);
+            assert_eq!(expected == WriteStyle::Always, writer.buffer(Level::Info, target).supports_color(), "{}", target);
+
+            let buf = Formatter::new(&writer, Level::Info, target);
+
+            assert_eq!(expected == WriteStyle::Always, buf.style_enabled(), "{}", target);
+            assert!(buf.matches(&writer, Level::Info, target));
+        }
+
+        let buf = Formatter::new(&writer, Level::Info, "app");
+
+        assert!(!buf.matches(&writer, Level::Info, "hyper"));
+    }
+
+    #[test]
+    fn try_build_unstyled() {
+        let writer = Builder::new()
+            .target(Target::Split(Level::Warn))
+            .color_choice(ColorChoice::Always)
+            .stdout_write_style(WriteStyle::Always)
+            .stderr_write_style(WriteStyle::Always)
+            .target_write_style("app", WriteStyle::Always)
+            .try_build_styled(false)
+            .unwrap();
+
+        for target in &["", "app", "app::net"] {
+            assert_eq!(WriteStyle::Never, writer.write_style_for(target), "{}", target);
+            assert!(!writer.buffer(Level::Error, target).supports_color(), "{}", target);
+            assert!(!writer.buffer(Level::Info, target).supports_color(), "{}", target);
+        }
+    }
+
+    #[test]
+    fn color_choices_per_stream_terminal() {
+        let inputs = vec![
+            (WriteStyle::Auto, true, true, ColorChoice::Auto, ColorChoice::Auto),
+            // Only standard output is redirected
+            (WriteStyle::Auto, false, true, ColorChoice::Never, ColorChoice::Auto),
+            // Only standard error is redirected
+            (WriteStyle::Auto, true, false, ColorChoice::Auto, ColorChoice::Never),
+            (WriteStyle::Auto, false, false, ColorChoice::Never, ColorChoice::Never),
+            (WriteStyle::Always, false, false, ColorChoice::Always, ColorChoice::Always),
+            (WriteStyle::Never, true, true, ColorChoice::Never, ColorChoice::Never),
+        ];
+
+        for (write_style, stdout_terminal, stderr_terminal, stdout_expected, stderr_expected) in inputs {
+            let mut builder = Builder::new();
+            builder.write_style(write_style);
+
+            let is_terminal = |stream| match stream {
+                terminal::Stream::Stdout => stdout_terminal,
+                terminal::Stream::Stderr => stderr_terminal,
+            };
+
+            let (_, stdout_color_choice, stderr_color_choice) = builder.color_choices(vars(&[]), is_terminal);
+
+            assert_eq!(stdout_expected, stdout_color_choice);
+            assert_eq!(stderr_expected, stderr_color_choice);
+        }
+    }
+
+    #[test]
+    fn color_choices_stream_write_styles() {
+        let not_terminal = |_| false;
+
+        // A forced style applies even if the stream isn't a terminal
+        let mut builder = Builder::new();
+        builder.stderr_write_style(WriteStyle::Always);
+
+        let (write_style, stdout_color_choice, stderr_color_choice) = builder.color_choices(vars(&[]), not_terminal);
+
+        assert_eq!(WriteStyle::Auto, write_style);
+        assert_eq!(ColorChoice::Never, stdout_color_choice);
+        assert_eq!(ColorChoice::Always, stderr_color_choice);
+
+        let (_, stdout_color_choice, _) = builder.color_choices(vars(&[("CLICOLOR_FORCE", "1")]), not_terminal);
+
+        assert_eq!(ColorChoice::Always, stdout_color_choice);
+
+        // Color choices are used as-is
+        let mut builder = Builder::new();
+        builder.color_choice(ColorChoice::Auto);
+
+        let (_, stdout_color_choice, stderr_color_choice) = builder.color_choices(vars(&[]), not_terminal);
+
+        assert_eq!(ColorChoice::Auto, stdout_color_choice);
+        assert_eq!(ColorChoice::Auto, stderr_color_choice);
+    }
+
+    fn vars(vars: &'static [(&'static str, &'static str)]) -> impl Fn(&str) -> Option<OsString> {
+        move |name| vars.iter().find(|&&(var, _)| var == name).map(|&(_, value)| value.into())
+    }
+
+    #[test]
+    fn env_write_style_no_color() {
+        let inputs = vec![
+            (WriteStyle::Auto, vars(&[]), WriteStyle::Auto),
+            (WriteStyle::Auto, vars(&[("NO_COLOR", "")]), WriteStyle::Auto),
+            (WriteStyle::Auto, vars(&[("NO_COLOR", "1")]), WriteStyle::Never),
+            (WriteStyle::Always, vars(&[("NO_COLOR", "1")]), WriteStyle::Always),
+            (WriteStyle::Never, vars(&[]), WriteStyle::Never),
+        ];
+
+        for (input, env, expected) in inputs {
+            assert_eq!(expected, env_write_style(input, env));
+        }
+    }
+
+    #[test]
+    fn env_write_style_clicolor() {
+        let inputs = vec![
+            (WriteStyle::Auto, vars(&[("CLICOLOR", "0")]), WriteStyle::Never),
+            (WriteStyle::Auto, vars(&[("CLICOLOR", "1")]), WriteStyle::Auto),
+            (WriteStyle::Auto, vars(&[("CLICOLOR_FORCE", "1")]), WriteStyle::Always),
+            (WriteStyle::Auto, vars(&[("CLICOLOR_FORCE", "0")]), WriteStyle::Auto),
+            (WriteStyle::Auto, vars(&[("CLICOLOR_FORCE", "")]), WriteStyle::Auto),
+            (WriteStyle::Auto, vars(&[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")]), WriteStyle::Always),
+            (WriteStyle::Auto, vars(&[("CLICOLOR_FORCE", "1"), ("CLICOLOR", "0")]), WriteStyle::Always),
+            (WriteStyle::Never, vars(&[("CLICOLOR_FORCE", "1")]), WriteStyle::Never),
+        ];
+
+        for (input, env, expected) in inputs {
+            assert_eq!(expected, env_write_style(input, env));
+        }
+    }
+
+    #[test]
+    fn default_level_style_never() {
+        let mut buf = Formatter::in_memory(WriteStyle::Never);
+
+        let level_style = buf.default_level_style(Level::Error);
+        write!(buf, "{}", level_style.value(Level::Error)).unwrap();
+
+        assert_eq!(b"ERROR", buf.buf.borrow().as_slice());
+    }
+
+    #[test]
+    fn terminal_width_without_terminal() {
+        assert_eq!(None, Formatter::in_memory(WriteStyle::Never).terminal_width());
+
+        let writer = Builder::new()
+            .target(Target::Null)
+            .try_build()
+            .unwrap();
+
+        assert_eq!(None, Formatter::new(&writer, Level::Info, "").terminal_width());
+    }
+
+    #[test]
+    fn level_styled() {
+        let inputs = vec![
+            (WriteStyle::Always, &b"\x1b[0m\x1b[33mWARN\x1b[0m"[..]),
+            (WriteStyle::Never, &b"WARN"[..]),
+        ];
+
+        for (write_style, expected) in inputs {
+            let mut buf = Formatter::in_memory(write_style);
+
+            let level = buf.level_styled(Level::Warn);
+            write!(buf, "{}", level).unwrap();
+
+            assert_eq!(expected, buf.buf.borrow().as_slice());
+        }
+    }
+
+    #[test]
+    fn style_set_color_named() {
+        let inputs = vec![
+            ("red", Color::Red, false),
+            ("Yellow", Color::Yellow, false),
+            ("bright-red", Color::Red, true),
+            ("BRIGHT-blue", Color::Blue, true),
+            ("208", Color::Ansi256(208), false),
+            ("0,128,128", Color::Rgb(0, 128, 128), false),
+        ];
+
+        for (name, color, intense) in inputs {
+            let buf = Formatter::in_memory(WriteStyle::Never);
+
+            let mut style = buf.style();
+            style.set_color_named(name).unwrap();
+
+            assert_eq!(Some(&color), style.spec.fg(), "{}", name);
+            assert_eq!(intense, style.spec.intense(), "{}", name);
+        }
+
+        let buf = Formatter::in_memory(WriteStyle::Never);
+
+        let mut style = buf.style();
+        style.set_color(Color::Green);
+
+        for name in vec!["", "purple", "bright-", "bright-purple", "br\u{e9}ght-red"] {
+            assert!(style.set_color_named(name).is_err(), "{}", name);
+            assert_eq!(Some(&Color::Green), style.spec.fg());
+        }
+    }
+
+    #[test]
+    fn windows_bold_spec_intensity() {
+        let inputs = vec![
+            (WindowsBold::Unchanged, true, (true, false)),
+            (WindowsBold::Intense, true, (true, true)),
+            (WindowsBold::Ignore, true, (false, false)),
+            // Text that isn't bold is never changed
+            (WindowsBold::Intense, false, (false, false)),
+            (WindowsBold::Ignore, false, (false, false)),
+        ];
+
+        for (windows_bold, bold, expected) in inputs {
+            let mut spec = ColorSpec::new();
+            spec.set_fg(Some(Color::Red)).set_bold(bold);
+
+            let spec = windows_bold_spec(&spec, windows_bold);
+
+            assert_eq!(expected, (spec.bold(), spec.intense()), "{:?}", windows_bold);
+            assert_eq!(Some(&Color::Red), spec.fg());
+        }
+    }
+
+    #[test]
+    fn style_write_bytes() {
+        let inputs = vec![
+            (WriteStyle::Always, &b"\x1b[0m\x1b[1m\x00\xff\xfe\x1b[0m"[..]),
+            (WriteStyle::Never, &b"\x00\xff\xfe"[..]),
+        ];
+
+        for (write_style, expected) in inputs {
+            let buf = Formatter::in_memory(write_style);
+
+            let mut style = buf.style();
+            style.set_bold(true);
+
+            style.write_bytes(b"\x00\xff\xfe").unwrap();
+
+            assert_eq!(expected, buf.buf.borrow().as_slice());
+        }
+    }
+
+    #[test]
+    fn default_level_style_custom_color() {
+        let mut buf = Formatter::in_memory(WriteStyle::Always);
+        buf.set_level_color(Some(Arc::new(|level| match level {
+            Level::Error => Color::Magenta,
+            _ => Color::White,
+        })));
+
+        let error_style = buf.default_level_style(Level::Error);
+        let info_style = buf.default_level_style(Level::Info);
+        write!(buf, "{} {}", error_style.value(Level::Error), info_style.value(Level::Info)).unwrap();
+
+        assert_eq!(&b"\x1b[0m\x1b[1m\x1b[35mERROR\x1b[0m \x1b[0m\x1b[37mINFO\x1b[0m"[..], buf.buf.borrow().as_slice());
+    }
+
+    #[test]
+    fn default_level_style_always() {
+        let inputs = vec![
+            (Level::Error, &b"\x1b[0m\x1b[1m\x1b[31mERROR\x1b[0m"[..]),
+            (Level::Warn, &b"\x1b[0m\x1b[33mWARN\x1b[0m"[..]),
+            (Level::Info, &b"\x1b[0m\x1b[32mINFO\x1b[0m"[..]),
+            (Level::Debug, &b"\x1b[0m\x1b[34mDEBUG\x1b[0m"[..]),
+            (Level::Trace, &b"\x1b[0m\x1b[37mTRACE\x1b[0m"[..]),
+        ];
+
+        for (level, expected) in inputs {
+            let mut buf = Formatter::in_memory(WriteStyle::Always);
+
+            let level_style = buf.default_level_style(level);
+            write!(buf, "{}", level_style.value(level)).unwrap();
+
+            assert_eq!(expected, buf.buf.borrow().as_slice());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "kv")]
+    fn default_format_kvs() {
+        use log::kv::Value;
+
+        let error = io::Error::new(io::ErrorKind::Other, "disk full");
+        let kvs = [
+            ("user", Value::from("alice")),
+            ("path", Value::from("/a b")),
+            ("error", Value::from_display(&error)),
+        ];
+
+        let written = write_record(&mut Default::default(), &Record::builder()
+            .level(Level::Info)
+            .args(format_args!("log message"))
+            .key_values(&kvs)
+            .build());
+
+        assert!(written.ends_with(": log message user=alice path=\"/a b\" error=\"disk full\"\n"));
+    }
+
+    #[test]
+    #[cfg(feature = "kv_serde")]
+    fn default_format_kvs_nested() {
+        use log::kv::Value;
+
+        let ids = vec![1, 2];
+        let kvs = [("ids", Value::from_serde(&ids)), ("user", Value::from("alice"))];
+
+        let written = write_record(&mut Default::default(), &Record::builder()
+            .level(Level::Info)
+            .args(format_args!("log message"))
+            .key_values(&kvs)
+            .build());
+
+        assert!(written.ends_with(": log message ids=[1,2] user=alice\n"));
+    }
+
+    #[test]
+    #[cfg(feature = "sync-formatter")]
+    fn formatter_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<Formatter>();
+        assert_send_sync::<Style>();
+    }
+
+    #[test]
+    fn parse_write_style_invalid() {
+        let inputs = vec![
+            "",
+            "true",
+            "false",
+            "NEVER!!"
+        ];
+
+        for input in inputs {
+            assert_eq!(WriteStyle::Auto, parse_write_style(input));
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! Printing records in the order they were logged.
+//!
+//! Records logged on different threads at nearly the same time can reach the
+//! target in a different order than they were logged in. Formatted records
+//! are held in a buffer for a short window, and the background thread prints
+//! them in the order they were logged once the window has passed.
+//!
+//! A record that's held up for longer than the window, like by a format that
+//! blocks, is still printed out of order. The window trades latency for how
+//! much disorder can be fixed.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::time::{Duration, Instant};
+
+use super::background::{Order, Pending};
+
+/// Records printed in the order they were logged, once they've been held
+/// for the window.
+pub(super) struct Reorder {
+    records: BinaryHeap<Oldest>,
+    window: Duration,
+}
+
+/// A pending record that's ordered so the oldest one is the greatest.
+struct Oldest(Pending);
+
+impl Reorder {
+    pub(super) fn new(window: Duration) -> Self {
+        Reorder {
+            records: BinaryHeap::new(),
+            window: window,
+        }
+    }
+}
+
+impl Order for Reorder {
+    fn push(&mut self, record: Pending) {
+        self.records.push(Oldest(record));
+    }
+
+    fn pop(&mut self) -> Option<Pending> {
+        self.records.pop().map(|record| record.0)
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn due(&self) -> Option<Instant> {
+        self.records.peek().map(|record| record.0.logged_at + self.window)
+    }
+}
+
+impl PartialEq for Oldest {
+    fn eq(&self, other: &Oldest) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Oldest {}
+
+impl PartialOrd for Oldest {
+    fn partial_cmp(&self, other: &Oldest) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Oldest {
+    fn cmp(&self, other: &Oldest) -> Ordering {
+        // The heap pops the greatest record first, so the oldest record is
+        // the greatest
+        (other.0.logged_at, other.0.sequence).cmp(&(self.0.logged_at, self.0.sequence))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{Builder, Target};
+    use std::io;
+    use std::thread;
+    use log::Level;
+    use termcolor::Buffer;
+    use testing::SharedBuf;
+
+    fn print(writer: &super::super::Writer, message: &str, logged_at: Instant) {
+        let mut buf = Buffer::no_color();
+        io::Write::write_all(&mut buf, message.as_bytes()).unwrap();
+
+        writer.print_at(&buf, Level::Info, logged_at).unwrap();
+    }
+
+    #[test]
+    fn prints_in_logged_order() {
+        let pipe = SharedBuf::default();
+
+        let writer = Builder::new()
+            .target(Target::Pipe(Box::new(pipe.clone())))
+            .reorder(Duration::from_secs(60), 16)
+            .try_build()
+            .unwrap();
+
+        let start = Instant::now();
+
+        print(&writer, "c\n", start + Duration::from_millis(2));
+        print(&writer, "a\n", start);
+        print(&writer, "d\n", start + Duration::from_millis(2));
+        print(&writer, "b\n", start + Duration::from_millis(1));
+
+        // Records are held for the window
+        thread::sleep(Duration::from_millis(20));
+        assert!(pipe.0.lock().unwrap().is_empty());
+
+        // Flushing prints them without waiting for the window to pass
+        writer.flush().unwrap();
+
+        assert_eq!(b"a\nb\nc\nd\n", &pipe.0.lock().unwrap()[..]);
+    }
+
+    #[test]
+    fn prints_after_window() {
+        let pipe = SharedBuf::default();
+
+        let writer = Builder::new()
+            .target(Target::Pipe(Box::new(pipe.clone())))
+            .reorder(Duration::from_millis(10), 16)
+            .try_build()
+            .unwrap();
+
+        let start = Instant::now();
+
+        print(&writer, "b\n", start);
+        print(&writer, "a\n", start - Duration::from_millis(1));
+
+        // Records are printed once the window has passed, without a flush
+        let deadline = Instant::now() + Duration::from_secs(10);
+
+        while pipe.0.lock().unwrap().len() < 4 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert_eq!(b"a\nb\n", &pipe.0.lock().unwrap()[..]);
+    }
+
+    #[test]
+    fn full_buffer_prints_oldest() {
+        let pipe = SharedBuf::default();
+
+        {
+            let writer = Builder::new()
+                .target(Target::Pipe(Box::new(pipe.clone())))
+                .reorder(Duration::from_secs(60), 2)
+                .try_build()
+                .unwrap();
+
+            let start = Instant::now();
+
+            print(&writer, "b\n", start + Duration::from_millis(1));
+            print(&writer, "a\n", start);
+            // The buffer is full, so `a` is printed to make room
+            print(&writer, "c\n", start + Duration::from_millis(2));
+
+            let deadline = Instant::now() + Duration::from_secs(10);
+
+            while pipe.0.lock().unwrap().is_empty() && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(1));
+            }
+
+            assert_eq!(b"a\n", &pipe.0.lock().unwrap()[..]);
+        }
+
+        // Dropping the writer prints the rest
+        assert_eq!(b"a\nb\nc\n", &pipe.0.lock().unwrap()[..]);
+    }
+}
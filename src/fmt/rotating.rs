@@ -0,0 +1,384 @@
+//! Printing records to files that are rotated by size or by date.
+//!
+//! Files are opened when the first record is printed to them, and appended
+//! to if they already exist. Before a record would take a size-rotated file
+//! past its maximum size, the file is renamed to `path.1`, older files are
+//! shifted along to `path.2` and so on, and a new file is started. A
+//! date-rotated file has the date in its name, like `app-2024-01-15.log`,
+//! and a new one is started by the first record printed on a new day.
+//!
+//! Rotating files is only safe within a single process. Records are printed
+//! to the file while holding the target's lock, so no records are written
+//! while the files are being renamed.
+//!
+//! A UTF-8 byte order mark can be written at the start of each file. It's
+//! only written to files that are empty when they're opened, so files that
+//! are appended to don't get a mark in the middle.
+
+use std::io::{self, Write};
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+
+use chrono::{Local, NaiveDate, Utc};
+
+const BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// A file target that's opened when the writer is built.
+pub(crate) enum FileTarget {
+    Rotating {
+        path: PathBuf,
+        max_bytes: u64,
+        max_files: usize,
+    },
+    Daily {
+        path: PathBuf,
+        local: bool,
+    },
+}
+
+impl FileTarget {
+    /// Create the writer for the file, with a byte order mark at the start of
+    /// each new file if `bom` is `true`.
+    pub(crate) fn into_writer(self, bom: bool) -> Box<Write + Send> {
+        match self {
+            FileTarget::Rotating { path, max_bytes, max_files } => Box::new(RotatingFile::new(path, max_bytes, max_files, bom)),
+            FileTarget::Daily { path, local } => Box::new(DailyFile::new(path, local, bom)),
+        }
+    }
+}
+
+/// Open `path` for appending, writing a byte order mark if `bom` is `true`
+/// and the file is empty.
+///
+/// Returns the file and its length.
+fn open_append(path: &Path, bom: bool) -> io::Result<(File, u64)> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut len = file.metadata()?.len();
+
+    if bom && len == 0 {
+        file.write_all(BOM)?;
+        len = BOM.len() as u64;
+    }
+
+    Ok((file, len))
+}
+
+/// A file that's rotated once it reaches `max_bytes`, keeping the last
+/// `max_files` rotated files.
+pub(crate) struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    bom: bool,
+    file: Option<File>,
+    // The number of bytes in the current file
+    written: u64,
+}
+
+impl RotatingFile {
+    fn new<P: AsRef<Path>>(path: P, max_bytes: u64, max_files: usize, bom: bool) -> RotatingFile {
+        RotatingFile {
+            path: path.as_ref().to_owned(),
+            max_bytes: max_bytes,
+            max_files: max_files,
+            bom: bom,
+            file: None,
+            written: 0,
+        }
+    }
+
+    /// The number of bytes in a new file before any records are written.
+    fn header_len(&self) -> u64 {
+        if self.bom {
+            BOM.len() as u64
+        }
+        else {
+            0
+        }
+    }
+
+    /// The path of the `n`th rotated file, like `app.log.1`.
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", n));
+
+        path.into()
+    }
+
+    fn open(&mut self) -> io::Result<&mut File> {
+        if self.file.is_none() {
+            let (file, written) = open_append(&self.path, self.bom)?;
+
+            self.written = written;
+            self.file = Some(file);
+        }
+
+        Ok(self.file.as_mut().expect("the file was just opened"))
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if let Some(mut file) = self.file.take() {
+            file.flush()?;
+        }
+
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        }
+        else {
+            // The oldest file is replaced by the one before it
+            for n in (1..self.max_files).rev() {
+                let from = self.rotated_path(n);
+
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(n + 1))?;
+                }
+            }
+
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        self.written = 0;
+
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.open()?;
+
+        // A record that's bigger than the maximum size still gets a file to itself
+        if self.written > self.header_len() && self.written + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+
+        let written = self.open()?.write(buf)?;
+        self.written += written as u64;
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.file {
+            Some(ref mut file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A file that's rotated when the date changes.
+pub(crate) struct DailyFile {
+    path: PathBuf,
+    local: bool,
+    bom: bool,
+    // The current file and the date it's for
+    file: Option<(NaiveDate, File)>,
+}
+
+impl DailyFile {
+    fn new<P: AsRef<Path>>(path: P, local: bool, bom: bool) -> DailyFile {
+        DailyFile {
+            path: path.as_ref().to_owned(),
+            local: local,
+            bom: bom,
+            file: None,
+        }
+    }
+
+    /// The path of the file for `date`, like `app-2024-01-15.log`.
+    fn dated_path(&self, date: NaiveDate) -> PathBuf {
+        let mut name = self.path.file_stem().unwrap_or_default().to_owned();
+        name.push(format!("-{}", date.format("%Y-%m-%d")));
+
+        if let Some(extension) = self.path.extension() {
+            name.push(".");
+            name.push(extension);
+        }
+
+        self.path.with_file_name(name)
+    }
+
+    fn today(&self) -> NaiveDate {
+        if self.local {
+            Local::now().date_naive()
+        }
+        else {
+            Utc::now().date_naive()
+        }
+    }
+
+    /// Write to the file for `date`, rotating to it if needed.
+    ///
+    /// The date is checked on every write, so a process that's idle over
+    /// midnight rotates on its first write afterwards.
+    fn write_on(&mut self, date: NaiveDate, buf: &[u8]) -> io::Result<usize> {
+        let rotate = match self.file {
+            Some((current, _)) => current != date,
+            None => true,
+        };
+
+        if rotate {
+            if let Some((_, mut file)) = self.file.take() {
+                file.flush()?;
+            }
+
+            let (file, _) = open_append(&self.dated_path(date), self.bom)?;
+
+            self.file = Some((date, file));
+        }
+
+        match self.file {
+            Some((_, ref mut file)) => file.write(buf),
+            None => unreachable!("the file was just opened"),
+        }
+    }
+}
+
+impl Write for DailyFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let today = self.today();
+
+        self.write_on(today, buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.file {
+            Some((_, ref mut file)) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{env, process};
+    use std::io::Read;
+
+    fn read(path: &Path) -> Option<String> {
+        let mut contents = String::new();
+
+        File::open(path).ok()?.read_to_string(&mut contents).ok()?;
+
+        Some(contents)
+    }
+
+    #[test]
+    fn rotate_by_size() {
+        let inputs = vec![
+            (9, 0, None, vec![Some("record 3\n"), None, None]),
+            (9, 1, None, vec![Some("record 3\n"), Some("record 2\n"), None]),
+            (9, 2, None, vec![Some("record 3\n"), Some("record 2\n"), Some("record 1\n")]),
+            (18, 2, None, vec![Some("record 3\n"), Some("record 1\nrecord 2\n"), None]),
+            // Records are appended to an existing file until it's full
+            (18, 2, Some("existing\n"), vec![Some("record 2\nrecord 3\n"), Some("existing\nrecord 1\n"), None]),
+        ];
+
+        for (i, (max_bytes, max_files, existing, expected)) in inputs.into_iter().enumerate() {
+            let dir = env::temp_dir().join(format!("env_logger_rotating_{}_{}", process::id(), i));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+
+            let path = dir.join("app.log");
+
+            if let Some(existing) = existing {
+                fs::write(&path, existing).unwrap();
+            }
+
+            let mut file = RotatingFile::new(&path, max_bytes, max_files, false);
+
+            for n in 1..4 {
+                writeln!(file, "record {}", n).unwrap();
+            }
+            file.flush().unwrap();
+
+            let actual = [read(&path), read(&file.rotated_path(1)), read(&file.rotated_path(2))];
+            let actual: Vec<_> = actual.iter().map(|contents| contents.as_ref().map(|contents| &**contents)).collect();
+
+            assert_eq!(expected, actual);
+
+            fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn daily_file_path() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+
+        let inputs = vec![
+            ("app.log", "app-2024-01-15.log"),
+            ("logs/app.log", "logs/app-2024-01-15.log"),
+            ("app", "app-2024-01-15"),
+            ("app.tar.gz", "app.tar-2024-01-15.gz"),
+        ];
+
+        for (path, expected) in inputs {
+            assert_eq!(Path::new(expected), DailyFile::new(path, false, false).dated_path(date));
+        }
+    }
+
+    #[test]
+    fn rotate_by_date() {
+        let dir = env::temp_dir().join(format!("env_logger_daily_{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let first = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let second = NaiveDate::from_ymd_opt(2024, 1, 16).unwrap();
+
+        let mut file = DailyFile::new(dir.join("app.log"), false, false);
+
+        file.write_on(first, b"record 1\n").unwrap();
+        file.write_on(first, b"record 2\n").unwrap();
+        file.write_on(second, b"record 3\n").unwrap();
+        file.flush().unwrap();
+
+        assert_eq!(Some("record 1\nrecord 2\n".to_owned()), read(&dir.join("app-2024-01-15.log")));
+        assert_eq!(Some("record 3\n".to_owned()), read(&dir.join("app-2024-01-16.log")));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn bom_only_in_new_files() {
+        let dir = env::temp_dir().join(format!("env_logger_bom_{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let first = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let second = NaiveDate::from_ymd_opt(2024, 1, 16).unwrap();
+
+        // Each rotated file starts with a mark, even when a record fills it
+        let path = dir.join("rotating.log");
+        let mut file = RotatingFile::new(&path, 12, 1, true);
+
+        for n in 1..3 {
+            writeln!(file, "record {}", n).unwrap();
+        }
+        file.flush().unwrap();
+
+        assert_eq!(Some("\u{feff}record 2\n".to_owned()), read(&path));
+        assert_eq!(Some("\u{feff}record 1\n".to_owned()), read(&file.rotated_path(1)));
+
+        // Files that are appended to don't get another mark
+        let mut file = RotatingFile::new(&path, 100, 1, true);
+        writeln!(file, "record 3").unwrap();
+        file.flush().unwrap();
+
+        assert_eq!(Some("\u{feff}record 2\nrecord 3\n".to_owned()), read(&path));
+
+        for _ in 0..2 {
+            let mut file = DailyFile::new(dir.join("daily.log"), false, true);
+
+            file.write_on(first, b"record\n").unwrap();
+            file.write_on(second, b"record\n").unwrap();
+            file.flush().unwrap();
+        }
+
+        assert_eq!(Some("\u{feff}record\nrecord\n".to_owned()), read(&dir.join("daily-2024-01-15.log")));
+        assert_eq!(Some("\u{feff}record\nrecord\n".to_owned()), read(&dir.join("daily-2024-01-16.log")));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
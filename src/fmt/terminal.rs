@@ -0,0 +1,128 @@
+//! Querying the terminal records are printed to.
+//!
+//! The width is asked for each time it's needed, so it follows the terminal
+//! when it's resized. Streams that aren't terminals don't have a width.
+
+/// A standard stream that might be a terminal.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// The file descriptor of `stream`.
+#[cfg(unix)]
+fn fd(stream: Stream) -> ::libc::c_int {
+    use libc;
+
+    match stream {
+        Stream::Stdout => libc::STDOUT_FILENO,
+        Stream::Stderr => libc::STDERR_FILENO,
+    }
+}
+
+/// Whether or not `stream` is connected to a terminal.
+#[cfg(unix)]
+pub(super) fn is_terminal(stream: Stream) -> bool {
+    use libc;
+
+    unsafe { libc::isatty(fd(stream)) == 1 }
+}
+
+/// Whether or not `stream` is connected to a console.
+#[cfg(windows)]
+pub(super) fn is_terminal(stream: Stream) -> bool {
+    use std::os::raw::c_void;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetStdHandle(std_handle: u32) -> *mut c_void;
+        fn GetConsoleMode(console_handle: *mut c_void, mode: *mut u32) -> i32;
+    }
+
+    let std_handle = match stream {
+        Stream::Stdout => -11i32 as u32,
+        Stream::Stderr => -12i32 as u32,
+    };
+
+    let mut mode = 0;
+
+    // The call fails if the stream isn't a console
+    unsafe { GetConsoleMode(GetStdHandle(std_handle), &mut mode) != 0 }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(super) fn is_terminal(_: Stream) -> bool {
+    false
+}
+
+/// The number of columns in the terminal `stream` is connected to.
+#[cfg(unix)]
+pub(super) fn width(stream: Stream) -> Option<usize> {
+    use std::mem;
+    use libc;
+
+    let mut size: libc::winsize = unsafe { mem::zeroed() };
+
+    // The request fails if the stream isn't a terminal
+    if unsafe { libc::ioctl(fd(stream), libc::TIOCGWINSZ, &mut size) } == -1 || size.ws_col == 0 {
+        return None;
+    }
+
+    Some(size.ws_col as usize)
+}
+
+/// The number of columns in the console `stream` is connected to.
+#[cfg(windows)]
+pub(super) fn width(stream: Stream) -> Option<usize> {
+    use std::mem;
+    use std::os::raw::c_void;
+
+    #[repr(C)]
+    struct Coord {
+        x: i16,
+        y: i16,
+    }
+
+    #[repr(C)]
+    struct SmallRect {
+        left: i16,
+        top: i16,
+        right: i16,
+        bottom: i16,
+    }
+
+    #[repr(C)]
+    struct ConsoleScreenBufferInfo {
+        size: Coord,
+        cursor_position: Coord,
+        attributes: u16,
+        window: SmallRect,
+        maximum_window_size: Coord,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetStdHandle(std_handle: u32) -> *mut c_void;
+        fn GetConsoleScreenBufferInfo(console_output: *mut c_void, info: *mut ConsoleScreenBufferInfo) -> i32;
+    }
+
+    let std_handle = match stream {
+        Stream::Stdout => -11i32 as u32,
+        Stream::Stderr => -12i32 as u32,
+    };
+
+    let mut info: ConsoleScreenBufferInfo = unsafe { mem::zeroed() };
+
+    // The call fails if the stream isn't a console
+    if unsafe { GetConsoleScreenBufferInfo(GetStdHandle(std_handle), &mut info) } == 0 {
+        return None;
+    }
+
+    Some((info.window.right - info.window.left + 1) as usize)
+}
+
+#[cfg(not(any(unix, windows)))]
+pub(super) fn width(_: Stream) -> Option<usize> {
+    None
+}
@@ -93,7 +93,8 @@
 //!
 //! The actual `level` is optional to specify. If omitted, all logging will
 //! be enabled. If specified, it must be one of the strings `debug`, `error`,
-//! `info`, `warn`, or `trace`.
+//! `info`, `warn`, `trace` or `off`. Levels are case-insensitive, so `DEBUG`
+//! and `Debug` are the same as `debug`.
 //!
 //! As the log level for a module is optional, the module to enable logging for
 //! is also optional. If only a `level` is provided, then the global log
@@ -107,6 +108,26 @@
 //! * `hello,std::option` turns on hello, and std's option logging
 //! * `error,hello=warn` turn on global error logging and also warn for hello
 //!
+//! ## Matching targets with a pattern
+//!
+//! If the module path in a directive starts with `~`, the rest of it is a
+//! regex that's matched anywhere in a record's target instead of a prefix of
+//! it. Without the `regex` feature, the pattern is matched as a plain
+//! substring, and a warning is printed if it has regex metacharacters like
+//! `.` or `*`. Patterns can't contain `,`, `=` or `/`.
+//!
+//! When a pattern matches a target, it takes precedence over any module path
+//! directives. If more than one pattern matches, the last one wins.
+//!
+//! * `~::db=debug` turns on debug logging for every target containing `::db`.
+//!   That includes `app::db` and `app::db::pool`, but also `app::dbx` and
+//!   `app::database`, and not a top-level `db` target.
+//! * `info,~::db::=trace,~::db::pool=off` turns on global info logging and
+//!   trace logging below any `db` module that isn't at the top level, but
+//!   disables logging for `db::pool`
+//! * `~(^|::)db(::|$)=debug` turns on debug logging for every module with a
+//!   `db` segment, including a top-level `db`. This needs the `regex` feature.
+//!
 //! ## Filtering results
 //!
 //! A `RUST_LOG` directive may include a regex filter. The syntax is to append `/`
@@ -133,16 +154,36 @@
 //! environment variable. It accepts the following values:
 //! 
 //! * `auto` (default) will attempt to print style characters, but don't force the issue.
-//! If the console isn't available on Windows, or if TERM=dumb, for example, then don't print colors.
+//! If the stream records are printed to isn't a terminal, the console isn't available on Windows,
+//! or if TERM=dumb, for example, then don't print colors. Standard output and standard error are
+//! checked separately, so redirecting one of them to a file doesn't turn colors off for the other.
 //! * `always` will always print style characters even if they aren't supported by the terminal.
 //! This includes emitting ANSI colors on Windows if the console API is unavailable.
 //! * `never` will never print style characters.
 //! 
+//! When the style is `auto`, the following conventional environment variables
+//! are also respected, in order of precedence:
+//! 
+//! 1. If [`CLICOLOR_FORCE`] is set to a non-zero value then style characters
+//! are always printed.
+//! 2. If [`NO_COLOR`] is set to a non-empty value then style characters are
+//! never printed.
+//! 3. If `CLICOLOR` is set to `0` then style characters are never printed.
+//! 
+//! An explicit `always` or `never` takes precedence over all of these.
+//! Standard output and standard error can be given their own style with
+//! [`Builder::stdout_write_style`] and [`Builder::stderr_write_style`], and
+//! the variables are respected for each stream whose style is `auto`.
+//! 
+//! [`CLICOLOR_FORCE`]: https://bixense.com/clicolors/
+//! [`NO_COLOR`]: https://no-color.org/
+//! [`Builder::stdout_write_style`]: struct.Builder.html#method.stdout_write_style
+//! [`Builder::stderr_write_style`]: struct.Builder.html#method.stderr_write_style
 //! [log-crate-url]: https://docs.rs/log/
 
 #![doc(html_logo_url = "http://www.rust-lang.org/logos/rust-logo-128x128-blk-v2.png",
        html_favicon_url = "http://www.rust-lang.org/favicon.ico",
-       html_root_url = "https://docs.rs/env_logger/0.5.0")]
+       html_root_url = "https://docs.rs/env_logger/0.6.0")]
 #![cfg_attr(test, deny(warnings))]
 
 // When compiled for the rustc compiler itself we want to make sure that this is
@@ -155,18 +196,33 @@
 extern crate log;
 extern crate termcolor;
 extern crate chrono;
+#[cfg(feature = "kv_serde")]
+extern crate serde_json;
+#[cfg(any(unix, windows))]
+extern crate libc;
 
 use std::env;
 use std::borrow::Cow;
+use std::error::Error;
 use std::io::prelude::*;
 use std::io;
-use std::mem;
 use std::cell::RefCell;
+use std::time::{Duration, Instant, SystemTime};
+use std::path::Path;
+use std::process;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use log::{Log, LevelFilter, Level, Record, SetLoggerError, Metadata};
+use log::{Log, Level, LevelFilter, Record, SetLoggerError, Metadata};
 
 pub mod filter;
 pub mod fmt;
+#[cfg(feature = "test-util")]
+pub mod capture;
+
+mod dedup;
+#[cfg(test)]
+mod testing;
 
 pub use self::fmt::{Target, WriteStyle, Color, Formatter};
 
@@ -200,16 +256,114 @@ pub struct Env<'a> {
 /// the associated [`Builder`] and install it with the
 /// [`log` crate][log-crate-url] directly.
 ///
+/// A `Logger` built with [`Builder::build`] doesn't have to be installed at
+/// all. It can be embedded in another `Log` implementation, which uses
+/// [`matches`] to filter records and [`format_to_string`] to format them
+/// on demand. Use [`Builder::write`] to write formatted records to any
+/// writer instead.
+///
+/// # Examples
+///
+/// Embed a `Logger` to filter and format records for another logger:
+///
+/// ```
+/// # extern crate log;
+/// # extern crate env_logger;
+/// use log::{Log, Metadata, Record};
+/// use env_logger::{Builder, Logger};
+///
+/// struct MyLogger {
+///     inner: Logger,
+/// }
+///
+/// impl Log for MyLogger {
+///     fn enabled(&self, metadata: &Metadata) -> bool {
+///         self.inner.enabled(metadata)
+///     }
+///
+///     fn log(&self, record: &Record) {
+///         if self.inner.matches(record) {
+///             if let Ok(formatted) = self.inner.format_to_string(record) {
+///                 print!("{}", formatted);
+///             }
+///         }
+///     }
+///
+///     fn flush(&self) {}
+/// }
+///
+/// # fn main() {
+/// let logger = MyLogger {
+///     inner: Builder::new().parse("info").build(),
+/// };
+/// # drop(logger);
+/// # }
+/// ```
+///
 /// [log-crate-url]: https://docs.rs/log/
 /// [`init()`]: fn.init.html
 /// [`try_init()`]: fn.try_init.html
 /// [`Builder::init()`]: struct.Builder.html#method.init
 /// [`Builder::try_init()`]: struct.Builder.html#method.try_init
 /// [`Builder`]: struct.Builder.html
+/// [`Builder::build`]: struct.Builder.html#method.build
+/// [`Builder::write`]: struct.Builder.html#method.write
+/// [`matches`]: #method.matches
+/// [`format_to_string`]: #method.format_to_string
 pub struct Logger {
     writer: fmt::Writer,
+    filter: Arc<RwLock<filter::Filter>>,
+    format: Box<fmt::FormatFn>,
+    level_color: Option<Arc<fmt::LevelColorFn>>,
+    clock: Option<Arc<fmt::ClockFn>>,
+    filter_fn: Option<Arc<FilterFn>>,
+    dedup: Option<dedup::Dedup>,
+    error_policy: fmt::ErrorPolicy,
+    // Whether or not the logger has been installed as the global logger
+    installed: Arc<AtomicBool>,
+    static_max_level: bool,
+    filter_error: Option<Arc<FilterErrorFn>>,
+}
+
+/// A handle that replaces the filter of a [`Logger`] while it's running.
+///
+/// Use [`Builder::try_init_with_handle`] or [`Logger::filter_handle`] to get
+/// a `FilterHandle`. This lets a long-running program change its log
+/// verbosity without restarting, such as when it receives a signal.
+///
+/// If the logger has been installed as the global logger by one of the
+/// `init` methods, replacing the filter also sets the `log` crate's maximum
+/// level. Otherwise the maximum level is left alone, so a logger that's
+/// embedded in another one doesn't change the level of the installed logger.
+///
+/// [`Logger`]: struct.Logger.html
+/// [`Builder::try_init_with_handle`]: struct.Builder.html#method.try_init_with_handle
+/// [`Logger::filter_handle`]: struct.Logger.html#method.filter_handle
+#[derive(Clone)]
+pub struct FilterHandle {
+    filter: Arc<RwLock<filter::Filter>>,
+    installed: Arc<AtomicBool>,
+    static_max_level: bool,
+    filter_error: Option<Arc<FilterErrorFn>>,
+}
+
+/// A logger that writes formatted records to an arbitrary writer.
+///
+/// It uses the same filtering and formatting as the [`Logger`], but records
+/// are only written when they're passed to [`log_to`] rather than through the
+/// global `log` facade.
+///
+/// Use [`Builder::write`] to construct a `WriteLogger`.
+///
+/// [`Logger`]: struct.Logger.html
+/// [`log_to`]: #method.log_to
+/// [`Builder::write`]: struct.Builder.html#method.write
+pub struct WriteLogger<'a> {
+    out: &'a mut Write,
+    formatter: Formatter,
     filter: filter::Filter,
-    format: Box<Fn(&mut Formatter, &Record) -> io::Result<()> + Sync + Send>,
+    filter_fn: Option<Arc<FilterFn>>,
+    format: Box<fmt::FormatFn>,
 }
 
 /// `Builder` acts as builder for initializing a `Logger`.
@@ -248,35 +402,37 @@ pub struct Logger {
 pub struct Builder {
     filter: filter::Builder,
     writer: fmt::Builder,
-    format: Box<Fn(&mut Formatter, &Record) -> io::Result<()> + Sync + Send>,
+    format: fmt::Format,
+    deduplicate: bool,
+    error_policy: fmt::ErrorPolicy,
+    filter_error: Option<Arc<FilterErrorFn>>,
+    filter_fn: Option<Arc<FilterFn>>,
+    level_color: Option<Arc<fmt::LevelColorFn>>,
+    clock: Option<Arc<fmt::ClockFn>>,
+    on_init: Option<Box<FnOnce(&Logger) + Sync + Send>>,
 }
 
+/// A function that decides whether or not records with some metadata are logged.
+type FilterFn = Fn(&Metadata) -> bool + Sync + Send;
+
+/// A function that's called for each part of the filter directives that
+/// couldn't be parsed.
+type FilterErrorFn = Fn(&filter::ParseError) + Sync + Send;
+
 impl Builder {
     /// Initializes the log builder with defaults.
     pub fn new() -> Builder {
         Builder {
             filter: Default::default(),
             writer: Default::default(),
-            format: Box::new(|buf, record| {
-                let ts = buf.timestamp();
-                let level = record.level();
-                let mut level_style = buf.style();
-
-                match level {
-                    Level::Trace => level_style.set_color(Color::White),
-                    Level::Debug => level_style.set_color(Color::Blue),
-                    Level::Info => level_style.set_color(Color::Green),
-                    Level::Warn => level_style.set_color(Color::Yellow),
-                    Level::Error => level_style.set_color(Color::Red).set_bold(true),
-                };
-
-                if let Some(module_path) = record.module_path() {
-                    writeln!(buf, "{:>5} {}: {}: {}", level_style.value(level), ts, module_path, record.args())
-                }
-                else {
-                    writeln!(buf, "{:>5} {}: {}", level_style.value(level), ts, record.args())
-                }
-            }),
+            format: Default::default(),
+            deduplicate: false,
+            error_policy: Default::default(),
+            filter_error: None,
+            filter_fn: None,
+            level_color: None,
+            clock: None,
+            on_init: None,
         }
     }
 
@@ -312,17 +468,53 @@ impl Builder {
         E: Into<Env<'a>>
     {
         let mut builder = Builder::new();
+        builder.parse_env(env);
+        builder
+    }
+
+    /// Applies the configuration from the environment to the builder.
+    /// 
+    /// This is the same as [`from_env`], but works on an existing builder, so
+    /// the variables to read from can be chosen after the builder has been
+    /// configured. That's useful when several tools built on `env_logger`
+    /// share a shell and each needs its own variables instead of `RUST_LOG`
+    /// and `RUST_LOG_STYLE`. Variables that aren't set are ignored.
+    /// 
+    /// # Examples
+    /// 
+    /// Read the filter from `MYAPP_LOG` and whether or not to write styles
+    /// from `MYAPP_LOG_STYLE`:
+    /// 
+    /// ```
+    /// # extern crate log;
+    /// # extern crate env_logger;
+    /// use log::LevelFilter;
+    /// use env_logger::{Builder, Env};
+    /// 
+    /// # fn main() {
+    /// let mut builder = Builder::new();
+    /// 
+    /// builder.filter(None, LevelFilter::Info)
+    ///        .parse_env(Env::new().filter("MYAPP_LOG").write_style("MYAPP_LOG_STYLE"));
+    /// # }
+    /// ```
+    /// 
+    /// [`from_env`]: #method.from_env
+    pub fn parse_env<'a, E>(&mut self, env: E) -> &mut Self
+    where
+        E: Into<Env<'a>>
+    {
         let env = env.into();
 
         if let Some(s) = env.get_filter() {
-            builder.parse(&s);
+            self.parse(&s);
         }
 
         if let Some(s) = env.get_write_style() {
-            builder.parse_write_style(&s);
+            self.parse_write_style(&s);
         }
 
-        builder
+        self
     }
 
     /// Adds filters to the logger.
@@ -336,6 +528,39 @@ impl Builder {
         self
     }
 
+    /// Sets a function that further filters records after the directives.
+    ///
+    /// Records are only logged if they're enabled by the filter directives
+    /// and `filter_fn` returns `true` for their metadata. This can express
+    /// things directives can't, like only logging targets with a certain
+    /// suffix. Setting a new function replaces the previous one, and it's
+    /// kept when the directives are reloaded with a [`FilterHandle`].
+    ///
+    /// The function is called for every record that passes the directives,
+    /// and when other libraries check whether logging is enabled, so it
+    /// should be cheap.
+    ///
+    /// # Examples
+    ///
+    /// Only log records from targets that end in `auth`:
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.filter_fn(|metadata| metadata.target().ends_with("auth"));
+    /// ```
+    ///
+    /// [`FilterHandle`]: struct.FilterHandle.html
+    pub fn filter_fn<F: 'static>(&mut self, filter_fn: F) -> &mut Self
+    where
+        F: Fn(&Metadata) -> bool + Sync + Send,
+    {
+        self.filter_fn = Some(Arc::new(filter_fn));
+        self
+    }
+
     /// Sets the format function for formatting the log output.
     ///
     /// This function is called on each record logged and should format the
@@ -352,149 +577,2511 @@ impl Builder {
     pub fn format<F: 'static>(&mut self, format: F) -> &mut Self
         where F: Fn(&mut Formatter, &Record) -> io::Result<()> + Sync + Send
     {
-        self.format = Box::new(format);
+        self.format.custom_format = Some(Box::new(format));
         self
     }
 
-    /// Sets the target for the log output.
+    /// Sets the format to write each record as a single-line JSON object.
     ///
-    /// Env logger can log to either stdout or stderr. The default is stderr.
-    pub fn target(&mut self, target: fmt::Target) -> &mut Self {
-        self.writer.target(target);
+    /// This replaces any custom [`format`] function. The JSON format is
+    /// useful for ingesting records into log aggregators. Each object has the
+    /// following keys:
+    ///
+    /// - `timestamp`: the RFC3339 timestamp the record was written at, unless
+    ///   timestamps are turned off with [`format_timestamp`].
+    /// - `level`: the level of the record, like `"INFO"`.
+    /// - `target`: the target of the record.
+    /// - `module_path`: the module path of the record, if it has one.
+    /// - `line`: the line number of the record as a number, if it has one.
+    /// - `message`: the formatted message of the record.
+    ///
+    /// With the `kv` feature, the structured key-value pairs of the record are
+    /// added as string values after these keys. With the `kv_serde` feature,
+    /// they're added as JSON values instead, so numbers, maps and sequences
+    /// keep their structure. Values that can't be serialized are still added
+    /// as strings. Any [`metadata`] is added as string values after that.
+    /// Neither should reuse any of the names above. Styles are never written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format_json();
+    /// ```
+    ///
+    /// A record written by the JSON format looks like this:
+    ///
+    /// ```text
+    /// {"timestamp":"2018-02-13T23:08:32Z","level":"INFO","target":"app","module_path":"app","line":12,"message":"starting up"}
+    /// ```
+    ///
+    /// [`format`]: #method.format
+    /// [`metadata`]: #method.metadata
+    /// [`format_timestamp`]: #method.format_timestamp
+    pub fn format_json(&mut self) -> &mut Self {
+        self.format.custom_format = None;
+        self.format.layout = fmt::Layout::Json;
+        self.format.json_fields = false;
         self
     }
 
-    /// Sets whether or not styles will be written.
-    /// 
-    /// This can be useful in environments that don't support control characters
-    /// for setting colors.
-    pub fn write_style(&mut self, write_style: fmt::WriteStyle) -> &mut Self {
-        self.writer.write_style(write_style);
+    /// Sets the format to write each record as a line of JSON, with its
+    /// key-value pairs nested under a `fields` key.
+    ///
+    /// This is the same as [`format_json`], except the structured key-value
+    /// pairs of the record are written as a `fields` object after the
+    /// `message`, instead of alongside the other keys. Many log shippers
+    /// expect newline-delimited JSON in this shape, and key-value pairs can't
+    /// clash with the keys of the record itself.
+    ///
+    /// Booleans and numbers are written as JSON booleans and numbers. With the
+    /// `kv_serde` feature, any value that can be serialized keeps its
+    /// structure. Other values are written as strings. Without the `kv`
+    /// feature, `fields` is always empty. Any [`metadata`] is still added
+    /// after `fields`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format_ndjson();
+    /// ```
+    ///
+    /// A record written by this format looks like this:
+    ///
+    /// ```text
+    /// {"timestamp":"2018-02-13T23:08:32Z","level":"INFO","target":"app","module_path":"app","line":12,"message":"request done","fields":{"status":200,"cached":false}}
+    /// ```
+    ///
+    /// [`format_json`]: #method.format_json
+    /// [`metadata`]: #method.metadata
+    pub fn format_ndjson(&mut self) -> &mut Self {
+        self.format_json();
+        self.format.json_fields = true;
         self
     }
 
-    /// Parses the directives string in the same form as the `RUST_LOG`
-    /// environment variable.
+    /// Sets the format to write each record as a line of [`logfmt`] pairs.
     ///
-    /// See the module documentation for more details.
-    pub fn parse(&mut self, filters: &str) -> &mut Self {
-        self.filter.parse(filters);
+    /// This replaces any custom [`format`] function. Each line has the
+    /// following keys:
+    ///
+    /// - `ts`: the RFC3339 timestamp the record was written at, unless
+    ///   timestamps are turned off with [`format_timestamp`].
+    /// - `level`: the level of the record in lowercase, like `info`.
+    /// - `target`: the target of the record.
+    /// - `msg`: the formatted message of the record. It's always the last key.
+    ///
+    /// With the `kv` feature, the structured key-value pairs of the record are
+    /// written after the `target`, followed by any [`metadata`]. With the
+    /// `kv_serde` feature, maps and sequences are written as compact JSON.
+    /// Keys and values that are empty or contain whitespace, `=` or `"` are
+    /// quoted and escaped. The message is always quoted. Styles are never
+    /// written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format_logfmt();
+    /// ```
+    ///
+    /// A record written by the `logfmt` format looks like this:
+    ///
+    /// ```text
+    /// ts=2018-02-13T23:08:32Z level=info target=app msg="starting up"
+    /// ```
+    ///
+    /// [`logfmt`]: https://brandur.org/logfmt
+    /// [`format`]: #method.format
+    /// [`metadata`]: #method.metadata
+    /// [`format_timestamp`]: #method.format_timestamp
+    pub fn format_logfmt(&mut self) -> &mut Self {
+        self.format.custom_format = None;
+        self.format.layout = fmt::Layout::Logfmt;
         self
     }
 
-    /// Parses whether or not to write styles in the same form as the `RUST_LOG_STYLE`
-    /// environment variable.
-    /// 
-    /// See the module documentation for more details.
-    pub fn parse_write_style(&mut self, write_style: &str) -> &mut Self {
-        self.writer.parse(write_style);
+    /// Sets how levels are padded in the default format.
+    ///
+    /// Levels are padded to 5 characters so messages line up in a column.
+    /// By default, levels are padded on the left so they're aligned to the
+    /// right. Styles don't count towards the padded width.
+    ///
+    /// # Examples
+    ///
+    /// Align levels to the left:
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    /// use env_logger::fmt::LevelPadding;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format_level_padding(LevelPadding::Right);
+    /// ```
+    pub fn format_level_padding(&mut self, padding: fmt::LevelPadding) -> &mut Self {
+        self.format.level_padding = padding;
         self
     }
 
-    /// Initializes the global logger with the built env logger.
+    /// Whether or not to write levels as a single letter in the default format.
     ///
-    /// This should be called early in the execution of a Rust program. Any log
-    /// events that occur before initialization will be ignored.
+    /// When enabled, levels are written as `E`, `W`, `I`, `D` and `T` instead
+    /// of their full names, and are still colored. Compact levels are always
+    /// one character wide, so they aren't padded. This is disabled by default.
     ///
-    /// # Errors
+    /// # Examples
     ///
-    /// This function will fail if it is called more than once, or if another
-    /// library has already initialized a global logger.
-    pub fn try_init(&mut self) -> Result<(), SetLoggerError> {
-        let logger = self.build();
-
-        log::set_max_level(logger.filter());
-        log::set_boxed_logger(Box::new(logger))
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format_level_compact(true);
+    /// ```
+    pub fn format_level_compact(&mut self, compact: bool) -> &mut Self {
+        self.format.level_compact = compact;
+        self
     }
 
-    /// Initializes the global logger with the built env logger.
+    /// Whether or not to write levels in lowercase in the default format.
     ///
-    /// This should be called early in the execution of a Rust program. Any log
-    /// events that occur before initialization will be ignored.
+    /// When enabled, levels are written as `error`, `warn`, `info`, `debug`
+    /// and `trace` instead of in uppercase. Lowercase levels are still
+    /// colored and padded, and compact levels are written as lowercase
+    /// letters. This is disabled by default.
     ///
-    /// # Panics
+    /// # Examples
     ///
-    /// This function will panic if it is called more than once, or if another
-    /// library has already initialized a global logger.
-    pub fn init(&mut self) {
-        self.try_init().unwrap();
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format_level_lowercase(true);
+    /// ```
+    pub fn format_level_lowercase(&mut self, lowercase: bool) -> &mut Self {
+        self.format.level_lowercase = lowercase;
+        self
     }
 
-    /// Build an env logger.
-    /// 
-    /// This method is kept private because the only way we support building
-    /// loggers is by installing them as the single global logger for the
-    /// `log` crate.
-    pub fn build(&mut self) -> Logger {
-        Logger {
-            writer: self.writer.build(),
-            filter: self.filter.build(),
-            format: mem::replace(&mut self.format, Box::new(|_, _| Ok(()))),
-        }
+    /// Whether or not to color whole records by their level in the default
+    /// format.
+    ///
+    /// When enabled, everything written for a record, from the
+    /// [`line_prefix`] to the [`line_suffix`], is written in the
+    /// color of its level instead of only the level itself. The colors can
+    /// be changed with [`format_level_color`]. Nothing is colored when styles
+    /// are disabled, like with `WriteStyle::Never`. This is disabled by
+    /// default.
+    ///
+    /// A prefix or suffix that writes its own styles ends the record's color
+    /// where its styles are reset.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.colorize_full_line(true);
+    /// ```
+    ///
+    /// [`line_prefix`]: #method.line_prefix
+    /// [`line_suffix`]: #method.line_suffix
+    /// [`format_level_color`]: #method.format_level_color
+    pub fn colorize_full_line(&mut self, full_line: bool) -> &mut Self {
+        self.format.full_line_color = full_line;
+        self
     }
-}
 
-impl Logger {
-    /// Returns the maximum `LevelFilter` that this env logger instance is
-    /// configured to output.
-    pub fn filter(&self) -> LevelFilter {
-        self.filter.filter()
+    /// Sets the style of the message for records at a level in the default
+    /// format.
+    ///
+    /// Only the message itself is styled, so errors can stand out in bold
+    /// while trace messages fade into the background. Setting a style for a
+    /// level again replaces it. Messages at levels without a style are
+    /// written as usual. With [`colorize_full_line`], the message is written
+    /// in its own style and the rest of the line keeps the line's color.
+    /// Nothing is styled when styles are disabled.
+    ///
+    /// # Examples
+    ///
+    /// Write error messages in bold white, and dim trace messages:
+    ///
+    /// ```
+    /// # extern crate log;
+    /// # extern crate env_logger;
+    /// use log::Level;
+    /// use env_logger::Builder;
+    /// use env_logger::fmt::{Color, StyleSpec};
+    ///
+    /// # fn main() {
+    /// let mut error = StyleSpec::new();
+    /// error.set_color(Color::White).set_bold(true);
+    ///
+    /// let mut trace = StyleSpec::new();
+    /// trace.set_dimmed(true);
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder
+    ///     .format_message_style(Level::Error, error)
+    ///     .format_message_style(Level::Trace, trace);
+    /// # }
+    /// ```
+    ///
+    /// [`colorize_full_line`]: #method.colorize_full_line
+    pub fn format_message_style(&mut self, level: Level, style: fmt::StyleSpec) -> &mut Self {
+        self.format.message_styles.retain(|&(styled, _)| styled != level);
+        self.format.message_styles.push((level, style));
+        self
     }
 
-    /// Checks if this record matches the configured filter.
-    pub fn matches(&self, record: &Record) -> bool {
-        self.filter.matches(record)
+    /// Whether or not to escape control characters in messages written by
+    /// the default format.
+    ///
+    /// Messages often include untrusted input, which could contain escape
+    /// sequences that restyle or clear the terminal, or carriage returns that
+    /// hide earlier output. When enabled, control characters in a record's
+    /// message are escaped, so `\x1b` is written as `\u{1b}`. Newlines are
+    /// kept. Styles written by the format itself, like the level's color,
+    /// aren't escaped.
+    ///
+    /// Custom formats write messages themselves, so they aren't affected.
+    ///
+    /// # Examples
+    ///
+    /// Escape control characters in messages:
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.sanitize_messages(true);
+    /// ```
+    pub fn sanitize_messages(&mut self, sanitize: bool) -> &mut Self {
+        self.format.sanitize_messages = sanitize;
+        self
     }
-}
 
-impl Log for Logger {
-    fn enabled(&self, metadata: &Metadata) -> bool {
-        self.filter.enabled(metadata)
+    /// Whether or not to write the module path of records in the default format.
+    ///
+    /// When enabled, the module path is written after the timestamp and
+    /// before the message. Records without a module path skip it. This is
+    /// enabled by default.
+    ///
+    /// # Examples
+    ///
+    /// Leave the module path out of records:
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format_module_path(false);
+    /// ```
+    pub fn format_module_path(&mut self, module_path: bool) -> &mut Self {
+        self.format.module_path = module_path;
+        self
     }
 
-    fn log(&self, record: &Record) {
-        if self.matches(record) {
-            // Log records are written to a thread-local buffer before being printed
-            // to the terminal. We clear these buffers afterwards, but they aren't shrinked
-            // so will always at least have capacity for the largest log record formatted
-            // on that thread.
-            // 
-            // If multiple `Logger`s are used by the same threads then the thread-local
-            // formatter might have different color support. If this is the case the
-            // formatter and its buffer are discarded and recreated.
-
-            thread_local! {
-                static FORMATTER: RefCell<Option<Formatter>> = RefCell::new(None);
-            }
-
-            FORMATTER.with(|tl_buf| {
-                let mut tl_buf = tl_buf.borrow_mut();
-
-                // Check the buffer style. If it's different from the logger's 
-                // style then drop the buffer and recreate it.
-                match *tl_buf {
-                    Some(ref mut formatter) => {
-                        if formatter.write_style() != self.writer.write_style() {
-                            *formatter = Formatter::new(&self.writer)
-                        }
-                    },
-                    ref mut tl_buf => *tl_buf = Some(Formatter::new(&self.writer))
-                }
-
-                // The format is guaranteed to be `Some` by this point
-                let mut formatter = tl_buf.as_mut().unwrap();
-
-                let _ = (self.format)(&mut formatter, record).and_then(|_| formatter.print(&self.writer));
-
-                // Always clear the buffer afterwards
-                formatter.clear();
-            });
-        }
+    /// Whether or not to write a sequence number before each record in the
+    /// default format.
+    ///
+    /// The sequence number is a counter that's shared by every logger in
+    /// the process and incremented once for each record that's written.
+    /// Unlike timestamps, it never goes backwards when the system clock is
+    /// adjusted, so it can be used to order records unambiguously. Records
+    /// written at the same time from different threads are numbered in the
+    /// order they're formatted. Records that are formatted without being
+    /// printed, like by [`Logger::format_to_string`], are written with the
+    /// number the next printed record will get, without using it up. This is
+    /// disabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format_sequence(true);
+    /// ```
+    ///
+    /// A record with a sequence number looks like this:
+    ///
+    /// ```text
+    /// 42  INFO 2018-02-13T23:08:32Z: app: starting up
+    /// ```
+    ///
+    /// [`Logger::format_to_string`]: struct.Logger.html#method.format_to_string
+    pub fn format_sequence(&mut self, sequence: bool) -> &mut Self {
+        self.format.sequence = sequence;
+        self
     }
 
-    fn flush(&self) {}
-}
-
+    /// Sets which fields the default format writes, and in what order.
+    ///
+    /// By default, records are written as the level, timestamp, thread,
+    /// module path and file, followed by the message, like
+    /// ` INFO 2018-02-13T23:08:32Z: app: starting up`. With a field order,
+    /// only the given fields are written, in that order, separated by the
+    /// [field separator]. Fields that a record doesn't have, like a module
+    /// path, are left out along with their separator.
+    ///
+    /// Each field keeps its other options, so the level is still padded and
+    /// colored, and the timestamp is only written if it's enabled with
+    /// [`format_timestamp`]. Toggles like [`format_module_path`] and
+    /// [`format_sequence`] are ignored, since the order picks the fields
+    /// instead.
+    ///
+    /// # Examples
+    ///
+    /// Write the timestamp first, then the level, module path and message:
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    /// use env_logger::fmt::Field;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format_field_order(&[Field::Timestamp, Field::Level, Field::ModulePath, Field::Message]);
+    /// ```
+    ///
+    /// A record then looks like this:
+    ///
+    /// ```text
+    /// 2018-02-13T23:08:32Z  INFO app starting up
+    /// ```
+    ///
+    /// [field separator]: #method.format_field_separator
+    /// [`format_timestamp`]: #method.format_timestamp
+    /// [`format_module_path`]: #method.format_module_path
+    /// [`format_sequence`]: #method.format_sequence
+    pub fn format_field_order(&mut self, field_order: &[fmt::Field]) -> &mut Self {
+        self.format.field_order = Some(field_order.to_vec());
+        self
+    }
+
+    /// Sets the separator written between fields when a
+    /// [field order] is set.
+    ///
+    /// The default is a single space.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    /// use env_logger::fmt::Field;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder
+    ///     .format_field_order(&[Field::Level, Field::ModulePath, Field::Message])
+    ///     .format_field_separator(" | ");
+    /// ```
+    ///
+    /// [field order]: #method.format_field_order
+    pub fn format_field_separator(&mut self, separator: &str) -> &mut Self {
+        self.format.field_separator = separator.to_owned();
+        self
+    }
+
+    /// Sets the width of the module path column in the default format.
+    ///
+    /// Module paths are padded to `width` characters so messages line up
+    /// in a column even when records come from modules with names of
+    /// different lengths. Longer module paths are cut short and end with
+    /// `…`. Paths are aligned to the left unless
+    /// [`format_target_right_align`] is enabled. Styles don't count towards
+    /// the width. The default of `0` writes module paths as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format_target_width(24);
+    /// ```
+    ///
+    /// [`format_target_right_align`]: #method.format_target_right_align
+    pub fn format_target_width(&mut self, width: usize) -> &mut Self {
+        self.format.target_width = width;
+        self
+    }
+
+    /// Whether or not to align module paths to the right when they're padded
+    /// by [`format_target_width`].
+    ///
+    /// This is disabled by default, so module paths are aligned to the left.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format_target_width(24)
+    ///        .format_target_right_align(true);
+    /// ```
+    ///
+    /// [`format_target_width`]: #method.format_target_width
+    pub fn format_target_right_align(&mut self, right_align: bool) -> &mut Self {
+        self.format.target_right_align = right_align;
+        self
+    }
+
+    /// Sets the delimiters to write around levels in the default format.
+    ///
+    /// When set, levels are written between `open` and `close`, like
+    /// `[INFO]`, and the level is separated from the rest of the record with
+    /// a space instead of `: `. Padded levels are padded outside of the
+    /// delimiters, so they stay next to the level. The delimiters aren't
+    /// colored unless [`format_color_delimiters`] is enabled. By default,
+    /// levels aren't delimited.
+    ///
+    /// # Examples
+    ///
+    /// Write records like `[INFO] [my_app] starting up`:
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format_level_delimiters("[", "]")
+    ///        .format_target_delimiters("[", "]");
+    /// ```
+    ///
+    /// [`format_color_delimiters`]: #method.format_color_delimiters
+    pub fn format_level_delimiters(&mut self, open: &str, close: &str) -> &mut Self {
+        self.format.level_delimiters = Some((open.to_owned(), close.to_owned()));
+        self
+    }
+
+    /// Sets the delimiters to write around module paths in the default format.
+    ///
+    /// When set, module paths are written between `open` and `close`, like
+    /// `[my_app]`, and are followed by a space instead of `: `. Module paths
+    /// padded by [`format_target_width`] are padded outside of the
+    /// delimiters. By default, module paths aren't delimited.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format_target_delimiters("<", ">");
+    /// ```
+    ///
+    /// [`format_target_width`]: #method.format_target_width
+    pub fn format_target_delimiters(&mut self, open: &str, close: &str) -> &mut Self {
+        self.format.target_delimiters = Some((open.to_owned(), close.to_owned()));
+        self
+    }
+
+    /// Whether or not the delimiters around levels are written in the
+    /// level's color.
+    ///
+    /// This only applies when delimiters are set with
+    /// [`format_level_delimiters`]. This is disabled by default, so only the
+    /// level itself is colored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format_level_delimiters("[", "]")
+    ///        .format_color_delimiters(true);
+    /// ```
+    ///
+    /// [`format_level_delimiters`]: #method.format_level_delimiters
+    pub fn format_color_delimiters(&mut self, color_delimiters: bool) -> &mut Self {
+        self.format.color_delimiters = color_delimiters;
+        self
+    }
+
+    /// Whether or not to write the source file and line of records in the
+    /// default format.
+    ///
+    /// When enabled, the location is written as `file:line` after the module
+    /// path and before the message. Records with a file but no line only
+    /// write the file, and records without a file skip it. This is disabled
+    /// by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format_file(true);
+    /// ```
+    pub fn format_file(&mut self, file: bool) -> &mut Self {
+        self.format.file = file;
+        self
+    }
+
+    /// Whether or not to write the name of the logging thread in the default
+    /// format.
+    ///
+    /// When enabled, the thread is written in brackets after the timestamp.
+    /// Threads without a name are written as their numeric id instead.
+    /// Control characters in thread names are escaped. This is disabled by
+    /// default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format_thread_name(true);
+    /// ```
+    pub fn format_thread_name(&mut self, thread_name: bool) -> &mut Self {
+        self.format.thread_name = thread_name;
+        self
+    }
+
+    /// Whether or not to write the numeric id of the logging thread in the
+    /// default format.
+    ///
+    /// When enabled, the thread id is written in brackets after the timestamp.
+    /// If [`format_thread_name`] is also enabled, named threads are written as
+    /// `[name:id]`. This is disabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format_thread_id(true);
+    /// ```
+    ///
+    /// [`format_thread_name`]: #method.format_thread_name
+    pub fn format_thread_id(&mut self, thread_id: bool) -> &mut Self {
+        self.format.thread_id = thread_id;
+        self
+    }
+
+    /// Sets the precision of timestamps in the built-in formats, or leaves
+    /// them out.
+    ///
+    /// Passing `None` omits the timestamp from records entirely, which is
+    /// useful when something else, like a container orchestrator, already
+    /// timestamps every line. Otherwise timestamps are written with the given
+    /// precision. The default is `Some(TimestampPrecision::Seconds)`.
+    ///
+    /// This applies to the default, JSON and `logfmt` formats.
+    ///
+    /// # Examples
+    ///
+    /// Leave timestamps out of records:
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format_timestamp(None);
+    /// ```
+    ///
+    /// Write timestamps with millisecond precision:
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    /// use env_logger::fmt::TimestampPrecision;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format_timestamp(Some(TimestampPrecision::Millis));
+    /// ```
+    pub fn format_timestamp(&mut self, timestamp: Option<fmt::TimestampPrecision>) -> &mut Self {
+        self.format.timestamp = timestamp;
+        self
+    }
+
+    /// Sets a fixed offset from UTC to write the timestamps of records in.
+    ///
+    /// Timestamps are written in UTC with a `Z` by default. With an offset,
+    /// they're written in that offset with a numeric suffix, like
+    /// `2018-02-14T08:08:32+09:00`, no matter what the local timezone is.
+    /// The precision set with [`format_timestamp`] still applies.
+    ///
+    /// This applies to the default, JSON and `logfmt` formats.
+    ///
+    /// # Examples
+    ///
+    /// Write timestamps in Japan Standard Time:
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    /// use env_logger::fmt::UtcOffset;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format_timestamp_offset(UtcOffset::east(9 * 3600));
+    /// ```
+    ///
+    /// [`format_timestamp`]: #method.format_timestamp
+    pub fn format_timestamp_offset(&mut self, offset: Option<fmt::UtcOffset>) -> &mut Self {
+        self.format.timestamp_offset = offset;
+        self
+    }
+
+    /// Whether or not to leave the date out of the timestamps of records.
+    ///
+    /// Timestamps are written with the date by default, like
+    /// `2018-02-14T00:08:32Z`. When `time_only` is `true`, only the time of
+    /// day is written, like `00:08:32`, which is easier to scan when a
+    /// process only runs for a short while. The precision set with
+    /// [`format_timestamp`] and the offset set with
+    /// [`format_timestamp_offset`] still apply, but the offset itself isn't
+    /// written.
+    ///
+    /// This only applies to the default format.
+    ///
+    /// # Examples
+    ///
+    /// Only write the time of day with milliseconds:
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    /// use env_logger::fmt::TimestampPrecision;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder
+    ///     .format_timestamp(Some(TimestampPrecision::Millis))
+    ///     .format_timestamp_time_only(true);
+    /// ```
+    ///
+    /// [`format_timestamp`]: #method.format_timestamp
+    /// [`format_timestamp_offset`]: #method.format_timestamp_offset
+    pub fn format_timestamp_time_only(&mut self, time_only: bool) -> &mut Self {
+        self.format.timestamp_time_only = time_only;
+        self
+    }
+
+    /// Reuses the formatted timestamp of records for up to `refresh`.
+    ///
+    /// Reading and formatting the clock for every record is measurable when
+    /// logging heavily. With a `refresh` period set, each thread formats the
+    /// timestamp once and reuses it until it's older than `refresh`, so
+    /// timestamps can lag behind the clock by up to that long. The default of
+    /// `None` gets a fresh timestamp for every record.
+    ///
+    /// This applies to the default, JSON and `logfmt` formats. Timestamps are
+    /// written with whole seconds by default, so a period of one second is
+    /// usually enough.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format_timestamp_cache(Some(Duration::from_secs(1)));
+    /// ```
+    pub fn format_timestamp_cache(&mut self, refresh: Option<Duration>) -> &mut Self {
+        self.format.timestamp_cache = refresh;
+        self
+    }
+
+    /// Sets the bytes written at the end of each record.
+    ///
+    /// The built-in formats end each record with `\n` by default. Use this to
+    /// end records with `\r\n` instead, or with nothing at all when records
+    /// are framed some other way. Custom [`format`] functions write their own
+    /// line endings.
+    ///
+    /// # Examples
+    ///
+    /// End each record with `\r\n`:
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.line_terminator(b"\r\n");
+    /// ```
+    ///
+    /// [`format`]: #method.format
+    pub fn line_terminator(&mut self, line_terminator: &[u8]) -> &mut Self {
+        self.format.line_terminator = line_terminator.to_vec();
+        self
+    }
+
+    /// Sets a function that writes a prefix at the start of each record in
+    /// the default format.
+    ///
+    /// The prefix is written before anything else in the record, including
+    /// the timestamp. Only an `sd-daemon` priority from [`format_sd_daemon`]
+    /// comes before it. The function is given the same [`Formatter`] as the
+    /// rest of the record, so the prefix can be styled, and the record itself.
+    ///
+    /// # Examples
+    ///
+    /// Start every record with the name of the host:
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.line_prefix(|buf, _| write!(buf, "[host=web01] "));
+    /// ```
+    ///
+    /// Write the prefix in bold:
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.line_prefix(|buf, _| {
+    ///     let mut style = buf.style();
+    ///     style.set_bold(true);
+    ///
+    ///     write!(buf, "{} ", style.value("[host=web01]"))
+    /// });
+    /// ```
+    ///
+    /// [`format_sd_daemon`]: #method.format_sd_daemon
+    /// [`Formatter`]: fmt/struct.Formatter.html
+    pub fn line_prefix<F: 'static>(&mut self, line_prefix: F) -> &mut Self
+        where F: Fn(&mut Formatter, &Record) -> io::Result<()> + Sync + Send
+    {
+        self.format.line_prefix = Some(Box::new(line_prefix));
+        self
+    }
+
+    /// Sets a function that writes a suffix at the end of each record in the
+    /// default format.
+    ///
+    /// The suffix is written after the message and any key-value pairs, but
+    /// before the [`line_terminator`]. See [`line_prefix`] for more details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.line_suffix(|buf, _| write!(buf, " [host=web01]"));
+    /// ```
+    ///
+    /// [`line_terminator`]: #method.line_terminator
+    /// [`line_prefix`]: #method.line_prefix
+    pub fn line_suffix<F: 'static>(&mut self, line_suffix: F) -> &mut Self
+        where F: Fn(&mut Formatter, &Record) -> io::Result<()> + Sync + Send
+    {
+        self.format.line_suffix = Some(Box::new(line_suffix));
+        self
+    }
+
+    /// Sets the number of spaces to indent continuation lines of a message.
+    ///
+    /// When a message spans multiple lines, like a stack trace or a
+    /// pretty-printed struct, every line after the first is indented by
+    /// `indent` spaces so it stands apart from the next record. The default
+    /// of `None` writes continuation lines as they are.
+    ///
+    /// This only applies to the default format. The JSON and `logfmt` formats
+    /// escape newlines, and custom [`format`] functions write messages
+    /// themselves.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format_indent(Some(4));
+    /// ```
+    ///
+    /// [`format`]: #method.format
+    pub fn format_indent(&mut self, indent: Option<usize>) -> &mut Self {
+        self.format.indent = indent;
+        self
+    }
+
+    /// Sets the number of spaces to indent messages by for each nested
+    /// [`Scope`] they're logged in.
+    ///
+    /// The default format indents each message by `scope_indent` spaces for
+    /// every scope entered on the thread that logged it, so the records of
+    /// nested operations read like a tree. The rest of the record isn't
+    /// indented, so timestamps and levels still line up. The default is `2`,
+    /// and `0` turns indentation off.
+    ///
+    /// This only applies to the default format. Custom [`format`] functions
+    /// can use [`Formatter::scope_depth`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format_scope_indent(4);
+    /// ```
+    ///
+    /// [`Scope`]: fmt/struct.Scope.html
+    /// [`format`]: #method.format
+    /// [`Formatter::scope_depth`]: fmt/struct.Formatter.html#method.scope_depth
+    pub fn format_scope_indent(&mut self, scope_indent: usize) -> &mut Self {
+        self.format.scope_indent = scope_indent;
+        self
+    }
+
+    /// Sets the maximum number of columns a record's message can reach.
+    ///
+    /// Messages that would extend past `width` columns, counting everything
+    /// written before them on the line, are cut short and end with `…`
+    /// instead of wrapping. Escape sequences for styles don't count towards
+    /// the width. The default of `None` never truncates messages.
+    ///
+    /// Each character is counted as a single column, and a message that
+    /// spans multiple lines is measured as if it were one line.
+    ///
+    /// This only applies to the default format.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.max_line_width(Some(120));
+    /// ```
+    pub fn max_line_width(&mut self, width: Option<usize>) -> &mut Self {
+        self.format.max_line_width = width;
+        self
+    }
+
+    /// Sets the function that picks the color of each log level.
+    ///
+    /// The default format and [`Formatter::default_level_style`] use the
+    /// returned color instead of the built-in ones. Errors are still bold.
+    /// Nothing is colored when styles are disabled.
+    ///
+    /// # Examples
+    ///
+    /// Use a palette that's easier to read on a light background:
+    ///
+    /// ```
+    /// # extern crate log;
+    /// # extern crate env_logger;
+    /// use log::Level;
+    /// use env_logger::Builder;
+    /// use env_logger::fmt::Color;
+    ///
+    /// # fn main() {
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format_level_color(|level| match level {
+    ///     Level::Error => Color::Red,
+    ///     Level::Warn => Color::Magenta,
+    ///     Level::Info => Color::Blue,
+    ///     Level::Debug | Level::Trace => Color::Black,
+    /// });
+    /// # }
+    /// ```
+    ///
+    /// [`Formatter::default_level_style`]: fmt/struct.Formatter.html#method.default_level_style
+    pub fn format_level_color<F: 'static>(&mut self, level_color: F) -> &mut Self
+        where F: Fn(Level) -> fmt::Color + Sync + Send
+    {
+        self.level_color = Some(Arc::new(level_color));
+        self
+    }
+
+    /// Sets the clock that timestamps are taken from.
+    ///
+    /// Timestamps are taken from the system clock by default. A custom clock
+    /// makes timestamps predictable, so tests can check the exact output of
+    /// a format. It's used for every timestamp a [`Formatter`] writes,
+    /// including ones in the default, JSON and `logfmt` formats, which
+    /// aren't cached when a custom clock is set. Relative timestamps still
+    /// measure the time between records with the system clock.
+    ///
+    /// # Examples
+    ///
+    /// Write every record at the same time:
+    ///
+    /// ```
+    /// # extern crate log;
+    /// # extern crate env_logger;
+    /// use std::io::Write;
+    /// use std::time::{Duration, UNIX_EPOCH};
+    /// use log::{Level, Record};
+    /// use env_logger::Builder;
+    ///
+    /// # fn main() {
+    /// let logger = Builder::new()
+    ///     .clock(|| UNIX_EPOCH + Duration::from_secs(1_518_563_312))
+    ///     .format(|buf, record| writeln!(buf, "{} {}", buf.timestamp(), record.args()))
+    ///     .build();
+    ///
+    /// let formatted = logger.format_to_string(&Record::builder()
+    ///     .level(Level::Info)
+    ///     .args(format_args!("started"))
+    ///     .build())
+    ///     .unwrap();
+    ///
+    /// assert_eq!("2018-02-13T23:08:32Z started\n", formatted);
+    /// # }
+    /// ```
+    ///
+    /// [`Formatter`]: fmt/struct.Formatter.html
+    pub fn clock<F: 'static>(&mut self, clock: F) -> &mut Self
+        where F: Fn() -> SystemTime + Sync + Send
+    {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// Adds a key-value pair that's written with every record.
+    ///
+    /// Metadata is useful for stamping records with constant values, like a
+    /// deployment region or an instance id. The default format writes each
+    /// pair after the message as `key=value`, in the order they were added.
+    /// With the `kv` feature, metadata is written after the record's own
+    /// key-value pairs.
+    /// Keys and values that are empty or contain whitespace, control
+    /// characters, `=` or `"` are quoted and escaped. Metadata isn't passed to
+    /// a custom [`format`].
+    ///
+    /// Every pair is written on every record, so keep the set of metadata small
+    /// enough that it doesn't crowd out the message.
+    ///
+    /// # Examples
+    ///
+    /// Write the region and instance id with each record:
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.metadata("region", "us-east-1")
+    ///        .metadata("instance", "i-0a1b2c3d");
+    /// ```
+    ///
+    /// [`format`]: #method.format
+    pub fn metadata<K, V>(&mut self, key: K, value: V) -> &mut Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.format.metadata.push((key.into(), value.into()));
+        self
+    }
+
+    /// Whether or not to prefix records with an `sd-daemon` priority.
+    ///
+    /// When enabled, each record starts with `<N>`, where `N` is the syslog
+    /// severity of the record's level. `systemd` recognizes these prefixes on
+    /// standard error and uses them to set the priority of journal entries.
+    /// The prefix is written before anything else in the record, including
+    /// records written by a custom [`format`], so it works alongside all of
+    /// the other format options.
+    ///
+    /// These are the same syslog priority prefixes understood by `journald`
+    /// on any stream it collects, so there's no separate syslog prefix option.
+    ///
+    /// Levels map to priorities as follows:
+    ///
+    /// | Level   | Priority        |
+    /// | ------- | --------------- |
+    /// | `Error` | `3` (`err`)     |
+    /// | `Warn`  | `4` (`warning`) |
+    /// | `Info`  | `6` (`info`)    |
+    /// | `Debug` | `7` (`debug`)   |
+    /// | `Trace` | `7` (`debug`)   |
+    ///
+    /// Styles are never written while this is enabled, even for streams or
+    /// targets with their own style choice, because the journal would store
+    /// the control characters verbatim.
+    ///
+    /// [`format`]: #method.format
+    pub fn format_sd_daemon(&mut self, sd_daemon: bool) -> &mut Self {
+        self.format.sd_daemon = sd_daemon;
+        self
+    }
+
+    /// Sends a desktop notification for records at or above the given level.
+    ///
+    /// After a record at `level` or more severe is written, an OSC 9 escape
+    /// sequence is written with its message, which terminals like iTerm2,
+    /// Windows Terminal and kitty show as a desktop notification. This is
+    /// handy for noticing errors in long-running tasks. Terminals that don't
+    /// support it ignore the sequence. Messages are written on one line and
+    /// truncated to 100 characters, so a long message doesn't flood the
+    /// notification.
+    ///
+    /// Notifications are escape sequences, so they're only written when
+    /// styles are, and never with [`WriteStyle::Never`]. This works with
+    /// custom formats too, but not with the JSON or `logfmt` formats, whose
+    /// records would no longer parse. The default is `None`, which never
+    /// notifies.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate log;
+    /// # extern crate env_logger;
+    /// use log::Level;
+    /// use env_logger::Builder;
+    ///
+    /// # fn main() {
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.notify_level(Some(Level::Error));
+    /// # }
+    /// ```
+    ///
+    /// [`WriteStyle::Never`]: fmt/enum.WriteStyle.html#variant.Never
+    pub fn notify_level(&mut self, level: Option<Level>) -> &mut Self {
+        self.format.notify_level = level;
+        self
+    }
+
+    /// Sets the target for the log output.
+    ///
+    /// Env logger can log to either stdout, stderr, both split by level, or a
+    /// custom pipe. It can also format records and discard them with
+    /// `Target::Null`, or print them to several targets at once with
+    /// `Target::Multi`. On Windows, `Target::DebugOutput` sends records to an
+    /// attached debugger. The default is stderr.
+    ///
+    /// # Examples
+    ///
+    /// Write warnings and errors to stderr, and everything else to stdout:
+    ///
+    /// ```
+    /// # extern crate log;
+    /// # extern crate env_logger;
+    /// use log::Level;
+    /// use env_logger::{Builder, Target};
+    ///
+    /// # fn main() {
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.target(Target::Split(Level::Warn));
+    /// # }
+    /// ```
+    ///
+    /// Write log records to a file:
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use env_logger::{Builder, Target};
+    ///
+    /// let file = File::create("app.log").expect("failed to create log file");
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.target(Target::Pipe(Box::new(file)));
+    /// ```
+    ///
+    /// Write log records to stderr and a file, only coloring them on stderr:
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use env_logger::{Builder, Target};
+    ///
+    /// let file = File::create("app.log").expect("failed to create log file");
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.target(Target::Multi(vec![Target::Stderr, Target::Pipe(Box::new(file))]));
+    /// ```
+    pub fn target(&mut self, target: fmt::Target) -> &mut Self {
+        self.writer.target(target);
+        self
+    }
+
+    /// Sets the target for the log output to a file that's rotated by size.
+    ///
+    /// Records are appended to the file at `path`, which is created if it
+    /// doesn't exist. Before a record would take the file past `max_bytes`,
+    /// it's renamed to `path.1`, older files are shifted along to `path.2`
+    /// and so on, and a new file is started. The last `max_files` rotated
+    /// files are kept, and older ones are removed.
+    ///
+    /// The file is opened when the first record is printed, so errors
+    /// opening or rotating it are reported like any other error printing
+    /// records. Only one logger should write to the file at a time, and
+    /// rotation isn't coordinated with other processes.
+    ///
+    /// # Examples
+    ///
+    /// Keep up to 5 files of 10MB each:
+    ///
+    /// ```no_run
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.target_rotating_file("app.log", 10 * 1024 * 1024, 5);
+    /// ```
+    pub fn target_rotating_file<P: AsRef<Path>>(&mut self, path: P, max_bytes: u64, max_files: usize) -> &mut Self {
+        self.writer.target_file(fmt::FileTarget::Rotating {
+            path: path.as_ref().to_owned(),
+            max_bytes: max_bytes,
+            max_files: max_files,
+        });
+        self
+    }
+
+    /// Sets the target for the log output to a file that's rotated daily.
+    ///
+    /// Records are appended to a file with the current date in its name, so
+    /// a `path` of `app.log` writes to files like `app-2024-01-15.log`. The
+    /// date is in the local timezone if `local` is `true`, and in UTC
+    /// otherwise. It's checked each time a record is printed, so a new file
+    /// is started by the first record after midnight, even if the process was
+    /// idle over it. Old files aren't removed.
+    ///
+    /// Errors opening the files are reported like any other error printing
+    /// records.
+    ///
+    /// # Examples
+    ///
+    /// Start a new file at midnight in the local timezone:
+    ///
+    /// ```no_run
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.target_daily_file("app.log", true);
+    /// ```
+    pub fn target_daily_file<P: AsRef<Path>>(&mut self, path: P, local: bool) -> &mut Self {
+        self.writer.target_file(fmt::FileTarget::Daily {
+            path: path.as_ref().to_owned(),
+            local: local,
+        });
+        self
+    }
+
+    /// Sets whether or not styles will be written.
+    /// 
+    /// This can be useful in environments that don't support control characters
+    /// for setting colors.
+    pub fn write_style(&mut self, write_style: fmt::WriteStyle) -> &mut Self {
+        self.writer.write_style(write_style);
+        self
+    }
+
+    /// Sets the `termcolor` color choice to write styles with.
+    ///
+    /// This bypasses [`write_style`] for users who need `termcolor`'s full
+    /// set of choices. In particular, `ColorChoice::AlwaysAnsi` writes ANSI
+    /// escape codes even on Windows, which is useful when a console is
+    /// redirected on CI but the captured output should still be colored.
+    /// The color choice isn't changed by environment variables like
+    /// `NO_COLOR`. Calling [`write_style`] or [`parse_write_style`] afterwards
+    /// replaces it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    /// use env_logger::fmt::ColorChoice;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.color_choice(ColorChoice::AlwaysAnsi);
+    /// ```
+    ///
+    /// [`write_style`]: #method.write_style
+    /// [`parse_write_style`]: #method.parse_write_style
+    pub fn color_choice(&mut self, color_choice: fmt::ColorChoice) -> &mut Self {
+        self.writer.color_choice(color_choice);
+        self
+    }
+
+    /// Sets whether or not styles will be written to standard output.
+    ///
+    /// This replaces the style chosen by [`write_style`] or [`color_choice`]
+    /// for standard output only, so a [`Target::Split`] logger can write
+    /// colors to an interactive terminal on one stream and plain text to a
+    /// redirected file on the other. The same goes for standard error with
+    /// [`stderr_write_style`].
+    ///
+    /// An `Auto` choice respects the conventional environment variables like
+    /// `NO_COLOR` for that stream, as described in the crate documentation,
+    /// while explicit `Always` and `Never` choices ignore them.
+    ///
+    /// # Examples
+    ///
+    /// Write styles to standard error, but never to standard output:
+    ///
+    /// ```
+    /// # extern crate log;
+    /// # extern crate env_logger;
+    /// use log::Level;
+    /// use env_logger::{Builder, Target, WriteStyle};
+    ///
+    /// # fn main() {
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.target(Target::Split(Level::Warn))
+    ///        .stdout_write_style(WriteStyle::Never);
+    /// # }
+    /// ```
+    ///
+    /// [`write_style`]: #method.write_style
+    /// [`color_choice`]: #method.color_choice
+    /// [`Target::Split`]: fmt/enum.Target.html#variant.Split
+    /// [`stderr_write_style`]: #method.stderr_write_style
+    pub fn stdout_write_style(&mut self, write_style: fmt::WriteStyle) -> &mut Self {
+        self.writer.stdout_write_style(write_style);
+        self
+    }
+
+    /// Sets whether or not styles will be written to standard error.
+    ///
+    /// This is the counterpart of [`stdout_write_style`] for standard error.
+    ///
+    /// [`stdout_write_style`]: #method.stdout_write_style
+    pub fn stderr_write_style(&mut self, write_style: fmt::WriteStyle) -> &mut Self {
+        self.writer.stderr_write_style(write_style);
+        self
+    }
+
+    /// Sets whether or not styles will be written for records whose target
+    /// starts with `target`.
+    ///
+    /// This replaces the style chosen by [`write_style`] or [`color_choice`]
+    /// for those records only, so a noisy dependency can be logged in plain
+    /// text while the rest of the records are colored. Targets are matched
+    /// by prefix like filter directives, and the longest matching prefix is
+    /// used. An `Auto` choice goes back to the logger's own style choice,
+    /// which is useful for turning styles back on for part of a target.
+    ///
+    /// Records with their own style choice are formatted separately, so
+    /// `Always` writes escape codes even if the target doesn't support
+    /// them.
+    ///
+    /// # Examples
+    ///
+    /// Write records from `hyper` without styles, except for `hyper::server`:
+    ///
+    /// ```
+    /// use env_logger::{Builder, WriteStyle};
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.target_write_style("hyper", WriteStyle::Never)
+    ///        .target_write_style("hyper::server", WriteStyle::Auto);
+    /// ```
+    ///
+    /// [`write_style`]: #method.write_style
+    /// [`color_choice`]: #method.color_choice
+    pub fn target_write_style(&mut self, target: &str, write_style: fmt::WriteStyle) -> &mut Self {
+        self.writer.target_write_style(target, write_style);
+        self
+    }
+
+    /// Whether or not to collapse consecutive duplicate records.
+    ///
+    /// Records are duplicates if they have the same level, target and
+    /// message. When enabled, a record that duplicates the one before it
+    /// isn't written. Instead, a record with the same level and target that
+    /// says `last message repeated N times` is written when the next record
+    /// that's different is logged, or when the logger is flushed with
+    /// `log::logger().flush()`. The summary is formatted like any other record.
+    ///
+    /// Records logged while formatting another one, like from a `Display`
+    /// implementation, are deduplicated too.
+    ///
+    /// Records are compared across all threads, but when several threads log
+    /// at once, another thread's record can be written before the summary or
+    /// between the summary and the next record. This only applies to the
+    /// logger installed by [`init`] or [`build`], and is disabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.deduplicate(true);
+    /// ```
+    ///
+    /// [`init`]: #method.init
+    /// [`build`]: #method.build
+    pub fn deduplicate(&mut self, deduplicate: bool) -> &mut Self {
+        self.deduplicate = deduplicate;
+        self
+    }
+
+    /// Sets what to do when a record can't be formatted or printed.
+    ///
+    /// Errors are ignored by default, so a target that stops working doesn't
+    /// bring the program down with it. With `ErrorPolicy::Stderr`, the error
+    /// and the record's message are written to standard error instead, which
+    /// makes a broken target easier to debug. `ErrorPolicy::Abort` writes
+    /// the error and then aborts the process, for programs where losing
+    /// records isn't acceptable. Errors flushing the target are handled in
+    /// the same way.
+    ///
+    /// Records printed on a background thread, with [`background`] or
+    /// [`reorder_window`], can't report their errors, so they're always
+    /// ignored. Streams closed by their reader are handled by
+    /// [`ignore_broken_pipe`] first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    /// use env_logger::fmt::ErrorPolicy;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.on_write_error(ErrorPolicy::Stderr);
+    /// ```
+    ///
+    /// [`background`]: #method.background
+    /// [`reorder_window`]: #method.reorder_window
+    /// [`ignore_broken_pipe`]: #method.ignore_broken_pipe
+    pub fn on_write_error(&mut self, error_policy: fmt::ErrorPolicy) -> &mut Self {
+        self.error_policy = error_policy;
+        self
+    }
+
+    /// Whether or not to strip escape sequences from records when styles
+    /// aren't written.
+    ///
+    /// Styles applied with a [`Style`] are already left out when styles are
+    /// disabled, but escape sequences that a custom [`format`] writes itself
+    /// are printed verbatim. When enabled, ANSI escape sequences are removed
+    /// from each record before it's printed if styles are disabled for the
+    /// target. Records are printed unchanged if styles are enabled. This is
+    /// disabled by default, so records can intentionally include escapes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.format(|buf, record| writeln!(buf, "\x1b[1m{}\x1b[0m: {}", record.level(), record.args()))
+    ///        .strip_ansi(true);
+    /// ```
+    ///
+    /// [`Style`]: fmt/struct.Style.html
+    /// [`format`]: #method.format
+    pub fn strip_ansi(&mut self, strip_ansi: bool) -> &mut Self {
+        self.writer.strip_ansi(strip_ansi);
+        self
+    }
+
+    /// Set how bold text is written to consoles on Windows.
+    ///
+    /// Windows consoles that understand escape sequences usually render bold
+    /// text, like the default style of `ERROR`, in a brighter color instead
+    /// of a heavier weight. The legacy console API ignores bold altogether.
+    /// Use `WindowsBold::Ignore` to keep bold from changing colors, or
+    /// `WindowsBold::Intense` to make bold text bright on every console.
+    /// Bold text is written as it is by default.
+    ///
+    /// This has no effect on other platforms.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    /// use env_logger::fmt::WindowsBold;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.windows_bold(WindowsBold::Ignore);
+    /// ```
+    pub fn windows_bold(&mut self, windows_bold: fmt::WindowsBold) -> &mut Self {
+        self.writer.windows_bold(windows_bold);
+        self
+    }
+
+    /// Whether or not to print records as HTML.
+    ///
+    /// When enabled, styles are always written, whatever the write style,
+    /// and each record is converted to HTML before it's printed. Styles
+    /// become `<span>` elements with inline CSS, like
+    /// `<span style="color:#cd0000;font-weight:bold">ERROR</span>`, and `<`,
+    /// `>`, `&` and `"` in the record are escaped. This is useful for
+    /// embedding records in a web page, like a dashboard. Newlines are kept,
+    /// so records should be shown in a `<pre>` element or with
+    /// `white-space: pre`. This is disabled by default.
+    ///
+    /// Records formatted with [`format_to_string`] or written by a
+    /// [`WriteLogger`] aren't converted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// use env_logger::{Builder, Target};
+    ///
+    /// # fn run() -> std::io::Result<()> {
+    /// let file = File::create("log.html")?;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.target(Target::Pipe(Box::new(file)))
+    ///        .write_html(true);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`format_to_string`]: struct.Logger.html#method.format_to_string
+    /// [`WriteLogger`]: struct.WriteLogger.html
+    pub fn write_html(&mut self, html: bool) -> &mut Self {
+        self.writer.html(html);
+        self
+    }
+
+    /// Whether or not to write a UTF-8 byte order mark at the start of a
+    /// [`Target::Pipe`].
+    ///
+    /// Some Windows tools only read a file as UTF-8 if it starts with a byte
+    /// order mark (`EF BB BF`), and show other characters garbled otherwise.
+    /// When enabled, the mark is written once, just before the first record
+    /// printed to each pipe, including the pipes of a `Target::Multi`. The
+    /// pipe should start out empty, so a file that's appended to shouldn't
+    /// be used as a pipe. This is disabled by default.
+    ///
+    /// Files set with [`target_rotating_file`] or [`target_daily_file`] get
+    /// a mark at the start of each new file instead, including each file
+    /// they're rotated to. Files that already exist don't get another one.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use env_logger::{Builder, Target};
+    ///
+    /// let file = File::create("app.log").expect("failed to create log file");
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.target(Target::Pipe(Box::new(file)))
+    ///        .write_bom(true);
+    /// ```
+    ///
+    /// [`Target::Pipe`]: fmt/enum.Target.html#variant.Pipe
+    /// [`target_rotating_file`]: #method.target_rotating_file
+    /// [`target_daily_file`]: #method.target_daily_file
+    pub fn write_bom(&mut self, bom: bool) -> &mut Self {
+        self.writer.bom(bom);
+        self
+    }
+
+    /// Whether or not to stop writing to a stream once its reader closes it.
+    ///
+    /// When a program's output is piped into a command like `head`, the reader
+    /// can close the pipe before the program is done logging. When enabled,
+    /// records are silently dropped from the first time writing to a stream
+    /// fails with `BrokenPipe` for the rest of the logger's life, and aren't
+    /// formatted either. When disabled, writing is attempted for every record.
+    /// Errors writing records are never returned or raised as panics either
+    /// way. This is enabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.ignore_broken_pipe(false);
+    /// ```
+    pub fn ignore_broken_pipe(&mut self, ignore_broken_pipe: bool) -> &mut Self {
+        self.writer.ignore_broken_pipe(ignore_broken_pipe);
+        self
+    }
+
+    /// Print records on a background thread instead of the logging thread.
+    ///
+    /// Formatted records are queued, and a dedicated thread prints them to
+    /// the target, so logging doesn't wait for slow terminals or pipes. The
+    /// queue holds up to `capacity` records. When it's full, `overflow`
+    /// decides whether the logging thread waits for room or the oldest
+    /// queued record is dropped.
+    ///
+    /// Errors printing records can't be reported from the background thread,
+    /// so they're ignored. Flushing the logger, or a record at or above the
+    /// [`flush_on_level`], waits until every queued record has been printed.
+    ///
+    /// Records that are still queued when the process exits normally, by
+    /// returning from `main` or calling `std::process::exit`, are printed
+    /// before it exits if the logger was installed with one of the `init`
+    /// methods. A logger that's only built prints them when it's dropped.
+    /// Records are lost if the process is aborted or killed by a signal, so
+    /// call `log::logger().flush()` before a crash is expected.
+    ///
+    /// This method is only available with the `background` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    /// use env_logger::fmt::Overflow;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.background(1024, Overflow::Block);
+    /// ```
+    ///
+    /// [`flush_on_level`]: #method.flush_on_level
+    #[cfg(feature = "background")]
+    pub fn background(&mut self, capacity: usize, overflow: fmt::Overflow) -> &mut Self {
+        self.writer.background(capacity, overflow);
+        self
+    }
+
+    /// Print records in the order they were logged, holding them for up to
+    /// `window` to put them back in order.
+    ///
+    /// Records logged on different threads at nearly the same time can be
+    /// printed slightly out of order, which makes their timestamps jump
+    /// backwards. With a reorder window, formatted records are buffered and
+    /// printed on a background thread in the order they were logged, once
+    /// they've been buffered for `window`. A few milliseconds is usually
+    /// enough. Records that are held up for longer than the window are still
+    /// printed out of order.
+    ///
+    /// The buffer holds up to `capacity` records. When it's full, the oldest
+    /// record is printed straight away and the logging thread waits for it.
+    /// Flushing the logger, or a record at or above the [`flush_on_level`],
+    /// prints every buffered record without waiting for the window to pass.
+    ///
+    /// This replaces the [`background`] queue, and has the same caveats:
+    /// errors printing records are ignored, and records that are still
+    /// buffered are printed when the process exits normally.
+    ///
+    /// This method is only available with the `background` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder.reorder_window(Duration::from_millis(5), 1024);
+    /// ```
+    ///
+    /// [`flush_on_level`]: #method.flush_on_level
+    /// [`background`]: #method.background
+    #[cfg(feature = "background")]
+    pub fn reorder_window(&mut self, window: Duration, capacity: usize) -> &mut Self {
+        self.writer.reorder(window, capacity);
+        self
+    }
+
+    /// Sets the minimum level of records that flush the target immediately.
+    ///
+    /// After a record at or above this level is written the target is flushed,
+    /// so critical records aren't held in a buffer if the process dies. Records
+    /// below this level may stay buffered by the target until it decides to
+    /// flush, which is cheaper when lots of records are being written.
+    /// The default is `LevelFilter::Error`.
+    ///
+    /// Standard error is unbuffered, so this mostly matters for standard output,
+    /// or for custom formats that don't end records with a newline.
+    ///
+    /// # Examples
+    ///
+    /// Flush the target after warnings and errors:
+    ///
+    /// ```
+    /// extern crate log;
+    /// extern crate env_logger;
+    ///
+    /// use log::LevelFilter;
+    /// use env_logger::{Builder, Target};
+    ///
+    /// fn main() {
+    ///     let mut builder = Builder::new();
+    ///
+    ///     builder.target(Target::Stdout)
+    ///            .flush_on_level(LevelFilter::Warn);
+    /// }
+    /// ```
+    pub fn flush_on_level(&mut self, level: LevelFilter) -> &mut Self {
+        self.writer.flush_level(level);
+        self
+    }
+
+    /// Parses the directives string in the same form as the `RUST_LOG`
+    /// environment variable.
+    ///
+    /// See the module documentation for more details.
+    pub fn parse(&mut self, filters: &str) -> &mut Self {
+        self.filter.parse(filters);
+        self
+    }
+
+    /// Sets a function that's called for each part of the filter directives
+    /// that couldn't be parsed.
+    ///
+    /// Invalid directives are ignored, so a typo like `RUST_LOG=myapp=ifno`
+    /// can make a module silently stop logging. The errors are reported when
+    /// the logger is built. By default, each one is written to `stderr` as a
+    /// warning. The function is also called for errors in directives passed
+    /// to [`FilterHandle::reload`].
+    ///
+    /// # Examples
+    ///
+    /// Collect the errors instead of printing them:
+    ///
+    /// ```
+    /// use std::sync::{Arc, Mutex};
+    /// use env_logger::Builder;
+    ///
+    /// let errors = Arc::new(Mutex::new(Vec::new()));
+    /// let collected = errors.clone();
+    ///
+    /// let logger = Builder::new()
+    ///     .on_filter_error(move |err| collected.lock().unwrap().push(err.to_string()))
+    ///     .parse("myapp=ifno")
+    ///     .build();
+    ///
+    /// assert_eq!(vec!["unknown log level 'ifno'"], *errors.lock().unwrap());
+    /// # drop(logger);
+    /// ```
+    ///
+    /// [`FilterHandle::reload`]: struct.FilterHandle.html#method.reload
+    pub fn on_filter_error<F: 'static>(&mut self, handler: F) -> &mut Self
+    where
+        F: Fn(&filter::ParseError) + Sync + Send,
+    {
+        self.filter_error = Some(Arc::new(handler));
+        self
+    }
+
+    /// Whether or not to lower parsed levels to the maximum level `log` was
+    /// compiled with.
+    ///
+    /// The `max_level_*` and `release_max_level_*` features of the `log`
+    /// crate remove more verbose log statements at compile time, so
+    /// `RUST_LOG=trace` can't turn them back on. When this is enabled, levels
+    /// parsed from directives that are above `log::STATIC_MAX_LEVEL` are
+    /// lowered to it, and the user is warned the same way as for
+    /// [other invalid directives]. This is disabled by default.
+    ///
+    /// Only directives parsed after calling this are checked, so call it
+    /// before [`parse`] or [`parse_env`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let mut builder = Builder::new();
+    ///
+    /// builder
+    ///     .static_max_level(true)
+    ///     .parse_env("RUST_LOG");
+    /// ```
+    ///
+    /// [other invalid directives]: #method.on_filter_error
+    /// [`parse`]: #method.parse
+    /// [`parse_env`]: #method.parse_env
+    pub fn static_max_level(&mut self, static_max_level: bool) -> &mut Self {
+        self.filter.static_max_level(static_max_level);
+        self
+    }
+
+    /// Sets a function that's called once the logger has been installed as
+    /// the global logger.
+    ///
+    /// This is a place for setup that should only happen when logging is
+    /// actually enabled, like printing a banner or registering a panic hook.
+    /// The function is called with the installed [`Logger`], so it can check
+    /// the logger's [`filter`] and [`directives`], or log a record through it.
+    /// The maximum log level has already been set when it's called. The
+    /// logger's target isn't available to the function, so setup that depends
+    /// on where records go needs to keep its own copy of that choice.
+    ///
+    /// The function is called by the `init` methods, like [`try_init`], and
+    /// only once. It isn't called if installing the logger fails, or when a
+    /// logger is only built.
+    ///
+    /// # Examples
+    ///
+    /// Print the filter once the logger has been installed:
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// Builder::new()
+    ///     .parse("info")
+    ///     .on_init(|logger| eprintln!("logging at {}", logger.filter()))
+    ///     .init();
+    /// ```
+    ///
+    /// [`Logger`]: struct.Logger.html
+    /// [`filter`]: struct.Logger.html#method.filter
+    /// [`directives`]: struct.Logger.html#method.directives
+    /// [`try_init`]: #method.try_init
+    pub fn on_init<F: 'static>(&mut self, on_init: F) -> &mut Self
+    where
+        F: FnOnce(&Logger) + Sync + Send,
+    {
+        self.on_init = Some(Box::new(on_init));
+        self
+    }
+
+    /// Parses whether or not to write styles in the same form as the `RUST_LOG_STYLE`
+    /// environment variable.
+    /// 
+    /// See the module documentation for more details.
+    pub fn parse_write_style(&mut self, write_style: &str) -> &mut Self {
+        self.writer.parse(write_style);
+        self
+    }
+
+    /// Initializes the global logger with the built env logger.
+    ///
+    /// This should be called early in the execution of a Rust program. Any log
+    /// events that occur before initialization will be ignored.
+    ///
+    /// # Errors
+    ///
+    /// This function will fail if it is called more than once, or if another
+    /// library has already initialized a global logger.
+    pub fn try_init(&mut self) -> Result<(), SetLoggerError> {
+        self.try_init_with_handle().map(|_| ())
+    }
+
+    /// Initializes the global logger with the built env logger, returning
+    /// a handle that can replace its filter later.
+    ///
+    /// # Examples
+    ///
+    /// Turn on debug logging after the logger has been installed:
+    ///
+    /// ```
+    /// # extern crate log;
+    /// # extern crate env_logger;
+    /// use log::LevelFilter;
+    /// use env_logger::Builder;
+    ///
+    /// # fn main() {
+    /// let handle = Builder::new().parse("info").try_init_with_handle().unwrap();
+    ///
+    /// handle.reload("debug");
+    ///
+    /// assert_eq!(LevelFilter::Debug, log::max_level());
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will fail if it is called more than once, or if another
+    /// library has already initialized a global logger.
+    pub fn try_init_with_handle(&mut self) -> Result<FilterHandle, SetLoggerError> {
+        let logger = self.build();
+        let handle = logger.filter_handle();
+
+        log::set_max_level(logger.filter());
+        let logger = install(logger)?;
+
+        self.run_on_init(logger);
+
+        Ok(handle)
+    }
+
+    /// Initializes the global logger with the built env logger, returning
+    /// an [`InitError`] instead of panicking if it fails.
+    ///
+    /// Unlike [`try_init`], this doesn't panic if the target can't be
+    /// written to, and the maximum log level is only changed once the logger
+    /// has been installed, so a logger that's already installed keeps its
+    /// level.
+    ///
+    /// # Examples
+    ///
+    /// Tell a logger that's already installed apart from other failures:
+    ///
+    /// ```
+    /// use env_logger::{Builder, InitErrorKind};
+    ///
+    /// match Builder::new().checked_init() {
+    ///     Ok(()) => (),
+    ///     Err(ref err) if err.kind() == InitErrorKind::AlreadyInitialized => (),
+    ///     Err(err) => eprintln!("failed to initialize logging: {}", err),
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will fail if it is called more than once, if another
+    /// library has already initialized a global logger, or if the target
+    /// can't be written to.
+    ///
+    /// [`InitError`]: struct.InitError.html
+    /// [`try_init`]: #method.try_init
+    pub fn checked_init(&mut self) -> Result<(), InitError> {
+        let logger = self.try_build().map_err(InitError::build)?;
+        let logger = install(logger).map_err(|_| InitError::new(InitErrorKind::AlreadyInitialized))?;
+
+        log::set_max_level(logger.filter());
+        self.run_on_init(logger);
+
+        Ok(())
+    }
+
+    /// Initializes the global logger with the built env logger, unless a
+    /// global logger has already been installed.
+    ///
+    /// This is useful when a library and the application using it might both
+    /// initialize logging. The first logger to be installed is kept, along
+    /// with its maximum log level.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// Builder::new().init_or_ignore();
+    ///
+    /// // The first logger is kept
+    /// Builder::new().init_or_ignore();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the target can't be written to.
+    pub fn init_or_ignore(&mut self) {
+        match self.checked_init() {
+            Err(ref err) if err.kind() == InitErrorKind::AlreadyInitialized => (),
+            result => result.expect("failed to initialize the logger"),
+        }
+    }
+
+    /// Initializes the global logger with the built env logger.
+    ///
+    /// This should be called early in the execution of a Rust program. Any log
+    /// events that occur before initialization will be ignored.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if it is called more than once, or if another
+    /// library has already initialized a global logger.
+    pub fn init(&mut self) {
+        self.try_init().unwrap();
+    }
+
+    /// Build a logger that writes records to the given writer.
+    ///
+    /// The returned [`WriteLogger`] isn't installed as the global logger.
+    /// Records are filtered and formatted using this builder's configuration
+    /// each time they're passed to [`WriteLogger::log_to`]. This is useful for
+    /// testing formats, replaying logs, or formatting records offline.
+    ///
+    /// There's no terminal to detect color support on, so styles are only
+    /// written if the write style is `WriteStyle::Always`.
+    ///
+    /// # Examples
+    ///
+    /// Format a record into an in-memory buffer:
+    ///
+    /// ```
+    /// extern crate log;
+    /// extern crate env_logger;
+    ///
+    /// use std::io::Write;
+    /// use log::{Level, Record};
+    /// use env_logger::Builder;
+    ///
+    /// fn main() {
+    ///     let mut out = Vec::new();
+    ///
+    ///     Builder::new()
+    ///         .format(|buf, record| writeln!(buf, "{}: {}", record.level(), record.args()))
+    ///         .write(&mut out)
+    ///         .log_to(&Record::builder()
+    ///             .level(Level::Error)
+    ///             .args(format_args!("a message"))
+    ///             .build())
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(b"ERROR: a message\n", &out[..]);
+    /// }
+    /// ```
+    ///
+    /// [`WriteLogger`]: struct.WriteLogger.html
+    /// [`WriteLogger::log_to`]: struct.WriteLogger.html#method.log_to
+    pub fn write<'a>(&mut self, out: &'a mut Write) -> WriteLogger<'a> {
+        let write_style = if self.format_has_styles() {
+            self.writer.get_write_style()
+        }
+        else {
+            WriteStyle::Never
+        };
+
+        let mut formatter = Formatter::in_memory(write_style);
+        formatter.set_level_color(self.level_color.clone());
+        formatter.set_clock(self.clock.clone());
+        formatter.set_prints(true);
+
+        WriteLogger {
+            out: out,
+            formatter: formatter,
+            filter: self.build_filter(),
+            filter_fn: self.filter_fn.clone(),
+            format: self.format.build(),
+        }
+    }
+
+    /// Build an env logger.
+    /// 
+    /// The returned [`Logger`] isn't installed as the global logger.
+    /// 
+    /// # Panics
+    /// 
+    /// This method panics if the target can't be written to. Use [`try_build`]
+    /// to handle the error instead.
+    /// 
+    /// [`Logger`]: struct.Logger.html
+    /// [`try_build`]: #method.try_build
+    pub fn build(&mut self) -> Logger {
+        self.try_build().expect("failed to build the logger")
+    }
+
+    /// Build an env logger, returning an error if the target can't be
+    /// written to.
+    /// 
+    /// This lets applications detect a target that doesn't work when the
+    /// logger is built, rather than losing records when they're written.
+    /// The builder's target is moved into the logger even if building fails,
+    /// so set a new one, like `Target::Stderr`, before trying again.
+    /// 
+    /// # Examples
+    /// 
+    /// Fall back to stderr if the configured target can't be used:
+    /// 
+    /// ```
+    /// use env_logger::{Builder, Target};
+    /// 
+    /// let mut builder = Builder::new();
+    /// builder.target(Target::Stdout);
+    /// 
+    /// let logger = match builder.try_build() {
+    ///     Ok(logger) => logger,
+    ///     Err(_) => builder.target(Target::Stderr).build(),
+    /// };
+    /// ```
+    pub fn try_build(&mut self) -> io::Result<Logger> {
+        self.try_build_styled(true)
+    }
+
+    /// Build an env logger that never writes styles unless `styles` is set.
+    pub(crate) fn try_build_styled(&mut self, styles: bool) -> io::Result<Logger> {
+        let dedup = if self.deduplicate {
+            Some(dedup::Dedup::new())
+        }
+        else {
+            None
+        };
+
+        // Styles are turned off even for targets with their own style choice
+        let styles = styles && self.format_has_styles();
+
+        Ok(Logger {
+            writer: self.writer.try_build_styled(styles)?,
+            filter: Arc::new(RwLock::new(self.build_filter())),
+            format: self.format.build(),
+            level_color: self.level_color.clone(),
+            clock: self.clock.clone(),
+            filter_fn: self.filter_fn.clone(),
+            dedup: dedup,
+            error_policy: self.error_policy,
+            installed: Arc::new(AtomicBool::new(false)),
+            static_max_level: self.filter.get_static_max_level(),
+            filter_error: self.filter_error.clone(),
+        })
+    }
+
+    fn build_filter(&mut self) -> filter::Filter {
+        for err in self.filter.parse_errors() {
+            match self.filter_error {
+                Some(ref filter_error) => filter_error(err),
+                None => eprintln!("warning: {}, ignoring it", err),
+            }
+        }
+
+        self.filter.build()
+    }
+
+    // Some formats can't contain control characters, so styles are turned off.
+    fn format_has_styles(&self) -> bool {
+        !self.format.sd_daemon
+    }
+
+    fn run_on_init(&mut self, logger: &Logger) {
+        if let Some(on_init) = self.on_init.take() {
+            on_init(logger);
+        }
+    }
+}
+
+impl Logger {
+    /// Returns the maximum `LevelFilter` that this env logger instance is
+    /// configured to output.
+    pub fn filter(&self) -> LevelFilter {
+        self.read_filter().filter()
+    }
+
+    /// Checks if this record matches the configured filter.
+    pub fn matches(&self, record: &Record) -> bool {
+        self.read_filter().matches(record) && filter_fn_enabled(&self.filter_fn, record.metadata())
+    }
+
+    /// Get a handle that can replace this logger's filter.
+    ///
+    /// The handle can be kept after the logger is installed as the global
+    /// logger.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate log;
+    /// # extern crate env_logger;
+    /// use log::LevelFilter;
+    /// use env_logger::Builder;
+    ///
+    /// # fn main() {
+    /// let logger = Builder::new().parse("info").build();
+    /// let handle = logger.filter_handle();
+    ///
+    /// handle.reload("warn");
+    ///
+    /// assert_eq!(LevelFilter::Warn, logger.filter());
+    /// # }
+    /// ```
+    pub fn filter_handle(&self) -> FilterHandle {
+        FilterHandle {
+            filter: self.filter.clone(),
+            installed: self.installed.clone(),
+            static_max_level: self.static_max_level,
+            filter_error: self.filter_error.clone(),
+        }
+    }
+
+    /// Write any records this logger is holding on to, then flush the target.
+    ///
+    /// This prints the summary of a repeated record when [`deduplicate`] is
+    /// enabled, and waits for queued records to be printed when they're
+    /// printed on a background thread. `Log::flush` calls this method, so
+    /// the global logger can be flushed with `log::logger().flush()`. Call
+    /// it before the program exits, like from a panic hook or the `Drop`
+    /// implementation of a guard in `main`, so no records are lost.
+    ///
+    /// # Examples
+    ///
+    /// Flush the global logger when the program panics:
+    ///
+    /// ```
+    /// # extern crate log;
+    /// # extern crate env_logger;
+    /// use std::panic;
+    ///
+    /// # fn main() {
+    /// env_logger::init();
+    ///
+    /// let hook = panic::take_hook();
+    ///
+    /// panic::set_hook(Box::new(move |info| {
+    ///     hook(info);
+    ///     log::logger().flush();
+    /// }));
+    /// # }
+    /// ```
+    ///
+    /// [`deduplicate`]: struct.Builder.html#method.deduplicate
+    pub fn flush(&self) {
+        if let Some(ref dedup) = self.dedup {
+            dedup.flush(|record| self.print(record));
+        }
+
+        if let Err(err) = self.writer.flush() {
+            self.write_error(&err, None);
+        }
+    }
+
+    /// Returns the module directives in effect for this logger.
+    ///
+    /// This reflects the directives parsed from the environment or given to
+    /// the [`Builder`], along with any filter set through a [`FilterHandle`]
+    /// since. It's useful for showing how logging is configured.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use env_logger::Builder;
+    ///
+    /// let logger = Builder::new().parse("warn,my_app::net=debug").build();
+    ///
+    /// for directive in logger.directives() {
+    ///     println!("{}", directive);
+    /// }
+    /// ```
+    ///
+    /// [`Builder`]: struct.Builder.html
+    /// [`FilterHandle`]: struct.FilterHandle.html
+    pub fn directives(&self) -> Vec<filter::Directive> {
+        self.read_filter().directives().to_vec()
+    }
+
+    fn read_filter(&self) -> ::std::sync::RwLockReadGuard<filter::Filter> {
+        self.filter.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Format a record into a `String` using the configured format.
+    ///
+    /// The record isn't checked against the filter and isn't printed.
+    /// Styles are only included if the write style is `Always`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate log;
+    /// # extern crate env_logger;
+    /// use std::io::Write;
+    /// use log::{Level, Record};
+    /// use env_logger::Builder;
+    ///
+    /// # fn main() {
+    /// let logger = Builder::new()
+    ///     .format(|buf, record| writeln!(buf, "{}: {}", record.level(), record.args()))
+    ///     .build();
+    ///
+    /// let formatted = logger.format_to_string(&Record::builder()
+    ///     .level(Level::Warn)
+    ///     .args(format_args!("a message"))
+    ///     .build())
+    ///     .unwrap();
+    ///
+    /// assert_eq!("WARN: a message\n", formatted);
+    /// # }
+    /// ```
+    pub fn format_to_string(&self, record: &Record) -> io::Result<String> {
+        let mut formatter = Formatter::in_memory(self.writer.write_style_for(record.target()));
+        formatter.set_level_color(self.level_color.clone());
+        formatter.set_clock(self.clock.clone());
+        formatter.set_previous_record(self.writer.previous_record());
+
+        (self.format)(&mut formatter, record)?;
+
+        let mut out = Vec::new();
+        formatter.write_to(&mut out)?;
+
+        String::from_utf8(out).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn print(&self, record: &Record) {
+        // Log records are written to a thread-local buffer before being printed
+        // to the terminal. We clear these buffers afterwards, but they aren't shrinked
+        // so will always at least have capacity for the largest log record formatted
+        // on that thread. That means records don't allocate a new buffer each time
+        // they're printed.
+        // 
+        // If multiple `Logger`s are used by the same threads then the thread-local
+        // formatter might have different color support. If this is the case the
+        // formatter and its buffer are discarded and recreated.
+        // 
+        // If a record is logged while formatting another one on the same thread,
+        // or while the thread-local buffer is being destroyed, then the buffer
+        // isn't available and the record is formatted in a new one instead.
+
+        // Don't bother formatting records that can't be printed
+        if self.writer.is_closed(record.level()) {
+            return;
+        }
+
+        thread_local! {
+            static FORMATTER: RefCell<Option<Formatter>> = RefCell::new(None);
+        }
+
+        let printed = FORMATTER.try_with(|tl_buf| {
+            let mut tl_buf = match tl_buf.try_borrow_mut() {
+                Ok(tl_buf) => tl_buf,
+                Err(_) => return false,
+            };
+
+            // Check the buffer style. If it's different from the logger's 
+            // style then drop the buffer and recreate it.
+            match *tl_buf {
+                Some(ref mut formatter) => {
+                    if !formatter.matches(&self.writer, record.level(), record.target()) {
+                        *formatter = Formatter::new(&self.writer, record.level(), record.target())
+                    }
+                },
+                ref mut tl_buf => *tl_buf = Some(Formatter::new(&self.writer, record.level(), record.target()))
+            }
+
+            // The format is guaranteed to be `Some` by this point
+            let formatter = tl_buf.as_mut().unwrap();
+
+            self.print_with(formatter, record);
+
+            true
+        });
+
+        if printed != Ok(true) {
+            self.print_with(&mut Formatter::new(&self.writer, record.level(), record.target()), record);
+        }
+    }
+
+    fn print_with(&self, formatter: &mut Formatter, record: &Record) {
+        // The formatter may be shared with other loggers on this thread
+        formatter.set_level_color(self.level_color.clone());
+        formatter.set_clock(self.clock.clone());
+        formatter.set_previous_record(self.writer.previous_record());
+
+        // Records can be put back in the order they were logged before they're printed
+        let logged_at = Instant::now();
+
+        let print = (self.format)(formatter, record).and_then(|_| formatter.print_at(&self.writer, record.level(), logged_at));
+
+        if let Err(err) = print {
+            self.write_error(&err, Some(record));
+        }
+
+        if self.writer.should_flush(record.level()) {
+            if let Err(err) = self.writer.flush() {
+                self.write_error(&err, None);
+            }
+        }
+
+        // Always clear the buffer afterwards
+        formatter.clear();
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.read_filter().enabled(metadata) && filter_fn_enabled(&self.filter_fn, metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.matches(record) {
+            match self.dedup {
+                Some(ref dedup) => dedup.log(record, |record| self.print(record)),
+                None => self.print(record),
+            }
+        }
+    }
+
+    fn flush(&self) {
+        Logger::flush(self)
+    }
+}
+
+/// Install `logger` as the global logger.
+///
+/// The installed logger is returned so it can be passed to an `on_init`
+/// function.
+fn install(logger: Logger) -> Result<&'static Logger, SetLoggerError> {
+    // The logger is leaked even if another logger is already installed
+    let logger: &'static Logger = Box::leak(Box::new(logger));
+
+    log::set_logger(logger)?;
+
+    logger.installed.store(true, Ordering::SeqCst);
+
+    #[cfg(feature = "background")]
+    {
+        if logger.writer.has_worker() {
+            flush_at_exit();
+        }
+    }
+
+    Ok(logger)
+}
+
+/// Print the records that are still queued on a background thread when the
+/// process exits.
+///
+/// The global logger is never dropped, so its background thread would
+/// otherwise be stopped with records still queued.
+#[cfg(all(feature = "background", any(unix, windows)))]
+fn flush_at_exit() {
+    extern "C" fn flush() {
+        log::logger().flush();
+    }
+
+    // If the hook can't be registered, queued records are lost at exit
+    unsafe {
+        libc::atexit(flush);
+    }
+}
+
+#[cfg(all(feature = "background", not(any(unix, windows))))]
+fn flush_at_exit() {}
+
+impl Logger {
+    /// Handle an error formatting or printing `record`, or flushing the
+    /// target if there's no record.
+    fn write_error(&self, err: &io::Error, record: Option<&Record>) {
+        if report_write_error(self.error_policy, &mut io::stderr(), err, record) {
+            process::abort();
+        }
+    }
+}
+
+/// Report an error logging `record` to `out` according to `policy`.
+///
+/// Returns whether or not the process should be aborted.
+fn report_write_error(policy: fmt::ErrorPolicy, out: &mut Write, err: &io::Error, record: Option<&Record>) -> bool {
+    match policy {
+        fmt::ErrorPolicy::Ignore => false,
+        fmt::ErrorPolicy::Stderr => {
+            let _ = write_error_report(out, err, record);
+            false
+        },
+        fmt::ErrorPolicy::Abort => {
+            let _ = write_error_report(out, err, record);
+            true
+        },
+    }
+}
+
+/// Describe an error logging `record` to `out`.
+///
+/// The record is written without the logger's format, since that may be
+/// what failed.
+fn write_error_report(out: &mut Write, err: &io::Error, record: Option<&Record>) -> io::Result<()> {
+    match record {
+        Some(record) => {
+            writeln!(out, "env_logger: failed to log a record: {}", err)?;
+            writeln!(out, "[{} {}] {}", record.level(), record.target(), record.args())
+        },
+        None => writeln!(out, "env_logger: failed to flush the logger: {}", err),
+    }
+}
+
+/// Whether or not an optional filter function allows records with `metadata`.
+fn filter_fn_enabled(filter_fn: &Option<Arc<FilterFn>>, metadata: &Metadata) -> bool {
+    match *filter_fn {
+        Some(ref filter_fn) => filter_fn(metadata),
+        None => true,
+    }
+}
+
+impl<'a> WriteLogger<'a> {
+    /// Filter and format a record, then write it to the underlying writer.
+    ///
+    /// Records that don't match the configured filter are ignored.
+    pub fn log_to(&mut self, record: &Record) -> io::Result<()> {
+        if !self.filter.matches(record) || !filter_fn_enabled(&self.filter_fn, record.metadata()) {
+            return Ok(());
+        }
+
+        let formatter = &mut self.formatter;
+        let out = &mut self.out;
+
+        let write = (self.format)(formatter, record).and_then(|_| formatter.write_to(*out));
+
+        // Always clear the buffer afterwards
+        formatter.clear();
+
+        write
+    }
+}
+
+impl FilterHandle {
+    /// Replace the logger's filter.
+    ///
+    /// If the logger is the global logger, the `log` crate's maximum level is
+    /// set to the new filter's level.
+    pub fn set_filter(&self, filter: filter::Filter) {
+        let level = filter.filter();
+
+        *self.filter.write().unwrap_or_else(|e| e.into_inner()) = filter;
+
+        if self.installed.load(Ordering::SeqCst) {
+            log::set_max_level(level);
+        }
+    }
+
+    /// Replace the logger's filter with directives in the same form as the
+    /// `RUST_LOG` environment variable.
+    ///
+    /// The directives are parsed with the logger builder's
+    /// [`static_max_level`] choice. Parts of them that can't be parsed are
+    /// ignored and returned, so they can be reported, and are passed to the
+    /// builder's [`on_filter_error`] function if there is one.
+    ///
+    /// [`static_max_level`]: struct.Builder.html#method.static_max_level
+    /// [`on_filter_error`]: struct.Builder.html#method.on_filter_error
+    pub fn reload(&self, filters: &str) -> Vec<filter::ParseError> {
+        let mut builder = filter::Builder::new();
+        builder.static_max_level(self.static_max_level);
+        builder.parse(filters);
+
+        let errors = builder.parse_errors().to_vec();
+
+        if let Some(ref filter_error) = self.filter_error {
+            for err in &errors {
+                filter_error(err);
+            }
+        }
+
+        self.set_filter(builder.build());
+
+        errors
+    }
+}
+
 impl<'a> Env<'a> {
     /// Get a default set of environment variables.
     pub fn new() -> Self {
@@ -546,6 +3133,64 @@ impl<'a> Default for Env<'a> {
     }
 }
 
+/// An error initializing the global logger.
+///
+/// It's returned by [`Builder::checked_init`].
+///
+/// [`Builder::checked_init`]: struct.Builder.html#method.checked_init
+#[derive(Debug)]
+pub struct InitError {
+    kind: InitErrorKind,
+    reason: Option<io::Error>,
+}
+
+/// The kind of an [`InitError`].
+///
+/// [`InitError`]: struct.InitError.html
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum InitErrorKind {
+    /// A global logger has already been installed, either by an earlier call
+    /// or by another library.
+    AlreadyInitialized,
+    /// The logger couldn't be built because its target can't be written to.
+    Build,
+}
+
+impl InitError {
+    fn new(kind: InitErrorKind) -> Self {
+        InitError {
+            kind: kind,
+            reason: None,
+        }
+    }
+
+    fn build(reason: io::Error) -> Self {
+        InitError {
+            kind: InitErrorKind::Build,
+            reason: Some(reason),
+        }
+    }
+
+    /// The kind of error.
+    pub fn kind(&self) -> InitErrorKind {
+        self.kind
+    }
+}
+
+impl Error for InitError {
+    fn description(&self) -> &str {
+        "failed to initialize the logger"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        self.reason.as_ref().map(|reason| reason as &Error)
+    }
+
+    fn source(&self) -> Option<&(Error + 'static)> {
+        self.reason.as_ref().map(|reason| reason as &(Error + 'static))
+    }
+}
+
 mod std_fmt_impls {
     use std::fmt;
     use super::*;
@@ -558,11 +3203,44 @@ mod std_fmt_impls {
         }
     }
 
+    impl fmt::Display for InitError {
+        fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
+            match self.kind {
+                InitErrorKind::AlreadyInitialized => write!(f, "a global logger has already been initialized"),
+                InitErrorKind::Build => write!(f, "failed to build the logger"),
+            }?;
+
+            if let Some(ref reason) = self.reason {
+                write!(f, ": {}", reason)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    impl fmt::Debug for FilterHandle{
+        fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
+            f.debug_struct("FilterHandle")
+                .field("filter", &self.filter)
+                .finish()
+        }
+    }
+
+    impl<'a> fmt::Debug for WriteLogger<'a>{
+        fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
+            f.debug_struct("WriteLogger")
+                .field("filter", &self.filter)
+                .finish()
+        }
+    }
+
     impl fmt::Debug for Builder{
         fn fmt(&self, f: &mut fmt::Formatter)->fmt::Result {
             f.debug_struct("Logger")
             .field("filter", &self.filter)
             .field("writer", &self.writer)
+            .field("deduplicate", &self.deduplicate)
+            .field("error_policy", &self.error_policy)
             .finish()
         }
     }
@@ -661,3 +3339,194 @@ where
 {
     try_init_from_env(env).unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use testing::SharedBuf;
+
+    fn log(logger: &Logger, message: &str) {
+        logger.log(&Record::builder()
+            .level(log::Level::Error)
+            .args(format_args!("{}", message))
+            .build());
+    }
+
+    #[test]
+    fn flush_writes_pending_records() {
+        let pipe = SharedBuf::default();
+
+        let mut builder = Builder::new();
+
+        builder
+            .format(|buf, record| writeln!(buf, "{}", record.args()))
+            .target(Target::Pipe(Box::new(pipe.clone())))
+            .deduplicate(true);
+
+        #[cfg(feature = "background")]
+        builder.background(16, fmt::Overflow::Block);
+
+        let logger = builder.build();
+
+        log(&logger, "a");
+        log(&logger, "a");
+        log(&logger, "a");
+
+        logger.flush();
+
+        assert_eq!("a\nlast message repeated 2 times\n", pipe.written());
+    }
+
+    #[test]
+    fn sd_daemon_ignores_style_overrides() {
+        let pipe = SharedBuf::default();
+
+        let logger = Builder::new()
+            .format_sd_daemon(true)
+            .color_choice(fmt::ColorChoice::AlwaysAnsi)
+            .target_write_style("app", fmt::WriteStyle::Always)
+            .target(Target::Pipe(Box::new(pipe.clone())))
+            .build();
+
+        logger.log(&Record::builder()
+            .level(log::Level::Error)
+            .target("app")
+            .args(format_args!("disk full"))
+            .build());
+
+        let written = pipe.written();
+
+        assert!(written.starts_with("<3>"), "{:?}", written);
+        assert!(!written.contains('\x1b'), "{:?}", written);
+    }
+
+    #[test]
+    fn sd_daemon_leaves_builder_style() {
+        let mut builder = Builder::new();
+
+        builder
+            .format_sd_daemon(true)
+            .write_style(fmt::WriteStyle::Always)
+            .target(Target::Null);
+
+        assert_eq!(fmt::WriteStyle::Never, builder.build().writer.write_style());
+        assert_eq!(fmt::WriteStyle::Always, builder.writer.get_write_style());
+
+        builder.format_sd_daemon(false);
+
+        assert_eq!(fmt::WriteStyle::Always, builder.build().writer.write_style());
+    }
+
+    #[test]
+    fn filter_handle_not_installed() {
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let collected = errors.clone();
+
+        let logger = Builder::new()
+            .parse("info")
+            .static_max_level(true)
+            .on_filter_error(move |err| collected.lock().unwrap().push(err.to_string()))
+            .target(Target::Null)
+            .build();
+
+        let handle = logger.filter_handle();
+        let max_level = log::max_level();
+
+        assert_eq!(1, handle.reload("debug,myapp=ifno").len());
+
+        // The logger isn't the global logger, so its maximum level is left alone
+        assert_eq!(LevelFilter::Debug, logger.filter());
+        assert_eq!(max_level, log::max_level());
+
+        assert!(handle.static_max_level);
+        assert_eq!(vec!["unknown log level 'ifno'"], *errors.lock().unwrap());
+    }
+
+    #[test]
+    fn filter_fn_after_directives() {
+        let inputs = vec![
+            ("error", "myapp::auth", true),
+            ("error", "myapp::net", false),
+            ("off", "myapp::auth", false),
+        ];
+
+        for (directives, target, expected) in inputs {
+            let logger = Builder::new()
+                .parse(directives)
+                .filter_fn(|metadata| metadata.target().ends_with("auth"))
+                .target(Target::Null)
+                .build();
+
+            let record = Record::builder()
+                .level(log::Level::Error)
+                .target(target)
+                .build();
+
+            assert_eq!(expected, logger.enabled(record.metadata()));
+            assert_eq!(expected, logger.matches(&record));
+        }
+    }
+
+    #[test]
+    fn write_error_report_describes_record() {
+        let err = io::Error::new(io::ErrorKind::Other, "disk full");
+
+        let record = Record::builder()
+            .level(log::Level::Warn)
+            .target("my_app")
+            .args(format_args!("a message"))
+            .build();
+
+        let inputs = vec![
+            (Some(&record), "env_logger: failed to log a record: disk full\n[WARN my_app] a message\n"),
+            (None, "env_logger: failed to flush the logger: disk full\n"),
+        ];
+
+        for (record, expected) in inputs {
+            let mut out = Vec::new();
+            write_error_report(&mut out, &err, record).unwrap();
+
+            assert_eq!(expected, String::from_utf8(out).unwrap());
+        }
+    }
+
+    #[test]
+    fn report_write_error_by_policy() {
+        let err = io::Error::new(io::ErrorKind::Other, "disk full");
+
+        let record = Record::builder()
+            .level(log::Level::Warn)
+            .target("my_app")
+            .args(format_args!("a message"))
+            .build();
+
+        let report = "env_logger: failed to log a record: disk full\n[WARN my_app] a message\n";
+
+        let inputs = vec![
+            (fmt::ErrorPolicy::Ignore, "", false),
+            (fmt::ErrorPolicy::Stderr, report, false),
+            (fmt::ErrorPolicy::Abort, report, true),
+        ];
+
+        for (policy, expected_report, expected_abort) in inputs {
+            let mut out = Vec::new();
+            let abort = report_write_error(policy, &mut out, &err, Some(&record));
+
+            assert_eq!(expected_report, String::from_utf8(out).unwrap());
+            assert_eq!(expected_abort, abort);
+        }
+    }
+
+    #[test]
+    fn init_error_source() {
+        let err = InitError::build(io::Error::new(io::ErrorKind::NotFound, "no such file"));
+
+        assert_eq!("failed to build the logger: no such file", err.to_string());
+        assert_eq!(Some("no such file".to_owned()), err.source().map(|source| source.to_string()));
+
+        let err = InitError::new(InitErrorKind::AlreadyInitialized);
+
+        assert!(err.source().is_none());
+    }
+}
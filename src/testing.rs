@@ -0,0 +1,32 @@
+//! Helpers shared by the unit tests.
+
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+use fmt::Formatter;
+
+/// A pipe that can be cloned to read back what was written to it.
+#[derive(Clone, Default)]
+pub(crate) struct SharedBuf(pub(crate) Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuf {
+    /// Everything written to the pipe so far.
+    pub(crate) fn written(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl Write for SharedBuf {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Everything written to a formatter so far.
+pub(crate) fn written(buf: &Formatter) -> String {
+    String::from_utf8(buf.buffer_bytes().to_vec()).expect("formatted record isn't utf8")
+}
@@ -0,0 +1,69 @@
+#[macro_use] extern crate log;
+extern crate env_logger;
+
+use std::process;
+use std::env;
+use std::str;
+use std::time::Duration;
+
+use env_logger::{Builder, Target};
+use env_logger::fmt::Overflow;
+
+const RECORDS: usize = 1000;
+
+fn main() {
+    match env::var("LOG_BACKGROUND_EXIT_TEST").ok() {
+        Some(ref mode) if mode == "background" => child_main(|builder| {
+            builder.background(RECORDS, Overflow::Block);
+        }),
+        Some(ref mode) if mode == "reorder" => child_main(|builder| {
+            builder.reorder_window(Duration::from_secs(60), RECORDS);
+        }),
+        Some(ref mode) if mode == "exit" => {
+            child_main(|builder| {
+                builder.background(RECORDS, Overflow::Block);
+            });
+            process::exit(0);
+        },
+        _ => parent_main(),
+    }
+}
+
+fn child_main<F: FnOnce(&mut Builder)>(configure: F) {
+    let mut builder = Builder::new();
+
+    builder.target(Target::Stdout)
+        .parse("info")
+        .format(|buf, record| {
+            use std::io::Write;
+            writeln!(buf, "{}", record.args())
+        });
+
+    configure(&mut builder);
+    builder.init();
+
+    for i in 0..RECORDS {
+        info!("record {}", i);
+    }
+}
+
+fn run_child(mode: &str) -> String {
+    let exe = env::current_exe().unwrap();
+    let out = process::Command::new(exe)
+        .env("LOG_BACKGROUND_EXIT_TEST", mode)
+        .output()
+        .unwrap_or_else(|e| panic!("Unable to start child process: {}", e));
+
+    assert!(out.status.success());
+
+    String::from_utf8(out.stdout).unwrap()
+}
+
+fn parent_main() {
+    let expected = (0..RECORDS).map(|i| format!("record {}\n", i)).collect::<String>();
+
+    // Records still queued when the process exits are printed
+    for mode in &["background", "reorder", "exit"] {
+        assert_eq!(expected, run_child(mode), "records were lost in {} mode", mode);
+    }
+}
@@ -0,0 +1,48 @@
+#[macro_use] extern crate log;
+extern crate env_logger;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use log::LevelFilter;
+use env_logger::{Builder, InitErrorKind};
+
+static ON_INIT: AtomicUsize = AtomicUsize::new(0);
+
+fn main() {
+    Builder::new()
+        .filter(None, LevelFilter::Warn)
+        .on_init(|logger| {
+            assert_eq!(LevelFilter::Warn, logger.filter());
+            assert_eq!(LevelFilter::Warn, log::max_level());
+
+            ON_INIT.fetch_add(1, Ordering::SeqCst);
+        })
+        .checked_init()
+        .unwrap();
+
+    assert_eq!(1, ON_INIT.load(Ordering::SeqCst));
+
+    // The function isn't called if the logger can't be installed
+    let err = Builder::new()
+        .filter(None, LevelFilter::Trace)
+        .on_init(|_| {
+            ON_INIT.fetch_add(1, Ordering::SeqCst);
+        })
+        .checked_init()
+        .unwrap_err();
+
+    assert_eq!(1, ON_INIT.load(Ordering::SeqCst));
+
+    assert_eq!(InitErrorKind::AlreadyInitialized, err.kind());
+
+    // The installed logger keeps its level
+    assert_eq!(LevelFilter::Warn, log::max_level());
+
+    Builder::new()
+        .filter(None, LevelFilter::Trace)
+        .init_or_ignore();
+
+    assert_eq!(LevelFilter::Warn, log::max_level());
+
+    warn!("still logging");
+}
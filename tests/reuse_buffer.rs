@@ -0,0 +1,97 @@
+#[macro_use] extern crate log;
+extern crate env_logger;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io::{self, Write};
+use std::str;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use log::LevelFilter;
+use env_logger::Builder;
+use env_logger::fmt::Target;
+
+/// An allocator that counts the allocations made through it.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+/// A pipe that only keeps what's written to it while `keep` is set.
+#[derive(Clone)]
+struct Pipe {
+    keep: Arc<Mutex<bool>>,
+    written: Arc<Mutex<Vec<u8>>>,
+}
+
+impl Write for Pipe {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if *self.keep.lock().unwrap() {
+            self.written.lock().unwrap().extend_from_slice(buf);
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    let pipe = Pipe {
+        keep: Arc::new(Mutex::new(false)),
+        written: Arc::new(Mutex::new(Vec::new())),
+    };
+
+    Builder::new()
+        .filter(None, LevelFilter::Info)
+        .format(|buf, record| {
+            // Log from within the format to check records can be nested
+            if record.target() == "outer" {
+                info!(target: "inner", "nested {}", record.args());
+            }
+
+            writeln!(buf, "{}: {}", record.target(), record.args())
+        })
+        .target(Target::Pipe(Box::new(pipe.clone())))
+        .init();
+
+    // The first record creates the thread-local buffer
+    info!(target: "bench", "warming up the buffer");
+
+    let records = 1000;
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+
+    for i in 0..records {
+        info!(target: "bench", "record {}", i);
+    }
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    println!("{} allocations for {} records", allocations, records);
+
+    // Records reuse the buffer instead of allocating a new one each time
+    assert!(allocations < records / 10, "{} allocations for {} records", allocations, records);
+
+    *pipe.keep.lock().unwrap() = true;
+
+    info!(target: "outer", "message");
+
+    let written = pipe.written.lock().unwrap();
+
+    assert_eq!("inner: nested message\nouter: message\n", str::from_utf8(&written).unwrap());
+}